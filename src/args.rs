@@ -0,0 +1,1775 @@
+use std::env;
+use std::io::BufRead;
+use std::path::PathBuf;
+use std::time::Duration;
+
+use open_share::{
+    AddressFamily, ConnectionScope, ConnectionSort, CredentialSource, DEFAULT_CONNECT_FLAGS, DEFAULT_DRIVE_POOL,
+    DEFAULT_DRIVE_WAIT, DEFAULT_ENUMERATION_BUFFER_KIB, EnumerationOptions, server_root, ShareRequest,
+    UncPath,
+};
+use windows::Win32::NetworkManagement::WNet::{
+    CONNECT_INTERACTIVE, CONNECT_PROMPT, CONNECT_TEMPORARY, CONNECT_UPDATE_PROFILE, NET_CONNECT_FLAGS,
+};
+use windows::Win32::UI::WindowsAndMessaging::{
+    SHOW_WINDOW_CMD, SW_SHOWMAXIMIZED, SW_SHOWMINIMIZED, SW_SHOWNORMAL,
+};
+
+#[cfg(feature = "credential-manager")]
+use crate::credential_blob::decode_credential_blob;
+use crate::net_use;
+
+
+pub(crate) const USAGE_OPTIONS: &str = "\
+Options:
+  --password PASSWORD   connect using this password instead of prompting
+  --smartcard           authenticate with the certificate on the inserted
+                        smartcard instead of a USERNAME (which is then omitted);
+                        Windows prompts for the PIN
+  --credential-blob BLOB
+                        use the credential marshalled by CredMarshalCredentialW,
+                        encoded as Base64 of its UTF-8 text, instead of a
+                        USERNAME (which is then omitted)
+  --guest               connect as guest (an empty username and password)
+                        without prompting, instead of a USERNAME (which is then
+                        omitted); note that current versions of Windows refuse
+                        guest access unless \"Enable insecure guest logons\" is
+                        allowed by policy, and many servers reject it as well
+  --username-from-current
+                        connect as the logged-in user with their existing logon
+                        session (e.g. Kerberos ticket) instead of a USERNAME
+                        (which is then omitted); unlike an empty USERNAME, no
+                        username or password is passed at all
+  --auth                for a PATH naming a server (\\\\server) rather than a
+                        share, first connect to its IPC$ share using the
+                        credentials, so that Explorer lists its shares as that
+                        user; otherwise, such a PATH is opened without
+                        connecting anything, listing the shares as the current
+                        user (the USERNAME is then unused)
+  --drive X:            map the share to this drive letter and open the drive
+                        (only with a single PATH); \"auto\" picks the first free
+                        letter of --drive-pool
+  --drive-pool LETTERS  the letters that --drive auto picks from, in order (e.g.
+                        PQR; default: Z down to D)
+  --force               if the drive is mapped to another share, disconnect it
+                        first (otherwise, fail naming that share)
+  --drive-wait MS       wait up to this long for a newly mapped drive to appear
+                        before opening it, falling back to PATH (default: 5000,
+                        0 to open immediately)
+  --open-timeout MS     fail (exit code 4) if opening a connected share takes
+                        longer than this; bounds the --drive-wait wait and the
+                        --properties dialog wait, but not connecting
+  --persistent          restore the mapping at next logon (requires --drive)
+  --register-only       only register the mapping to be restored at next logon,
+                        without connecting or opening the share now (requires
+                        --drive; written to HKEY_CURRENT_USER\\Network\\X)
+  --ephemeral           keep running after opening the shares and disconnect
+                        those that were not connected before once Enter is
+                        pressed
+  --disconnect-after SECONDS
+                        keep running after opening the shares and disconnect
+                        those that were not connected before once SECONDS have
+                        passed (or earlier if Enter is pressed during an
+                        --ephemeral session); ending the process early skips
+                        the disconnect unless --ephemeral is also given, in
+                        which case closing the window still disconnects
+  --keepalive SECONDS   during an --ephemeral or --disconnect-after session,
+                        check the connections at this interval and reconnect
+                        those that have dropped
+  --post-disconnect COMMAND
+                        run COMMAND after each share has been disconnected at
+                        the end of an --ephemeral or --disconnect-after session
+                        and wait for it; a failure is reported (exit code 1);
+                        OPEN_SHARE_PATH and OPEN_SHARE_DRIVE describe the share
+  --no-prompt           never show a credential prompt
+  --clear-cached-credentials
+                        before connecting, remove the credentials stored in the
+                        Credential Manager for the server and disconnect all
+                        connections to it (e.g. after a password change)
+  --credential-target NAME
+                        the target name under which the credentials are stored
+                        in the Credential Manager, if not the server name (e.g.
+                        a wildcard target such as *.corp.example); used by
+                        --clear-cached-credentials and to report that stored
+                        credentials are used, whereas Windows itself picks the
+                        most specific entry matching the server when connecting
+  --modern-prompt       ask for credentials using the modern credential dialog
+                        instead of the one shown by WNetAddConnection2W
+  --prompt-title TEXT   the caption of the modern credential dialog (default:
+                        \"Connect to SHARE\"; requires --modern-prompt, as the
+                        other dialog cannot be customized)
+  --prompt-message TEXT the message of the modern credential dialog (default:
+                        \"Enter your credentials for PATH\"; requires
+                        --modern-prompt)
+  --raw-flags FLAGS     OR these NET_CONNECT_FLAGS (decimal or 0x hex) into the
+                        flags derived from the options above
+  --raw-flags-exact     use --raw-flags as the complete flags, overriding
+                        --persistent and --no-prompt
+  --provider NAME       use this network provider (as listed by --list-providers,
+                        e.g. \"Microsoft Windows Network\")
+  --comment TEXT        store this comment with the connection; {user}, {host}
+                        and {date} are replaced by the current user, computer
+                        name and date, {{ and }} by literal braces
+  --comment-from-env VAR
+                        take the comment (including tokens) from the
+                        environment variable VAR
+  --verb VERB           open the share using this shell verb (default: open)
+  --properties          show the properties dialog of the share (e.g. to inspect
+                        its permissions) instead of opening it; waits until the
+                        dialog is closed
+  --terminal            open a terminal in the share instead of opening it:
+                        Windows Terminal if wt.exe is on the PATH, otherwise the
+                        Command Prompt (which maps a temporary drive for a UNC
+                        path)
+  --with PROGRAM        open the share using PROGRAM (e.g. another file manager)
+                        instead of the shell
+  --open-with-args ARGS the arguments for the --with PROGRAM; the quoted path
+                        replaces the placeholder {} if given (at most once, and
+                        not within quotes), otherwise it is appended
+  --only-if-missing     connect as usual, but do not open the share if an
+                        Explorer window already shows it (found using the
+                        shell's IShellWindows COM collection; if that cannot be
+                        enumerated, the share is opened anyway)
+  --window STATE        show the opened window normal, minimized or maximized
+  --open-in-browser     the PATHs are http:// or https:// URLs (e.g. of WebDAV
+                        folders) to open in the default browser instead of
+                        mounting them; no USERNAME is given and nothing is
+                        connected, so --drive and the other connection options
+                        are rejected
+  --admin-fallback      if the server reports that a PATH's share does not exist,
+                        connect to the same folder through the administrative
+                        share C$ instead (\\\\server\\data\\x becomes
+                        \\\\server\\C$\\data\\x) and log the substitution; only
+                        administrators of the server can do this, so pass the
+                        credentials of one, and use it deliberately, as the
+                        share's permissions are bypassed
+  --admin-fallback-dir DIR
+                        the directory on drive C containing the folder of the
+                        share for --admin-fallback (default: the root)
+  --check-only          only check the health of each PATH instead of connecting
+                        and opening it: whether it is connected and whether it
+                        is online or only available from the Offline Files
+                        cache because its server cannot be reached; prints a
+                        line per PATH and exits with 1 unless all are online
+  --skip-check          connect without checking whether the share is connected
+                        already (faster with many connections); a drive that is
+                        mapped to the share already counts as connected
+  --assume-connected    open the share right away without checking whether it is
+                        connected; only if that fails with \"file not found\",
+                        \"path not found\" or \"access denied\" is it connected
+                        and opened again (if the current logon can access PATH
+                        already, USERNAME is not used; not with --terminal,
+                        --properties or --parallel)
+  --heal-stale          if the share is connected already, access it first to
+                        revive a connection that went stale (e.g. after resuming
+                        from hibernation) and reconnect it if that fails; with
+                        --drive, a drive remembered for the share but shown as
+                        disconnected is revived as well
+  --reuse-any           if this session has any connection to the server of
+                        PATH, even one made by another user (e.g. a service
+                        account), open PATH through it instead of connecting;
+                        PATH is then accessed with that user's rights, and
+                        USERNAME and the password are ignored (not with --drive)
+  --max-connections N   refuse to connect (exit code 3) if N or more disk
+                        connections exist already
+  --parallel N          connect to up to N shares on different servers
+                        concurrently (default: 1); shares are still opened
+                        in order
+  --continue-on-error   keep processing the remaining PATHs after one has failed
+                        (otherwise, they are skipped)
+  --tabs                open the second and subsequent PATHs as tabs of the first
+                        PATH's Explorer window (Windows 11 24H2 or later)
+  --open-delay MS       pause this long before opening each PATH after the first,
+                        so that Explorer keeps up with many windows (default: 0);
+                        only delays opening, not connecting
+  --long-names          once connected, replace 8.3 short names (e.g. PROJEC~1) in
+                        PATH by the long names before opening and reporting it;
+                        PATH must exist and be accessible, otherwise it is kept
+  --use-fqdn            replace the server name of each PATH with its fully
+                        qualified name from DNS before connecting, so that
+                        Kerberos finds its service principal; follows DNS
+                        aliases (not for clustered names), IP addresses are kept
+  --connect-as-system   do not warn when running as SYSTEM (e.g. as a service or
+                        scheduled task), where shares are accessed using the
+                        computer account unless credentials are given
+  --diagnose            explain logon failures (IP vs. host name, Kerberos tickets)
+  --quiet-success       write nothing to stderr unless something fails, in which
+                        case all messages are written (--output json still
+                        prints its summary)
+  --gui-errors          show fatal errors in a message box even if a console is
+                        attached (always done without a console)
+  --no-color            never color the output (also if NO_COLOR is set)
+  --log-file FILE       append the outcome to this log file
+                        (default: %LOCALAPPDATA%\\open-share\\log)
+  --dump-netresource    print the arguments passed to WNetAddConnection2W
+                        (except the password) before connecting
+  --print-env           print what was resolved for each PATH (paths, server and
+                        share, drive, user name and where it comes from, flags
+                        and provider) as KEY=VALUE lines to stderr before doing
+                        anything, with secrets redacted
+  --enum-buffer-kib N   enumerate the existing connections into a buffer of N KiB
+                        at first (1 to 1024, default: 16); it grows if needed
+  --scope SCOPE         which connections are enumerated when checking whether a
+                        share is connected already, counting them for
+                        --max-connections and finding the holder of a --drive:
+                        \"connected\" (the default; those established now) or
+                        \"remembered\" (those restored at logon, so that a
+                        remembered mapping counts as connected even if it is
+                        not established); connecting is unaffected, as Windows
+                        ignores the scope there (see --persistent)
+  --warn-redirection    warn if a folder of your profile (e.g. Documents) is
+                        redirected to the share of a PATH, as connecting it
+                        again may interfere with Folder Redirection; only
+                        informational
+  --max-enum-time MS    stop enumerating the existing connections after MS
+                        milliseconds with a warning; a share not found by then
+                        counts as not connected
+  --dump-enum-raw       print every NETRESOURCEW encountered while enumerating
+                        the existing connections
+  --show-space          print the free and total space of each opened share
+  --require-write       after connecting each PATH, create and delete an empty
+                        file in the root of its share; if that fails, it is not
+                        opened but stays connected (exit code 8)
+  --toast               also show the outcome as a desktop notification (which
+                        delays exiting by a few seconds)
+  --copy-in FILE        after connecting each PATH, copy the local FILE into the
+                        root of its share (in addition to opening it),
+                        reporting the progress; an existing file of the same
+                        name is kept and copying fails unless --copy-overwrite
+                        is given
+  --copy-overwrite      let --copy-in replace an existing file of the same name
+  --write-drive-to FILE once the share has been mapped, write its drive letter
+                        (e.g. X:, useful with --drive auto) to FILE, replacing
+                        it atomically; a failure to write is only a warning
+  --pre-connect COMMAND run COMMAND for each PATH before anything is connected
+                        (e.g. to start a VPN) and wait for it; if it fails for
+                        any PATH, nothing is connected; OPEN_SHARE_PATH and
+                        (with --drive) OPEN_SHARE_DRIVE describe the share
+  --on-success COMMAND  run COMMAND for each share that was connected and opened;
+                        OPEN_SHARE_PATH, OPEN_SHARE_OPENED_PATH and (with
+                        --drive) OPEN_SHARE_DRIVE describe the share
+  --wait-on-success     wait for the --on-success COMMAND to finish and fail if
+                        it does
+  --output FORMAT       print a summary to stdout as text (nothing) or json
+  --json-errors         report each failed PATH on stderr as a single JSON object
+                        (phase, win32_code, symbolic_name, message, path,
+                        exit_code, hints) instead of text; like --output json,
+                        this skips the \"press Enter\" prompt on failure
+  --home                connect to and open the current user's home directory
+                        share instead of PATHs: HOMESHARE and HOMEPATH if set,
+                        otherwise the home directory of the account as stored on
+                        LOGONSERVER; only an optional USERNAME may be given
+  --stdin-kv            read the PATHs and credentials from stdin instead of the
+                        command line, one KEY=VALUE per line: path=PATH
+                        (repeatable), user=USERNAME and password=PASSWORD
+  --no-strict           treat unknown options as PATHs instead of failing
+  --                    treat all following arguments as PATHs and USERNAME,
+                        even if they start with a dash
+  --capabilities        print the supported subcommands and options as JSON
+  --print-unc NAME      print the UNC path of NAME, a drive letter (e.g. X:) or a
+                        UNC path, to stdout without connecting; exit with 1 if
+                        NAME is not mapped
+
+Ensure options:
+  --password PASSWORD, --persistent, --no-prompt, --provider NAME
+                        as above
+
+Disconnect options:
+  --force               disconnect even if files are open on the connection
+  --dry-run             only list the connections that would be removed; exit
+                        with 0 if there are any, 1 otherwise
+  --verify              check afterwards that the connections are gone, retrying
+                        once with --force if not; exit with 1 if any remain
+
+Selftest options:
+  --prefer-ipv4         only probe SERVER's IPv4 addresses
+  --prefer-ipv6         only probe SERVER's IPv6 addresses
+                        (neither affects the address Windows uses for SMB)
+
+net use syntax:
+  Arguments in the syntax of `net use` (e.g. X: \\\\server\\share
+  /user:DOMAIN\\me PASSWORD /persistent:yes) are recognized if the first is a
+  DRIVE or * (a free drive) or if a /SWITCH is given, and are translated into
+  the options above. Supported are a DRIVE, the PATH, a PASSWORD (* prompts),
+  /USER, /PERSISTENT, /SMARTCARD and /DELETE with /Y (abbreviations such as /u:
+  work). Unlike `net use`, the share is opened after connecting and the other
+  options cannot be mixed in; /SAVECRED, /HOME, listing and /DELETE with * are
+  not supported.
+
+Exit codes:
+  0                     success
+  1                     failure
+  2                     failure because the Workstation service is not running
+  3                     refused to connect because of --max-connections
+  4                     opening a share exceeded --open-timeout
+  5                     partial success: some PATHs were opened, others failed
+                        or were skipped (the other codes only apply if none was
+                        opened)
+  6                     the server has reached its connection limit
+  7                     the drive is not a valid drive specification
+  8                     a share was connected, but --require-write could not
+                        write to it
+  9                     the server accepted the credentials, but has no share
+                        of that name (the shares it offers are listed)
+  64                    invalid arguments (all problems are listed; nothing has
+                        been connected)";
+
+
+/// The smallest initial enumeration buffer accepted by `--enum-buffer-kib`.
+const MIN_ENUM_BUFFER_KIB: usize = 1;
+
+/// The largest initial enumeration buffer accepted by `--enum-buffer-kib`.
+const MAX_ENUM_BUFFER_KIB: usize = 1024;
+
+/// The options understood when opening shares.
+pub(crate) const OPEN_OPTIONS: &[&str] = &[
+    "--password", "--drive", "--drive-pool", "--force", "--drive-wait", "--open-timeout",
+    "--persistent", "--register-only", "--ephemeral", "--disconnect-after", "--keepalive",
+    "--post-disconnect", "--no-prompt", "--clear-cached-credentials", "--credential-target",
+    "--raw-flags", "--raw-flags-exact", "--provider", "--comment", "--comment-from-env", "--verb",
+    "--properties", "--terminal", "--with", "--open-with-args", "--only-if-missing", "--window",
+    "--open-in-browser", "--check-only", "--admin-fallback", "--admin-fallback-dir", "--smartcard",
+    "--credential-blob", "--guest", "--username-from-current", "--auth", "--skip-check",
+    "--assume-connected", "--heal-stale", "--reuse-any", "--max-connections", "--parallel",
+    "--continue-on-error", "--tabs", "--open-delay", "--long-names", "--connect-as-system",
+    "--diagnose", "--gui-errors", "--quiet-success", "--log-file", "--show-space",
+    "--require-write", "--toast", "--copy-in", "--copy-overwrite", "--write-drive-to",
+    "--pre-connect", "--on-success", "--wait-on-success", "--output", "--json-errors",
+    "--dump-netresource", "--print-env", "--dump-enum-raw", "--enum-buffer-kib", "--scope",
+    "--warn-redirection", "--max-enum-time", "--home", "--stdin-kv", "--no-strict", "--no-color",
+];
+
+/// The subcommands and the options each of them understands.
+pub(crate) const SUBCOMMANDS: &[(&str, &[&str])] = &[
+    ("selftest", &["--prefer-ipv4", "--prefer-ipv6", "--no-color"]),
+    ("ensure", ENSURE_OPTIONS),
+    ("disconnect", DISCONNECT_OPTIONS),
+    ("disconnect-server", DISCONNECT_SERVER_OPTIONS),
+    ("browse", BROWSE_OPTIONS),
+    ("list", LIST_OPTIONS),
+    ("export-config", EXPORT_CONFIG_OPTIONS),
+];
+
+/// The options understood by the `list` subcommand.
+const LIST_OPTIONS: &[&str] = &["--sort", "--output", "--scope", "--no-color"];
+
+/// The options understood by the `export-config` subcommand.
+const EXPORT_CONFIG_OPTIONS: &[&str] = &["--no-color"];
+
+/// The options understood by the `browse` subcommand.
+const BROWSE_OPTIONS: &[&str] = &["--pick", "--no-color"];
+
+/// The options understood by the `disconnect` subcommand.
+const DISCONNECT_OPTIONS: &[&str] = &["--force", "--dry-run", "--verify", "--post-disconnect", "--no-color"];
+
+/// The options understood by the `disconnect-server` subcommand.
+const DISCONNECT_SERVER_OPTIONS: &[&str] = &["--force", "--no-color"];
+
+/// The options understood by the `ensure` subcommand.
+const ENSURE_OPTIONS: &[&str] = &[
+    "--password", "--persistent", "--no-prompt", "--provider", "--no-color",
+];
+
+
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub(crate) enum OutputFormat {
+    #[default]
+    Text,
+    Json,
+}
+
+
+#[derive(Clone, Debug)]
+pub(crate) enum Command {
+    /// Connect to and open one or more shares.
+    Open(Box<Args>),
+
+    /// Check whether the environment is able to connect to shares.
+    SelfTest { server: Option<String>, family: AddressFamily },
+
+    /// Make sure that a drive is mapped to a share.
+    Ensure(Box<ShareRequest>),
+
+    /// Remove the connections to a share or drive.
+    Disconnect { name: String, force: bool, dry_run: bool, verify: bool, post_disconnect: Option<String> },
+
+    /// Remove all connections to the shares of a server.
+    DisconnectServer { server: String, force: bool },
+
+    /// Print the supported subcommands and options.
+    Capabilities,
+
+    /// Print the UNC path of a drive or UNC path.
+    PrintUnc { name: String },
+
+    /// List the installed network providers in their order.
+    ListProviders { output: OutputFormat },
+
+    /// List the existing connections.
+    List { sort: ConnectionSort, output: OutputFormat, scope: ConnectionScope },
+
+    /// List the shares of a server, optionally picking one to open.
+    Browse { server: String, pick: bool },
+
+    /// Write the existing connections to a configuration file.
+    ExportConfig { file: PathBuf },
+}
+
+
+#[derive(Clone, Debug)]
+pub(crate) struct Args {
+    pub paths: Vec<String>,
+    pub username: Option<String>,
+    pub password: Option<String>,
+    pub drive: Option<String>,
+    pub force: bool,
+    pub drive_wait: Option<Duration>,
+    pub open_timeout: Option<Duration>,
+    pub persistent: bool,
+    pub register_only: bool,
+    pub ephemeral: bool,
+    pub disconnect_after: Option<Duration>,
+    pub keepalive: Option<Duration>,
+    pub post_disconnect: Option<String>,
+    pub no_prompt: bool,
+    pub clear_cached_credentials: bool,
+    pub credential_target: Option<String>,
+    pub modern_prompt: bool,
+    pub prompt_title: Option<String>,
+    pub prompt_message: Option<String>,
+    pub raw_flags: Option<u32>,
+    pub raw_flags_exact: bool,
+    pub provider: Option<String>,
+    pub comment: Option<String>,
+    pub verb: Option<String>,
+    pub properties: bool,
+    pub terminal: bool,
+    pub open_with: Option<String>,
+    pub open_with_args: Option<String>,
+    pub only_if_missing: bool,
+    pub auth: bool,
+    pub window: SHOW_WINDOW_CMD,
+    pub open_in_browser: bool,
+    pub check_only: bool,
+    pub admin_fallback: bool,
+    pub admin_fallback_dir: Option<String>,
+    pub log_file: Option<PathBuf>,
+    pub copy_in: Option<PathBuf>,
+    pub write_drive_to: Option<PathBuf>,
+    pub copy_overwrite: bool,
+    pub show_space: bool,
+    pub toast: bool,
+    pub pre_connect: Option<String>,
+    pub on_success: Option<String>,
+    pub wait_on_success: bool,
+    pub diagnose: bool,
+    pub connect_as_system: bool,
+    pub use_fqdn: bool,
+    pub long_names: bool,
+    pub tabs: bool,
+    pub open_delay: Duration,
+    pub continue_on_error: bool,
+    pub parallel: usize,
+    pub skip_check: bool,
+    pub assume_connected: bool,
+    pub heal_stale: bool,
+    pub reuse_any: bool,
+    pub require_write: bool,
+    pub max_connections: Option<usize>,
+    pub output: OutputFormat,
+    pub json_errors: bool,
+    pub smartcard: bool,
+    pub guest: bool,
+    pub credentials_from_stdin: bool,
+    pub dump_netresource: bool,
+    pub print_env: bool,
+    pub dump_enum_raw: bool,
+    pub enum_buffer_kib: usize,
+    pub scope: ConnectionScope,
+    pub warn_redirection: bool,
+    pub max_enum_time: Option<Duration>,
+}
+impl Args {
+    fn flags(&self) -> NET_CONNECT_FLAGS {
+        let mut flags = connect_flags(self.persistent, self.no_prompt || self.guest);
+        if let Some(raw) = self.raw_flags {
+            if self.raw_flags_exact {
+                flags.0 = raw;
+            } else {
+                flags.0 |= raw;
+            }
+        }
+        flags
+    }
+
+    /// How the existing connections are enumerated for the requests.
+    fn enumeration(&self) -> EnumerationOptions {
+        EnumerationOptions {
+            dump: self.dump_enum_raw,
+            buffer_kib: self.enum_buffer_kib,
+            max_time: self.max_enum_time,
+            scope: self.scope,
+        }
+    }
+
+    /// Builds a request for each of the given paths, which are [`Args::paths`] after any
+    /// rewriting (e.g. for `--fqdn`).
+    pub fn to_share_requests(&self, paths: &[String]) -> Vec<ShareRequest> {
+        paths.iter()
+            .map(|p| self.to_share_request(p))
+            .collect()
+    }
+
+    fn to_share_request(&self, path: &str) -> ShareRequest {
+        let mut builder = ShareRequest::builder(path)
+            .flags(self.flags())
+            .window(self.window)
+            .drive_wait(self.drive_wait)
+            .open_timeout(self.open_timeout)
+            .smartcard(self.smartcard)
+            .dump_netresource(self.dump_netresource)
+            .enumeration(self.enumeration())
+            .query_space(self.show_space)
+            .properties(self.properties)
+            .terminal(self.terminal)
+            .force(self.force)
+            .modern_prompt(self.modern_prompt)
+            .clear_cached_credentials(self.clear_cached_credentials)
+            .skip_check(self.skip_check)
+            .assume_connected(self.assume_connected)
+            .heal_stale(self.heal_stale)
+            .reuse_any(self.reuse_any)
+            .require_write(self.require_write)
+            .resolve_long_names(self.long_names)
+            .admin_fallback(self.admin_fallback)
+            .only_if_missing(self.only_if_missing)
+            .authenticate_server(self.auth)
+            .max_connections(self.max_connections);
+        if let Some(username) = &self.username {
+            builder = builder.username(username.clone());
+        }
+        if let Some(password) = &self.password {
+            builder = builder.password(password.clone());
+        }
+        if let Some(program) = &self.open_with {
+            builder = builder.open_with(program.clone());
+        }
+        if let Some(args) = &self.open_with_args {
+            builder = builder.open_with_args(args.clone());
+        }
+        if let Some(target) = &self.credential_target {
+            builder = builder.credential_target(target.clone());
+        }
+        if let Some(directory) = &self.admin_fallback_dir {
+            builder = builder.admin_fallback_dir(directory.clone());
+        }
+        if self.credentials_from_stdin {
+            builder = builder.credential_source(CredentialSource::Stdin);
+        }
+        if self.guest {
+            // empty (rather than missing) credentials request guest access
+            builder = builder.username("").password("");
+        }
+        if let Some(drive) = &self.drive {
+            builder = builder.drive(drive.clone());
+        }
+        if let Some(provider) = &self.provider {
+            builder = builder.provider(provider.clone());
+        }
+        if let Some(comment) = &self.comment {
+            builder = builder.comment(comment.clone());
+        }
+        if let Some(verb) = &self.verb {
+            builder = builder.verb(verb.clone());
+        }
+        if let Some(title) = &self.prompt_title {
+            builder = builder.prompt_title(title.clone());
+        }
+        if let Some(message) = &self.prompt_message {
+            builder = builder.prompt_message(message.clone());
+        }
+        builder.build()
+    }
+}
+
+
+fn connect_flags(persistent: bool, no_prompt: bool) -> NET_CONNECT_FLAGS {
+    let mut flags = DEFAULT_CONNECT_FLAGS;
+    if persistent {
+        flags.0 &= !CONNECT_TEMPORARY.0;
+        flags.0 |= CONNECT_UPDATE_PROFILE.0;
+    }
+    if no_prompt {
+        flags.0 &= !(CONNECT_INTERACTIVE.0 | CONNECT_PROMPT.0);
+    }
+    flags
+}
+
+
+fn parse_window(value: &str) -> Result<SHOW_WINDOW_CMD, String> {
+    match value {
+        "normal" => Ok(SW_SHOWNORMAL),
+        "minimized" => Ok(SW_SHOWMINIMIZED),
+        "maximized" => Ok(SW_SHOWMAXIMIZED),
+        other => Err(format!("unknown window state {:?}", other)),
+    }
+}
+
+fn parse_output(value: &str) -> Result<OutputFormat, String> {
+    match value {
+        "text" => Ok(OutputFormat::Text),
+        "json" => Ok(OutputFormat::Json),
+        other => Err(format!("unknown output format {:?}", other)),
+    }
+}
+
+fn parse_flags_value(value: &str) -> Result<u32, String> {
+    let parsed = match value.strip_prefix("0x").or_else(|| value.strip_prefix("0X")) {
+        Some(hex) => u32::from_str_radix(hex, 16),
+        None => value.parse(),
+    };
+    parsed.map_err(|_| format!("invalid flags value {:?}; expected a decimal or 0x-prefixed hexadecimal number", value))
+}
+
+/// Checks the usage of the placeholder `{}` in the arguments of `--open-with-args`.
+fn check_open_with_args(args: &str) -> Result<(), String> {
+    if args.matches("{}").count() > 1 {
+        return Err(format!("--open-with-args: the placeholder {{}} may only be given once in {:?}", args));
+    }
+    if args.contains("\"{}\"") {
+        return Err(format!("--open-with-args: the path is quoted already; use {{}} instead of \"{{}}\" in {:?}", args));
+    }
+    Ok(())
+}
+
+fn parse_millis(name: &str, value: &str) -> Result<Duration, String> {
+    value.parse()
+        .map(Duration::from_millis)
+        .map_err(|_| format!("{} expects a number of milliseconds, not {:?}", name, value))
+}
+
+fn parse_seconds(name: &str, value: &str) -> Result<Duration, String> {
+    match value.parse() {
+        Ok(seconds) if seconds > 0 => Ok(Duration::from_secs(seconds)),
+        _ => Err(format!("{} expects a positive number of seconds, not {:?}", name, value)),
+    }
+}
+
+fn parse_buffer_kib(name: &str, value: &str) -> Result<usize, String> {
+    match value.parse() {
+        Ok(kib) if (MIN_ENUM_BUFFER_KIB..=MAX_ENUM_BUFFER_KIB).contains(&kib) => Ok(kib),
+        _ => Err(format!(
+            "{} expects a number of KiB from {} to {}, not {:?}",
+            name, MIN_ENUM_BUFFER_KIB, MAX_ENUM_BUFFER_KIB, value,
+        )),
+    }
+}
+
+fn parse_count(name: &str, value: &str) -> Result<usize, String> {
+    match value.parse() {
+        Ok(n) if n > 0 => Ok(n),
+        _ => Err(format!("{} expects a positive number, not {:?}", name, value)),
+    }
+}
+
+/// The hyphen and the characters commonly substituted for it, such as the en dash inserted by word
+/// processors.
+const DASHES: [char; 5] = ['-', '\u{2010}', '\u{2013}', '\u{2014}', '\u{2212}'];
+
+/// The problem reported for an option whose cargo feature was disabled when building.
+fn not_compiled_in(option: &str, feature: &str) -> String {
+    format!("{}: feature not compiled in (this build lacks the {} feature)", option, feature)
+}
+
+#[cfg(not(feature = "credential-manager"))]
+fn decode_credential_blob(_blob: &str) -> Result<String, String> {
+    Err(not_compiled_in("--credential-blob", "credential-manager"))
+}
+
+/// Whether the path has the form `\\server\share`, optionally followed by more components.
+/// Forward slashes are accepted as separators.
+pub(crate) fn looks_like_unc_path(path: &str) -> bool {
+    UncPath::parse(&path.replace('/', "\\")).is_some()
+}
+
+/// Whether the path names a server (`\\server`) rather than a share.
+fn looks_like_server_root(path: &str) -> bool {
+    server_root(path).is_some()
+}
+
+/// Whether the path is an `http://` or `https://` URL with a host.
+pub(crate) fn looks_like_web_url(path: &str) -> bool {
+    let lower = path.to_ascii_lowercase();
+    ["http://", "https://"].iter()
+        .filter_map(|scheme| lower.strip_prefix(scheme))
+        .any(|rest| !rest.is_empty() && !rest.starts_with('/'))
+}
+
+/// Parses the letters of `--drive-pool`, which may be separated by commas (e.g. `PQR` or `P,Q,R`).
+fn parse_drive_pool(value: &str) -> Result<String, String> {
+    let mut pool = String::new();
+    for c in value.chars().filter(|c| *c != ',') {
+        let letter = c.to_ascii_uppercase();
+        if !letter.is_ascii_uppercase() {
+            return Err(format!("--drive-pool: {:?} is not a drive letter", c));
+        }
+        if pool.contains(letter) {
+            return Err(format!("--drive-pool: {} is given more than once", letter));
+        }
+        pool.push(letter);
+    }
+    if pool.is_empty() {
+        return Err("--drive-pool requires at least one letter".to_owned());
+    }
+    Ok(pool)
+}
+
+/// Whether the drive has the form `X:`.
+pub(crate) fn looks_like_drive(drive: &str) -> bool {
+    let mut chars = drive.chars();
+    matches!((chars.next(), chars.next(), chars.next()), (Some(letter), Some(':'), None) if letter.is_ascii_alphabetic())
+}
+
+/// Whether the argument starts with a dash, which makes it an option unless it follows `--`.
+fn looks_like_option(arg: &str) -> bool {
+    arg.chars().count() > 1 && arg.starts_with(DASHES)
+}
+
+fn edit_distance(a: &str, b: &str) -> usize {
+    let b_chars: Vec<char> = b.chars().collect();
+    let mut previous: Vec<usize> = (0..=b_chars.len()).collect();
+    for (i, a_char) in a.chars().enumerate() {
+        let mut current = vec![i + 1];
+        for (j, b_char) in b_chars.iter().enumerate() {
+            let substitution = previous[j] + if a_char == *b_char { 0 } else { 1 };
+            current.push(substitution.min(previous[j + 1] + 1).min(current[j] + 1));
+        }
+        previous = current;
+    }
+    previous[b_chars.len()]
+}
+
+/// Produces the error for an unknown option, suggesting the most similar known option if there is
+/// one that is reasonably close.
+fn unknown_option_error(arg: &str, known: &[&str]) -> String {
+    let dashes_fixed: String = arg.chars()
+        .map(|c| if DASHES.contains(&c) { '-' } else { c })
+        .collect();
+    let closest = known.iter()
+        .map(|k| (edit_distance(&dashes_fixed, k), *k))
+        .min();
+    match closest {
+        Some((distance, option)) if distance <= 3 => format!("unknown option {:?}; did you mean {}?", arg, option),
+        _ => format!("unknown option {:?}", arg),
+    }
+}
+
+/// Paths and credentials read using `--stdin-kv`.
+#[derive(Default)]
+struct StdinInput {
+    paths: Vec<String>,
+    username: Option<String>,
+    password: Option<String>,
+}
+
+fn read_stdin_kv() -> Result<StdinInput, String> {
+    let mut input = StdinInput::default();
+
+    let si = std::io::stdin();
+    let sil = si.lock();
+    for (index, line) in sil.lines().enumerate() {
+        let line = line
+            .map_err(|e| format!("failed to read stdin: {}", e))?;
+        let line = line.trim_end_matches('\r');
+        if line.trim().is_empty() {
+            continue;
+        }
+        let (key, value) = line.split_once('=')
+            .ok_or_else(|| format!("stdin line {} is not of the form KEY=VALUE", index + 1))?;
+        match key {
+            "path" => input.paths.push(value.to_owned()),
+            "user" => input.username = Some(value.to_owned()),
+            "password" => input.password = Some(value.to_owned()),
+            other => return Err(format!("unknown key {:?} on stdin line {}; expected path, user or password", other, index + 1)),
+        }
+    }
+    Ok(input)
+}
+
+fn parse_selftest_args(args: &[String]) -> Result<Command, String> {
+    let mut server = None;
+    let mut family = AddressFamily::Any;
+    let mut options_ended = false;
+    for arg in args {
+        match arg.as_str() {
+            "--prefer-ipv4" if !options_ended => family = AddressFamily::Ipv4,
+            "--prefer-ipv6" if !options_ended => family = AddressFamily::Ipv6,
+            "--no-color" if !options_ended => {}, // already handled before parsing
+            "--" if !options_ended => options_ended = true,
+            _ if !options_ended && looks_like_option(arg) => {
+                return Err(unknown_option_error(arg, SUBCOMMANDS[0].1));
+            },
+            _ => {
+                if server.is_some() {
+                    return Err("selftest takes at most one SERVER".to_owned());
+                }
+                server = Some(arg.clone());
+            },
+        }
+    }
+    Ok(Command::SelfTest { server, family })
+}
+
+fn parse_ensure_args(args: &[String]) -> Result<Command, String> {
+    let mut positional = Vec::new();
+    let mut password = None;
+    let mut persistent = false;
+    let mut no_prompt = false;
+    let mut provider = None;
+
+    let mut iter = args.iter();
+    while let Some(arg) = iter.next() {
+        if arg == "--" {
+            positional.extend(iter.by_ref().cloned());
+            break;
+        }
+
+        let mut value = |name: &str| iter.next()
+            .cloned()
+            .ok_or_else(|| format!("{} requires a value", name));
+        match arg.as_str() {
+            "--password" => password = Some(value(arg)?),
+            "--persistent" => persistent = true,
+            "--no-prompt" => no_prompt = true,
+            "--provider" => provider = Some(value(arg)?),
+            "--no-color" => {}, // already handled before parsing
+            _ if looks_like_option(arg) => return Err(unknown_option_error(arg, ENSURE_OPTIONS)),
+            _ => positional.push(arg.clone()),
+        }
+    }
+
+    if positional.len() < 2 || positional.len() > 3 {
+        return Err("ensure expects a PATH, a DRIVE and optionally a USERNAME".to_owned());
+    }
+    let mut builder = ShareRequest::builder(positional[0].clone())
+        .drive(positional[1].clone())
+        .flags(connect_flags(persistent, no_prompt));
+    if let Some(username) = positional.get(2) {
+        builder = builder.username(username.clone());
+    }
+    if let Some(password) = password {
+        builder = builder.password(password);
+    }
+    if let Some(provider) = provider {
+        builder = builder.provider(provider);
+    }
+    Ok(Command::Ensure(Box::new(builder.build())))
+}
+
+fn parse_disconnect_args(args: &[String]) -> Result<Command, String> {
+    let mut name = None;
+    let mut force = false;
+    let mut dry_run = false;
+    let mut verify = false;
+    let mut post_disconnect = None;
+    let mut options_ended = false;
+    let mut iter = args.iter();
+    while let Some(arg) = iter.next() {
+        match arg.as_str() {
+            "--force" if !options_ended => force = true,
+            "--dry-run" if !options_ended => dry_run = true,
+            "--verify" if !options_ended => verify = true,
+            "--post-disconnect" if !options_ended => {
+                let command = iter.next()
+                    .ok_or_else(|| format!("{} requires a value", arg))?;
+                post_disconnect = Some(command.clone());
+            },
+            "--no-color" if !options_ended => {}, // already handled before parsing
+            "--" if !options_ended => options_ended = true,
+            _ if !options_ended && looks_like_option(arg) => {
+                return Err(unknown_option_error(arg, DISCONNECT_OPTIONS));
+            },
+            _ => {
+                if name.is_some() {
+                    return Err("disconnect takes exactly one PATH or DRIVE".to_owned());
+                }
+                name = Some(arg.clone());
+            },
+        }
+    }
+    let name = name
+        .ok_or_else(|| "disconnect takes exactly one PATH or DRIVE".to_owned())?;
+    if dry_run && verify {
+        return Err("--dry-run and --verify cannot be combined".to_owned());
+    }
+    Ok(Command::Disconnect { name, force, dry_run, verify, post_disconnect })
+}
+
+fn parse_disconnect_server_args(args: &[String]) -> Result<Command, String> {
+    let mut server = None;
+    let mut force = false;
+    let mut options_ended = false;
+    for arg in args {
+        match arg.as_str() {
+            "--force" if !options_ended => force = true,
+            "--no-color" if !options_ended => {}, // already handled before parsing
+            "--" if !options_ended => options_ended = true,
+            _ if !options_ended && looks_like_option(arg) => {
+                return Err(unknown_option_error(arg, DISCONNECT_SERVER_OPTIONS));
+            },
+            _ => {
+                if server.is_some() {
+                    return Err("disconnect-server takes exactly one SERVER".to_owned());
+                }
+                server = Some(arg.clone());
+            },
+        }
+    }
+    let server = server
+        .ok_or_else(|| "disconnect-server takes exactly one SERVER".to_owned())?;
+    Ok(Command::DisconnectServer { server, force })
+}
+
+fn parse_sort(value: &str) -> Result<ConnectionSort, String> {
+    match value {
+        "remote" => Ok(ConnectionSort::Remote),
+        "local" => Ok(ConnectionSort::Local),
+        "provider" => Ok(ConnectionSort::Provider),
+        other => Err(format!("unknown sort key {:?}", other)),
+    }
+}
+
+fn parse_scope(value: &str) -> Result<ConnectionScope, String> {
+    match value {
+        "connected" => Ok(ConnectionScope::Connected),
+        "remembered" => Ok(ConnectionScope::Remembered),
+        "globalnet" => Ok(ConnectionScope::GlobalNet),
+        _ => Err(format!("--scope expects connected, remembered or globalnet, not {:?}", value)),
+    }
+}
+
+fn parse_list_args(args: &[String]) -> Result<Command, String> {
+    let mut sort = ConnectionSort::default();
+    let mut output = OutputFormat::Text;
+    let mut scope = ConnectionScope::default();
+    let mut iter = args.iter();
+    while let Some(arg) = iter.next() {
+        let mut value = |name: &str| iter.next()
+            .cloned()
+            .ok_or_else(|| format!("{} requires a value", name));
+        match arg.as_str() {
+            "--sort" => sort = parse_sort(&value(arg)?)?,
+            "--output" => output = parse_output(&value(arg)?)?,
+            "--scope" => scope = parse_scope(&value(arg)?)?,
+            "--no-color" => {}, // already handled before parsing
+            _ if looks_like_option(arg) => return Err(unknown_option_error(arg, LIST_OPTIONS)),
+            _ => return Err("list takes no arguments besides options".to_owned()),
+        }
+    }
+    Ok(Command::List { sort, output, scope })
+}
+
+fn parse_list_providers_args(args: &[String]) -> Result<Command, String> {
+    let mut output = OutputFormat::Text;
+    let mut iter = args.iter();
+    while let Some(arg) = iter.next() {
+        match arg.as_str() {
+            "--output" => {
+                let value = iter.next()
+                    .ok_or_else(|| "--output requires a value".to_owned())?;
+                output = parse_output(value)?;
+            },
+            "--json" => output = OutputFormat::Json,
+            "--no-color" => {}, // already handled before parsing
+            _ => return Err(format!("--list-providers takes no arguments besides --output or --json, not {:?}", arg)),
+        }
+    }
+    Ok(Command::ListProviders { output })
+}
+
+fn parse_browse_args(args: &[String]) -> Result<Command, String> {
+    let mut server = None;
+    let mut pick = false;
+    let mut options_ended = false;
+    for arg in args {
+        match arg.as_str() {
+            "--pick" if !options_ended => pick = true,
+            "--no-color" if !options_ended => {}, // already handled before parsing
+            "--" if !options_ended => options_ended = true,
+            _ if !options_ended && looks_like_option(arg) => {
+                return Err(unknown_option_error(arg, BROWSE_OPTIONS));
+            },
+            _ => {
+                if server.is_some() {
+                    return Err("browse takes exactly one SERVER".to_owned());
+                }
+                server = Some(arg.clone());
+            },
+        }
+    }
+    let server = server
+        .ok_or_else(|| "browse takes exactly one SERVER".to_owned())?;
+    Ok(Command::Browse { server, pick })
+}
+
+fn parse_export_config_args(args: &[String]) -> Result<Command, String> {
+    let mut file = None;
+    let mut options_ended = false;
+    for arg in args {
+        match arg.as_str() {
+            "--no-color" if !options_ended => {}, // already handled before parsing
+            "--" if !options_ended => options_ended = true,
+            _ if !options_ended && looks_like_option(arg) => {
+                return Err(unknown_option_error(arg, EXPORT_CONFIG_OPTIONS));
+            },
+            _ => {
+                if file.is_some() {
+                    return Err("export-config takes exactly one FILE".to_owned());
+                }
+                file = Some(PathBuf::from(arg));
+            },
+        }
+    }
+    let file = file
+        .ok_or_else(|| "export-config takes exactly one FILE".to_owned())?;
+    Ok(Command::ExportConfig { file })
+}
+
+/// Parses the command-line arguments, excluding the program name.
+pub(crate) fn parse_command(args: &[String]) -> Result<Command, String> {
+    match args.first().map(|a| a.as_str()) {
+        Some("selftest") => parse_selftest_args(&args[1..]),
+        Some("ensure") => parse_ensure_args(&args[1..]),
+        Some("disconnect") => parse_disconnect_args(&args[1..]),
+        Some("disconnect-server") => parse_disconnect_server_args(&args[1..]),
+        Some("browse") => parse_browse_args(&args[1..]),
+        Some("list") => parse_list_args(&args[1..]),
+        Some("export-config") => parse_export_config_args(&args[1..]),
+        Some("--capabilities") => Ok(Command::Capabilities),
+        Some("--list-providers") => parse_list_providers_args(&args[1..]),
+        Some("--print-unc") => match &args[1..] {
+            [name] => Ok(Command::PrintUnc { name: name.clone() }),
+            _ => Err("--print-unc expects exactly one NAME".to_owned()),
+        },
+        _ if net_use::looks_like_net_use(args) => match net_use::translate(args)? {
+            translated if translated[0] == "disconnect" => parse_disconnect_args(&translated[1..]),
+            translated => Ok(Command::Open(Box::new(parse_args(&translated)?))),
+        },
+        _ => Ok(Command::Open(Box::new(parse_args(args)?))),
+    }
+}
+
+fn parse_args(args: &[String]) -> Result<Args, String> {
+    let mut positional = Vec::new();
+    let mut password = None;
+    let mut drive = None;
+    let mut drive_pool = None;
+    let mut force = false;
+    let mut drive_wait = Some(DEFAULT_DRIVE_WAIT);
+    let mut open_timeout = None;
+    let mut persistent = false;
+    let mut register_only = false;
+    let mut ephemeral = false;
+    let mut disconnect_after = None;
+    let mut keepalive = None;
+    let mut post_disconnect = None;
+    let mut no_prompt = false;
+    let mut clear_cached_credentials = false;
+    let mut credential_target = None;
+    let mut modern_prompt = false;
+    let mut prompt_title = None;
+    let mut prompt_message = None;
+    let mut raw_flags = None;
+    let mut raw_flags_exact = false;
+    let mut provider = None;
+    let mut comment = None;
+    let mut verb = None;
+    let mut properties = false;
+    let mut terminal = false;
+    let mut open_with = None;
+    let mut open_with_args = None;
+    let mut only_if_missing = false;
+    let mut auth = false;
+    let mut window = SW_SHOWNORMAL;
+    let mut open_in_browser = false;
+    let mut check_only = false;
+    let mut admin_fallback = false;
+    let mut admin_fallback_dir = None;
+    let mut log_file = None;
+    let mut copy_in: Option<PathBuf> = None;
+    let mut write_drive_to = None;
+    let mut copy_overwrite = false;
+    let mut show_space = false;
+    let mut toast = false;
+    let mut pre_connect = None;
+    let mut on_success = None;
+    let mut wait_on_success = false;
+    let mut diagnose = false;
+    let mut connect_as_system = false;
+    let mut use_fqdn = false;
+    let mut long_names = false;
+    let mut tabs = false;
+    let mut open_delay = Duration::ZERO;
+    let mut continue_on_error = false;
+    let mut parallel = 1;
+    let mut skip_check = false;
+    let mut assume_connected = false;
+    let mut heal_stale = false;
+    let mut reuse_any = false;
+    let mut require_write = false;
+    let mut max_connections = None;
+    let mut output = OutputFormat::Text;
+    let mut json_errors = false;
+    let mut smartcard = false;
+    let mut username_from_current = false;
+    let mut credential_blob = None;
+    let mut guest = false;
+    let mut dump_netresource = false;
+    let mut print_env = false;
+    let mut dump_enum_raw = false;
+    let mut enum_buffer_kib = DEFAULT_ENUMERATION_BUFFER_KIB;
+    let mut scope = ConnectionScope::default();
+    let mut warn_redirection = false;
+    let mut max_enum_time = None;
+    let mut strict = true;
+    let mut stdin_kv = false;
+    let mut home = false;
+    let mut unknown_options = Vec::new();
+
+    // collect all problems instead of stopping at the first so that they can be fixed in one go
+    let mut problems = Vec::new();
+    let mut iter = args.iter();
+    while let Some(arg) = iter.next() {
+        if arg == "--" {
+            positional.extend(iter.by_ref().cloned());
+            break;
+        }
+
+        // a missing or invalid value is reported with the other problems; the option stays unset
+        let mut parse_option = || -> Result<(), String> {
+            let mut value = |name: &str| iter.next()
+                .cloned()
+                .ok_or_else(|| format!("{} requires a value", name));
+            match arg.as_str() {
+                "--password" => password = Some(value(arg)?),
+                "--drive" => drive = Some(value(arg)?),
+                "--drive-pool" => drive_pool = Some(parse_drive_pool(&value(arg)?)?),
+                "--force" => force = true,
+                "--drive-wait" => {
+                    let wait = parse_millis(arg, &value(arg)?)?;
+                    drive_wait = if wait.is_zero() { None } else { Some(wait) };
+                },
+                "--open-timeout" => {
+                    let timeout = parse_millis(arg, &value(arg)?)?;
+                    if timeout.is_zero() {
+                        return Err(format!("{} expects a positive number of milliseconds", arg));
+                    }
+                    open_timeout = Some(timeout);
+                },
+                "--persistent" => persistent = true,
+                "--register-only" => register_only = true,
+                "--ephemeral" => ephemeral = true,
+                "--disconnect-after" => disconnect_after = Some(parse_seconds(arg, &value(arg)?)?),
+                "--keepalive" => keepalive = Some(parse_seconds(arg, &value(arg)?)?),
+                "--post-disconnect" => post_disconnect = Some(value(arg)?),
+                "--no-prompt" => no_prompt = true,
+                "--clear-cached-credentials" => clear_cached_credentials = true,
+                "--credential-target" => credential_target = Some(value(arg)?),
+                "--modern-prompt" => modern_prompt = true,
+                "--prompt-title" => prompt_title = Some(value(arg)?),
+                "--prompt-message" => prompt_message = Some(value(arg)?),
+                "--raw-flags" => raw_flags = Some(parse_flags_value(&value(arg)?)?),
+                "--raw-flags-exact" => raw_flags_exact = true,
+                "--provider" => provider = Some(value(arg)?),
+                "--comment" => comment = Some(value(arg)?),
+                "--comment-from-env" => {
+                    let var_name = value(arg)?;
+                    let var_value = env::var(&var_name)
+                        .map_err(|e| format!("cannot read comment from environment variable {:?}: {}", var_name, e))?;
+                    comment = Some(var_value);
+                },
+                "--verb" => verb = Some(value(arg)?),
+                "--properties" => properties = true,
+                "--terminal" => terminal = true,
+                "--with" => open_with = Some(value(arg)?),
+                "--open-with-args" => open_with_args = Some(value(arg)?),
+                "--only-if-missing" => only_if_missing = true,
+                "--auth" => auth = true,
+                "--window" => window = parse_window(&value(arg)?)?,
+                "--open-in-browser" => open_in_browser = true,
+                "--check-only" => check_only = true,
+                "--admin-fallback" => admin_fallback = true,
+                "--admin-fallback-dir" => admin_fallback_dir = Some(value(arg)?),
+                "--log-file" => log_file = Some(PathBuf::from(value(arg)?)),
+                "--show-space" => show_space = true,
+                "--require-write" => require_write = true,
+                "--toast" => toast = true,
+                "--copy-in" => copy_in = Some(PathBuf::from(value(arg)?)),
+                "--write-drive-to" => write_drive_to = Some(PathBuf::from(value(arg)?)),
+                "--copy-overwrite" => copy_overwrite = true,
+                "--pre-connect" => pre_connect = Some(value(arg)?),
+                "--on-success" => on_success = Some(value(arg)?),
+                "--wait-on-success" => wait_on_success = true,
+                "--diagnose" => diagnose = true,
+                "--connect-as-system" => connect_as_system = true,
+                "--use-fqdn" => use_fqdn = true,
+                "--long-names" => long_names = true,
+                "--tabs" => tabs = true,
+                "--open-delay" => open_delay = parse_millis(arg, &value(arg)?)?,
+                "--continue-on-error" => continue_on_error = true,
+                "--skip-check" => skip_check = true,
+                "--assume-connected" => assume_connected = true,
+                "--heal-stale" => heal_stale = true,
+                "--reuse-any" => reuse_any = true,
+                "--max-connections" => max_connections = Some(parse_count(arg, &value(arg)?)?),
+                "--parallel" => parallel = parse_count(arg, &value(arg)?)?,
+                "--gui-errors" => {}, // already handled before parsing
+                "--quiet-success" => {}, // ditto
+                "--no-color" => {}, // ditto
+                "--output" => output = parse_output(&value(arg)?)?,
+                "--json-errors" => json_errors = true,
+                "--smartcard" => smartcard = true,
+                "--username-from-current" => username_from_current = true,
+                "--guest" => guest = true,
+                "--credential-blob" => credential_blob = Some(decode_credential_blob(&value(arg)?)?),
+                "--dump-netresource" => dump_netresource = true,
+                "--print-env" => print_env = true,
+                "--dump-enum-raw" => dump_enum_raw = true,
+                "--enum-buffer-kib" => enum_buffer_kib = parse_buffer_kib(arg, &value(arg)?)?,
+                "--scope" => scope = parse_scope(&value(arg)?)?,
+                "--warn-redirection" => warn_redirection = true,
+                "--max-enum-time" => {
+                    let limit = parse_millis(arg, &value(arg)?)?;
+                    if limit.is_zero() {
+                        return Err(format!("{} expects a positive number of milliseconds", arg));
+                    }
+                    max_enum_time = Some(limit);
+                },
+                "--no-strict" => strict = false,
+                "--stdin-kv" => stdin_kv = true,
+                "--home" => home = true,
+                _ => {
+                    if looks_like_option(arg) {
+                        unknown_options.push(arg.clone());
+                    }
+                    positional.push(arg.clone());
+                },
+            }
+            Ok(())
+        };
+        if let Err(e) = parse_option() {
+            problems.push(e);
+        }
+    }
+
+    if strict {
+        for unknown in &unknown_options {
+            problems.push(unknown_option_error(unknown, OPEN_OPTIONS));
+        }
+    }
+
+    let mut stdin_username = None;
+    let mut credentials_from_stdin = false;
+    if stdin_kv && !positional.is_empty() {
+        problems.push("--stdin-kv cannot be combined with PATH or USERNAME arguments".to_owned());
+    } else if stdin_kv {
+        match read_stdin_kv() {
+            Ok(input) => {
+                if input.password.is_some() && password.is_some() {
+                    problems.push("a password cannot be passed using both --password and stdin".to_owned());
+                }
+                positional = input.paths;
+                credentials_from_stdin = input.username.is_some() || input.password.is_some();
+                stdin_username = input.username;
+                password = password.or(input.password);
+            },
+            Err(e) => problems.push(e),
+        }
+    }
+
+    let feature_options = [
+        ("--smartcard", smartcard, "smartcard", cfg!(feature = "smartcard")),
+        ("--modern-prompt", modern_prompt, "modern-prompt", cfg!(feature = "modern-prompt")),
+        (
+            "--clear-cached-credentials", clear_cached_credentials,
+            "credential-manager", cfg!(feature = "credential-manager"),
+        ),
+        (
+            "--credential-target", credential_target.is_some(),
+            "credential-manager", cfg!(feature = "credential-manager"),
+        ),
+    ];
+    for (option, given, feature, compiled_in) in feature_options {
+        if given && !compiled_in {
+            problems.push(not_compiled_in(option, feature));
+        }
+    }
+
+    let credential_sources = [smartcard, username_from_current, credential_blob.is_some(), guest];
+    if credential_sources.iter().filter(|s| **s).count() > 1 {
+        problems.push("only one of --smartcard, --credential-blob, --guest and --username-from-current can be used".to_owned());
+    }
+    if stdin_username.is_some() && credential_sources.iter().any(|s| *s) {
+        problems.push("a user cannot be passed on stdin together with another way of obtaining credentials".to_owned());
+    }
+    if username_from_current && password.is_some() {
+        problems.push("--username-from-current cannot be combined with --password".to_owned());
+    }
+    if guest && password.is_some() {
+        problems.push("--guest cannot be combined with --password".to_owned());
+    }
+    if modern_prompt && (credential_sources.iter().any(|s| *s) || no_prompt) {
+        problems.push("--modern-prompt cannot be combined with --no-prompt or another way of obtaining credentials".to_owned());
+    }
+    if (prompt_title.is_some() || prompt_message.is_some()) && !modern_prompt {
+        problems.push("--prompt-title and --prompt-message require --modern-prompt".to_owned());
+    }
+
+    // with neither a username nor a password, WNetAddConnection2W uses the caller's logon session
+    let mut username_missing = false;
+    let username = if smartcard || username_from_current || guest || open_in_browser {
+        None
+    } else if credential_blob.is_some() {
+        credential_blob
+    } else if stdin_kv {
+        stdin_username
+    } else if home {
+        if positional.len() > 1 {
+            problems.push("--home cannot be combined with PATH arguments, only with a USERNAME".to_owned());
+        }
+        positional.pop()
+    } else if positional.len() < 2 {
+        username_missing = true;
+        None
+    } else {
+        positional.pop()
+    };
+    let mut paths = positional;
+    if home {
+        if stdin_kv {
+            problems.push("--home and --stdin-kv cannot be combined".to_owned());
+        }
+        // the home directory share is only looked up once the arguments are known to be valid
+        paths = Vec::new();
+    }
+    if username_missing {
+        problems.push("expected at least one PATH and a USERNAME".to_owned());
+    } else if paths.is_empty() && !home {
+        problems.push("expected at least one PATH".to_owned());
+    }
+    for path in &paths {
+        if open_in_browser {
+            if !looks_like_web_url(path) {
+                problems.push(format!("{:?} is not an http:// or https:// URL", path));
+            }
+        } else if looks_like_web_url(path) {
+            problems.push(format!("{:?} is a web URL; pass --open-in-browser to open it in the default browser", path));
+        } else if !looks_like_unc_path(path) && !looks_like_server_root(path) {
+            problems.push(format!("{:?} is not a UNC path of the form \\\\server\\share", path));
+        }
+    }
+    if !open_in_browser && paths.iter().any(|path| looks_like_server_root(path)) {
+        // there is no share to map, check or copy into, only the server's list of shares to open
+        let share_options = [
+            ("--drive", drive.is_some()),
+            ("--register-only", register_only),
+            ("--ephemeral", ephemeral),
+            ("--disconnect-after", disconnect_after.is_some()),
+            ("--check-only", check_only),
+            ("--show-space", show_space),
+            ("--copy-in", copy_in.is_some()),
+            ("--require-write", require_write),
+            ("--admin-fallback", admin_fallback),
+            ("--terminal", terminal),
+        ];
+        for (option, given) in share_options {
+            if given {
+                problems.push(format!("{} cannot be used with a PATH naming a server (\\\\server)", option));
+            }
+        }
+    } else if auth {
+        problems.push("--auth requires a PATH naming a server (\\\\server)".to_owned());
+    }
+    if drive_pool.is_some() && drive.as_deref() != Some("auto") {
+        problems.push("--drive-pool requires --drive auto".to_owned());
+    }
+    if let Some(drive) = drive.as_deref().filter(|d| *d != "auto") {
+        if !looks_like_drive(drive) {
+            problems.push(format!("{:?} is not a drive of the form X:", drive));
+        }
+    }
+
+    if persistent && drive.is_none() {
+        problems.push("--persistent requires --drive".to_owned());
+    }
+    if register_only && drive.is_none() {
+        problems.push("--register-only requires --drive".to_owned());
+    }
+    if register_only && ephemeral {
+        problems.push("--register-only and --ephemeral cannot be combined".to_owned());
+    }
+    if ephemeral && persistent {
+        problems.push("--ephemeral and --persistent cannot be combined".to_owned());
+    }
+    if disconnect_after.is_some() && (register_only || persistent) {
+        problems.push("--disconnect-after cannot be combined with --register-only or --persistent".to_owned());
+    }
+    let session = ephemeral || disconnect_after.is_some();
+    if keepalive.is_some() && !session {
+        problems.push("--keepalive requires --ephemeral or --disconnect-after".to_owned());
+    }
+    if post_disconnect.is_some() && !session {
+        problems.push("--post-disconnect requires --ephemeral or --disconnect-after".to_owned());
+    }
+    if pre_connect.is_some() && (register_only || open_in_browser) {
+        problems.push("--pre-connect cannot be combined with --register-only or --open-in-browser".to_owned());
+    }
+    if force && drive.is_none() {
+        problems.push("--force requires --drive".to_owned());
+    }
+    if raw_flags_exact && raw_flags.is_none() {
+        problems.push("--raw-flags-exact requires --raw-flags".to_owned());
+    }
+    if properties && verb.is_some() {
+        problems.push("--properties and --verb cannot be combined".to_owned());
+    }
+    if terminal && (properties || verb.is_some()) {
+        problems.push("--terminal cannot be combined with --properties or --verb".to_owned());
+    }
+    if let Some(args) = &open_with_args {
+        if open_with.is_none() {
+            problems.push("--open-with-args requires --with".to_owned());
+        }
+        if let Err(problem) = check_open_with_args(args) {
+            problems.push(problem);
+        }
+    }
+    if only_if_missing && (terminal || properties || open_with.is_some() || open_in_browser || register_only) {
+        problems.push("--only-if-missing cannot be combined with --terminal, --properties, --with, --open-in-browser or --register-only".to_owned());
+    }
+    if open_with.is_some() && (terminal || properties || verb.is_some() || open_in_browser) {
+        problems.push("--with cannot be combined with --terminal, --properties, --verb or --open-in-browser".to_owned());
+    }
+    if open_in_browser {
+        let connection_options = [
+            ("--drive", drive.is_some()),
+            ("--ephemeral", ephemeral),
+            ("--disconnect-after", disconnect_after.is_some()),
+            ("--home", home),
+            ("--properties", properties),
+            ("--terminal", terminal),
+            ("--clear-cached-credentials", clear_cached_credentials),
+            ("--smartcard", smartcard),
+            ("--guest", guest),
+        ];
+        for (option, given) in connection_options {
+            if given {
+                problems.push(format!("--open-in-browser and {} cannot be combined", option));
+            }
+        }
+    }
+    if skip_check && max_connections.is_some() {
+        problems.push("--skip-check and --max-connections cannot be combined".to_owned());
+    }
+    if assume_connected && (terminal || properties || parallel > 1) {
+        problems.push("--assume-connected cannot be combined with --terminal, --properties or --parallel".to_owned());
+    }
+    if heal_stale && (skip_check || assume_connected || register_only || open_in_browser || check_only) {
+        problems.push("--heal-stale cannot be combined with --skip-check, --assume-connected, --register-only, --open-in-browser or --check-only".to_owned());
+    }
+    if reuse_any && (drive.is_some() || skip_check || assume_connected || register_only || open_in_browser) {
+        problems.push("--reuse-any cannot be combined with --drive, --skip-check, --assume-connected, --register-only or --open-in-browser".to_owned());
+    }
+    if require_write && (assume_connected || register_only || open_in_browser || check_only) {
+        problems.push("--require-write cannot be combined with --assume-connected, --register-only, --open-in-browser or --check-only".to_owned());
+    }
+    if assume_connected && (register_only || open_in_browser) {
+        problems.push("--assume-connected cannot be combined with --register-only or --open-in-browser".to_owned());
+    }
+    if check_only && (register_only || open_in_browser || session) {
+        problems.push("--check-only cannot be combined with --register-only, --open-in-browser, --ephemeral or --disconnect-after".to_owned());
+    }
+    if admin_fallback_dir.is_some() && !admin_fallback {
+        problems.push("--admin-fallback-dir requires --admin-fallback".to_owned());
+    }
+    if admin_fallback && open_in_browser {
+        problems.push("--admin-fallback and --open-in-browser cannot be combined".to_owned());
+    }
+    if let Some(source) = &copy_in {
+        if !source.is_file() {
+            problems.push(format!("--copy-in: {:?} is not an existing file", source));
+        }
+        if register_only || open_in_browser || check_only {
+            problems.push("--copy-in cannot be combined with --register-only, --open-in-browser or --check-only".to_owned());
+        }
+    }
+    if write_drive_to.is_some() && (drive.is_none() || register_only) {
+        problems.push("--write-drive-to requires --drive and cannot be combined with --register-only".to_owned());
+    }
+    if scope == ConnectionScope::GlobalNet {
+        problems.push("--scope globalnet enumerates network providers rather than connections and is only supported by list".to_owned());
+    }
+    if copy_overwrite && copy_in.is_none() {
+        problems.push("--copy-overwrite requires --copy-in".to_owned());
+    }
+    if wait_on_success && on_success.is_none() {
+        problems.push("--wait-on-success requires --on-success".to_owned());
+    }
+    if drive.is_some() && paths.len() > 1 {
+        problems.push("--drive can only be used with a single PATH".to_owned());
+    }
+    if !problems.is_empty() {
+        return Err(problems.join("\n"));
+    }
+
+    if home {
+        match open_share::home_share() {
+            Some(home_share) if looks_like_unc_path(&home_share) => paths.push(home_share),
+            Some(home_share) => return Err(format!("the home directory share {:?} is not a UNC path of the form \\\\server\\share", home_share)),
+            None => return Err("cannot determine the home directory share: HOMESHARE is not set and the account has none".to_owned()),
+        }
+    }
+    if drive.as_deref() == Some("auto") {
+        let pool = drive_pool.as_deref().unwrap_or(DEFAULT_DRIVE_POOL);
+        drive = open_share::free_drive(pool);
+        if drive.is_none() {
+            return Err(format!("--drive auto: all drive letters of the pool {} are in use", pool));
+        }
+    }
+
+    Ok(Args {
+        paths,
+        username,
+        password,
+        drive,
+        force,
+        drive_wait,
+        open_timeout,
+        persistent,
+        register_only,
+        ephemeral,
+        disconnect_after,
+        keepalive,
+        post_disconnect,
+        no_prompt,
+        clear_cached_credentials,
+        credential_target,
+        modern_prompt,
+        prompt_title,
+        prompt_message,
+        raw_flags,
+        raw_flags_exact,
+        provider,
+        comment,
+        verb,
+        properties,
+        terminal,
+        open_with,
+        open_with_args,
+        only_if_missing,
+        auth,
+        window,
+        log_file,
+        copy_in,
+        write_drive_to,
+        copy_overwrite,
+        show_space,
+        toast,
+        pre_connect,
+        on_success,
+        wait_on_success,
+        diagnose,
+        connect_as_system,
+        use_fqdn,
+        long_names,
+        tabs,
+        open_delay,
+        continue_on_error,
+        parallel,
+        skip_check,
+        assume_connected,
+        heal_stale,
+        reuse_any,
+        require_write,
+        open_in_browser,
+        check_only,
+        admin_fallback,
+        admin_fallback_dir,
+        max_connections,
+        output,
+        json_errors,
+        smartcard,
+        guest,
+        credentials_from_stdin,
+        dump_netresource,
+        print_env,
+        dump_enum_raw,
+        enum_buffer_kib,
+        scope,
+        warn_redirection,
+        max_enum_time,
+    })
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SHARE: &str = "\\\\server\\share";
+
+    /// Argument lists (following a PATH and a USERNAME unless they start with `!`) and whether
+    /// they pass validation.
+    const COMBINATIONS: &[(&[&str], bool)] = &[
+        (&[], true),
+        (&["!"], false),
+        (&["!", SHARE], false),
+
+        // drives
+        (&["--drive", "X:"], true),
+        (&["--drive", "X"], false),
+        (&["--drive", "X:\\"], false),
+        (&["--drive-pool", "XYZ"], false),
+        (&["--force"], false),
+        (&["--drive", "X:", "--force"], true),
+        (&["--persistent"], false),
+        (&["--drive", "X:", "--persistent"], true),
+        (&["--register-only"], false),
+        (&["--drive", "X:", "--register-only"], true),
+        (&["--write-drive-to", "drive.txt"], false),
+        (&["--drive", "X:", "--write-drive-to", "drive.txt"], true),
+        (&["--drive", "X:", "--register-only", "--write-drive-to", "drive.txt"], false),
+
+        // sessions
+        (&["--ephemeral"], true),
+        (&["--ephemeral", "--keepalive", "30"], true),
+        (&["--keepalive", "30"], false),
+        (&["--post-disconnect", "cleanup.cmd"], false),
+        (&["--disconnect-after", "60", "--post-disconnect", "cleanup.cmd"], true),
+        (&["--disconnect-after", "0"], false),
+        (&["--drive", "X:", "--ephemeral", "--persistent"], false),
+        (&["--drive", "X:", "--ephemeral", "--register-only"], false),
+        (&["--drive", "X:", "--disconnect-after", "60", "--persistent"], false),
+
+        // sources of credentials, which replace the USERNAME
+        (&["!", "--smartcard", SHARE], true),
+        (&["!", "--guest", SHARE], true),
+        (&["!", "--username-from-current", SHARE], true),
+        (&["!", "--smartcard", "--guest", SHARE], false),
+        (&["!", "--guest", "--username-from-current", SHARE], false),
+        (&["!", "--guest", "--password", "secret", SHARE], false),
+        (&["!", "--username-from-current", "--password", "secret", SHARE], false),
+        (&["--password", "secret"], true),
+
+        // how the share is opened
+        (&["--verb", "explore"], true),
+        (&["--properties", "--verb", "explore"], false),
+        (&["--terminal", "--properties"], false),
+        (&["--terminal", "--verb", "explore"], false),
+        (&["--with", "app.exe", "--open-with-args", "--path {}"], true),
+        (&["--open-with-args", "--path {}"], false),
+        (&["--with", "app.exe", "--open-with-args", "{} {}"], false),
+        (&["--with", "app.exe", "--terminal"], false),
+        (&["--only-if-missing"], true),
+        (&["--only-if-missing", "--terminal"], false),
+        (&["--only-if-missing", "--with", "app.exe"], false),
+        (&["--open-delay", "250", SHARE], true),
+        (&["--open-delay", "soon"], false),
+        (&["!", "--open-in-browser", "https://example.com/dav"], true),
+        (&["!", "--open-in-browser", SHARE], false),
+        (&["!", "https://example.com/dav", "user"], false),
+        (&["!", "--open-in-browser", "--drive", "X:", "https://example.com/dav"], false),
+        (&["!", "--open-in-browser", "--ephemeral", "https://example.com/dav"], false),
+
+        // checking and connecting
+        (&["--skip-check"], true),
+        (&["--skip-check", "--max-connections", "10"], false),
+        (&["--assume-connected"], true),
+        (&["--assume-connected", "--terminal"], false),
+        (&["--assume-connected", "--parallel", "2"], false),
+        (&["--check-only"], true),
+        (&["--check-only", "--ephemeral"], false),
+        (&["--admin-fallback", "--admin-fallback-dir", "shares"], true),
+        (&["--admin-fallback-dir", "shares"], false),
+        (&["--scope", "remembered"], true),
+        (&["--scope", "globalnet"], false),
+        (&["--scope", "everything"], false),
+        (&["--raw-flags-exact"], false),
+        (&["--raw-flags", "0x8", "--raw-flags-exact"], true),
+
+        // hooks and extras
+        (&["--wait-on-success"], false),
+        (&["--on-success", "done.cmd", "--wait-on-success"], true),
+        (&["--copy-overwrite"], false),
+        (&["--prompt-title", "Log in"], false),
+        (&["--modern-prompt", "--prompt-title", "Log in"], true),
+        (&["--credential-target", "*.corp.example", "--clear-cached-credentials"], true),
+
+        // several PATHs
+        (&["!", SHARE, "\\\\server\\other", "user"], true),
+        (&["!", "--drive", "X:", SHARE, "\\\\server\\other", "user"], false),
+        (&["!", "C:\\local", "user"], false),
+
+        // checking the existing connections
+        (&["--heal-stale"], true),
+        (&["--drive", "X:", "--heal-stale"], true),
+        (&["--heal-stale", "--skip-check"], false),
+        (&["--heal-stale", "--assume-connected"], false),
+        (&["--reuse-any"], true),
+        (&["--reuse-any", "--drive", "X:"], false),
+        (&["--reuse-any", "--skip-check"], false),
+        (&["--require-write"], true),
+        (&["--require-write", "--assume-connected"], false),
+
+        // a server instead of a share
+        (&["!", "\\\\server", "user"], true),
+        (&["!", "\\\\server\\", "user"], true),
+        (&["!", "--auth", "\\\\server", "user"], true),
+        (&["--auth"], false),
+        (&["!", "--drive", "X:", "\\\\server", "user"], false),
+        (&["!", "--check-only", "\\\\server", "user"], false),
+        (&["!", "--ephemeral", "\\\\server", "user"], false),
+        (&["!", "\\\\", "user"], false),
+
+        // options
+        (&["--no-such-option"], false),
+        (&["!", SHARE, "user", "--drive"], false),
+        (&["--stdin-kv"], false),
+        (&["!", "--stdin-kv", "--drive", "X", SHARE], false),
+    ];
+
+    #[test]
+    fn flag_combinations() {
+        for (options, valid) in COMBINATIONS {
+            let args: Vec<String> = match options.split_first() {
+                Some((&"!", rest)) => rest.iter().map(|a| (*a).to_owned()).collect(),
+                _ => options.iter()
+                    .chain([SHARE, "user"].iter())
+                    .map(|a| (*a).to_owned())
+                    .collect(),
+            };
+            let result = parse_command(&args);
+            assert_eq!(result.is_ok(), *valid, "{:?}: {:?}", args, result.err());
+        }
+    }
+
+    #[test]
+    fn export_config_takes_one_file() {
+        let parse = |args: &[&str]| parse_command(&args.iter().map(|a| (*a).to_owned()).collect::<Vec<_>>());
+        assert!(matches!(parse(&["export-config", "shares.toml"]), Ok(Command::ExportConfig { file }) if file.as_os_str() == "shares.toml"));
+        assert!(matches!(parse(&["export-config", "--", "--odd.toml"]), Ok(Command::ExportConfig { .. })));
+        assert!(parse(&["export-config"]).is_err());
+        assert!(parse(&["export-config", "a.toml", "b.toml"]).is_err());
+        assert!(parse(&["export-config", "--force", "a.toml"]).is_err());
+    }
+
+    #[test]
+    fn invalid_values_are_reported_together() {
+        let args: Vec<String> = ["--drive-pool", "1", "--open-delay", "soon", "--persistent", SHARE, "user"].iter()
+            .map(|a| (*a).to_owned())
+            .collect();
+        let problems = parse_command(&args).err().unwrap();
+        assert_eq!(problems.lines().count(), 3, "{}", problems);
+        assert!(problems.contains("--persistent requires --drive"), "{}", problems);
+    }
+}
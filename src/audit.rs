@@ -0,0 +1,80 @@
+//! Appends a line for every connection attempt to a size-bounded log file.
+//!
+//! Once the log grows beyond [`MAX_LOG_SIZE`], it is renamed to `log.1` (replacing any previous
+//! backup) and a fresh log is started. Problems writing the log are reported as warnings and never
+//! affect the outcome of the actual operation.
+
+
+use std::env;
+use std::fs::{self, OpenOptions};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+use windows::Win32::System::SystemInformation::GetSystemTime;
+
+use crate::report::progress;
+
+
+/// The size beyond which the log file is rotated.
+pub(crate) const MAX_LOG_SIZE: u64 = 1024*1024;
+
+
+/// The log file used if none is specified: `%LOCALAPPDATA%\open-share\log`.
+pub(crate) fn default_log_path() -> Option<PathBuf> {
+    let local_app_data = env::var_os("LOCALAPPDATA")?;
+    let mut path = PathBuf::from(local_app_data);
+    path.push("open-share");
+    path.push("log");
+    Some(path)
+}
+
+fn utc_timestamp() -> String {
+    let now = unsafe { GetSystemTime() };
+    format!(
+        "{:04}-{:02}-{:02}T{:02}:{:02}:{:02}.{:03}Z",
+        now.wYear, now.wMonth, now.wDay,
+        now.wHour, now.wMinute, now.wSecond, now.wMilliseconds,
+    )
+}
+
+fn rotate_if_full(path: &Path) -> std::io::Result<()> {
+    let size = match fs::metadata(path) {
+        Ok(m) => m.len(),
+        Err(_) => return Ok(()),
+    };
+    if size < MAX_LOG_SIZE {
+        return Ok(());
+    }
+
+    let mut backup_name = path.as_os_str().to_owned();
+    backup_name.push(".1");
+    let backup_path = PathBuf::from(backup_name);
+    if backup_path.exists() {
+        fs::remove_file(&backup_path)?;
+    }
+    fs::rename(path, &backup_path)
+}
+
+fn try_append(path: &Path, line: &str) -> std::io::Result<()> {
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    rotate_if_full(path)?;
+
+    let mut file = OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(path)?;
+    writeln!(file, "{}", line)
+}
+
+/// Records the outcome of a connection attempt in the audit log at the given path.
+pub(crate) fn record(log_path: &Path, path: &str, username: Option<&str>, outcome: &str, win32_code: u32) {
+    let line = format!(
+        "{} path={:?} user={:?} outcome={} code={}",
+        utc_timestamp(), path, username.unwrap_or(""), outcome, win32_code,
+    );
+    if let Err(e) = try_append(log_path, &line) {
+        progress(format!("warning: failed to write audit log {}: {}", log_path.display(), e));
+    }
+}
@@ -0,0 +1,228 @@
+use std::collections::BTreeMap;
+use std::sync::Mutex;
+use std::thread::scope;
+use std::time::Duration;
+
+use crate::{
+    CancellationToken, connect_share, open_assuming_connected, open_connected_share, opens_optimistically, Connected,
+    normalize_path, OpenShareError, ShareLogger, ShareRequest, Summary, UncPath,
+};
+use crate::cancel::sleep_cancellable;
+use crate::log::{log, Logger};
+use crate::shell::explorer_opens_tabs;
+
+
+/// How long to wait after opening the first share before opening the others as tabs, giving
+/// Explorer time to create the window that the tabs will be added to.
+const TAB_SETTLE_DELAY: Duration = Duration::from_millis(1500);
+
+
+/// Options that apply to a batch of shares as a whole.
+#[derive(Clone, Debug, Default)]
+pub struct BatchOptions {
+    /// Open the second and subsequent shares as tabs of the first share's Explorer window, if
+    /// the operating system supports it.
+    pub tabs: bool,
+
+    /// The maximum number of shares to connect to concurrently; 0 and 1 connect to one share
+    /// after the other.
+    pub parallel: usize,
+
+    /// Keep processing the remaining shares after one of them has failed.
+    pub continue_on_error: bool,
+
+    /// How long to wait before opening each share after the first, so that Explorer is not
+    /// swamped with windows; zero opens them right away. Connecting is not delayed.
+    pub open_delay: Duration,
+}
+
+
+/// Removes the requests that repeat an earlier one, i.e. have the same [normalized](normalize_path)
+/// path, drive and username, so that each share is connected and opened once. Returns the
+/// remaining requests and the paths of those removed, in order.
+///
+/// Fails with [`OpenShareError::ConflictingCredentials`] if a path is requested for different
+/// usernames, as connecting the second time would fail with `ERROR_SESSION_CREDENTIAL_CONFLICT`
+/// or, worse, silently reuse the first user's connection.
+pub fn deduplicate_requests(requests: &[ShareRequest]) -> Result<(Vec<ShareRequest>, Vec<String>), OpenShareError> {
+    let mut unique: Vec<ShareRequest> = Vec::with_capacity(requests.len());
+    let mut collapsed = Vec::new();
+    for request in requests {
+        let path = normalize_path(request.path());
+        let drive = request.drive().map(|d| d.to_lowercase());
+        let mut duplicate = false;
+        for earlier in unique.iter().filter(|e| normalize_path(e.path()) == path) {
+            if earlier.username() != request.username() {
+                return Err(OpenShareError::ConflictingCredentials {
+                    path: request.path().to_owned(),
+                    first: earlier.username().map(|u| u.to_owned()),
+                    second: request.username().map(|u| u.to_owned()),
+                });
+            }
+            if earlier.drive().map(|d| d.to_lowercase()) == drive {
+                duplicate = true;
+            }
+        }
+        if duplicate {
+            collapsed.push(request.path().to_owned());
+        } else {
+            unique.push(request.clone());
+        }
+    }
+    Ok((unique, collapsed))
+}
+
+/// The key by which shares are grouped for concurrent connects: the server name, or the whole
+/// path if it is not a UNC path.
+fn server_key(request: &ShareRequest) -> String {
+    let normalized = normalize_path(request.path());
+    match UncPath::parse(&normalized) {
+        Some(unc) => unc.server.to_owned(),
+        None => normalized,
+    }
+}
+
+/// Connects to each of the requested shares using up to `parallel` threads, returning the results
+/// in the order of the requests.
+///
+/// Shares on the same server are connected one after the other by the same thread, as the second
+/// connection to a server usually reuses the session established by the first.
+fn connect_concurrently(
+    requests: &[ShareRequest],
+    parallel: usize,
+    cancel: Option<&CancellationToken>,
+    logger: Logger<'_>,
+) -> Vec<Result<Connected, OpenShareError>> {
+    let mut groups: BTreeMap<String, Vec<usize>> = BTreeMap::new();
+    for (i, request) in requests.iter().enumerate() {
+        groups.entry(server_key(request)).or_default().push(i);
+    }
+
+    let queue = Mutex::new(groups.into_values().collect::<Vec<_>>());
+    let results = Mutex::new(BTreeMap::new());
+    let thread_count = parallel.min(requests.len());
+    scope(|s| {
+        for _ in 0..thread_count {
+            s.spawn(|| {
+                loop {
+                    let group = match queue.lock().unwrap().pop() {
+                        Some(g) => g,
+                        None => break,
+                    };
+                    for i in group {
+                        let result = connect_share(&requests[i], cancel, logger);
+                        results.lock().unwrap().insert(i, result);
+                    }
+                }
+            });
+        }
+    });
+
+    results.into_inner().unwrap().into_values().collect()
+}
+
+/// Connects to and opens each of the requested shares in order.
+///
+/// Unless `options.continue_on_error` is set, processing stops at the first failure; the returned
+/// vector contains one result for each share that was attempted.
+///
+/// If `options.parallel` is greater than 1, all shares are connected concurrently before any of
+/// them is opened; they are then opened in order. A failure still ends the batch, but shares
+/// after the failed one may have been connected by then.
+///
+/// Shares requested more than once are processed each time; see [`deduplicate_requests`].
+///
+/// If `options.open_delay` is set, the batch pauses that long before opening each share after the
+/// first (once it is connected, so that the pause does not slow down connecting when that happens
+/// concurrently).
+///
+/// Once `cancel` is set, each share not finished yet fails with [`OpenShareError::Cancelled`].
+/// The events of all shares are passed to `logger`, from several threads if connecting
+/// concurrently.
+pub fn open_shares(
+    requests: &[ShareRequest],
+    options: &BatchOptions,
+    cancel: Option<&CancellationToken>,
+    logger: Option<&dyn ShareLogger>,
+) -> Vec<Result<Summary, OpenShareError>> {
+    let tabs = options.tabs && requests.len() > 1 && explorer_opens_tabs();
+    if options.tabs && requests.len() > 1 && !tabs {
+        log!(logger, Warning, Open, "Explorer on this version of Windows does not open folders as tabs; opening separate windows...");
+    }
+
+    let mut connected = if options.parallel > 1 {
+        Some(connect_concurrently(requests, options.parallel, cancel, logger).into_iter())
+    } else {
+        None
+    };
+
+    let mut results = Vec::with_capacity(requests.len());
+    for (i, request) in requests.iter().enumerate() {
+        let connect_result = match &mut connected {
+            Some(c) => Some(c.next().unwrap()),
+            None if opens_optimistically(request) => None,
+            None => Some(connect_share(request, cancel, logger)),
+        };
+        let delay = match i {
+            0 => Duration::ZERO,
+            1 if tabs => TAB_SETTLE_DELAY.max(options.open_delay),
+            _ => options.open_delay,
+        };
+        let settled = if delay.is_zero() {
+            Ok(())
+        } else {
+            sleep_cancellable(cancel, delay)
+        };
+
+        let result = match (settled, connect_result) {
+            (Err(e), _) => Err(e),
+            (Ok(()), Some(r)) => r.and_then(|c| open_connected_share(request, c, cancel, logger)),
+            (Ok(()), None) => open_assuming_connected(request, cancel, logger),
+        };
+        let failed = result.is_err();
+        results.push(result);
+        if failed && !options.continue_on_error {
+            break;
+        }
+    }
+    results
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn request(path: &str, username: Option<&str>) -> ShareRequest {
+        let builder = ShareRequest::builder(path);
+        match username {
+            Some(u) => builder.username(u).build(),
+            None => builder.build(),
+        }
+    }
+
+    #[test]
+    fn duplicates_are_collapsed() {
+        let requests = [
+            request("\\\\server\\share", Some("alice")),
+            request("\\\\server\\other", Some("alice")),
+            request("//SERVER/share/", Some("alice")),
+        ];
+        let (unique, collapsed) = deduplicate_requests(&requests).unwrap();
+        let paths: Vec<&str> = unique.iter().map(|r| r.path()).collect();
+        assert_eq!(paths, ["\\\\server\\share", "\\\\server\\other"]);
+        assert_eq!(collapsed, ["//SERVER/share/"]);
+    }
+
+    #[test]
+    fn different_users_conflict() {
+        let requests = [
+            request("\\\\server\\share", Some("alice")),
+            request("\\\\Server\\Share", Some("bob")),
+        ];
+        assert!(matches!(
+            deduplicate_requests(&requests),
+            Err(OpenShareError::ConflictingCredentials { first: Some(_), second: Some(_), .. }),
+        ));
+    }
+}
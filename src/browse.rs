@@ -0,0 +1,89 @@
+//! Listing the shares offered by a server.
+
+
+use std::ptr::null_mut;
+use std::slice;
+
+use serde::Serialize;
+use windows::core::PCWSTR;
+use windows::Win32::Foundation::{ERROR_MORE_DATA, NO_ERROR};
+use windows::Win32::NetworkManagement::NetManagement::{MAX_PREFERRED_LENGTH, NetApiBufferFree};
+use windows::Win32::Storage::FileSystem::{
+    NetShareEnum, SHARE_INFO_1, STYPE_DISKTREE, STYPE_MASK, STYPE_SPECIAL,
+};
+
+use crate::error::OpenShareError;
+use crate::wstr::{str_to_wcstring, wcstr_to_string};
+
+
+/// A disk share offered by a server.
+#[derive(Clone, Debug, Eq, PartialEq, Serialize)]
+pub struct ServerShare {
+    /// The name of the share, e.g. `public`.
+    pub name: String,
+
+    /// The UNC path of the share, e.g. `\\server\public`.
+    pub path: String,
+
+    /// The description of the share set on the server, if any.
+    pub remark: Option<String>,
+
+    /// Whether this is a special share such as `C$` or `ADMIN$`.
+    pub special: bool,
+}
+
+
+/// Lists the disk shares that the given server (`server` or `\\server`) offers, sorted by name.
+///
+/// Printer and IPC shares are omitted. Listing uses the credentials of the current logon session
+/// or of an existing connection to the server.
+pub fn list_shares(server: &str) -> Result<Vec<ServerShare>, OpenShareError> {
+    let server = server.trim_start_matches('\\');
+    let server_windows = str_to_wcstring(server);
+    let mut buffer: *mut u8 = null_mut();
+    let mut read = 0u32;
+    let mut total = 0u32;
+    let result = unsafe {
+        NetShareEnum(
+            PCWSTR(server_windows.as_ptr()),
+            1,
+            &mut buffer,
+            MAX_PREFERRED_LENGTH,
+            &mut read,
+            &mut total,
+            None,
+        )
+    };
+    if result != NO_ERROR.0 && result != ERROR_MORE_DATA.0 {
+        return Err(OpenShareError::Browse { code: result });
+    }
+    if buffer.is_null() {
+        return Ok(Vec::new());
+    }
+
+    let entries = unsafe {
+        slice::from_raw_parts(buffer as *const SHARE_INFO_1, read.try_into().unwrap())
+    };
+    let mut shares: Vec<ServerShare> = entries.iter()
+        .filter(|entry| (entry.shi1_type.0 & STYPE_MASK.0) == STYPE_DISKTREE.0)
+        .map(|entry| {
+            let name = wcstr_to_string(entry.shi1_netname.0);
+            let remark = if entry.shi1_remark.0.is_null() {
+                None
+            } else {
+                Some(wcstr_to_string(entry.shi1_remark.0))
+                    .filter(|r| !r.is_empty())
+            };
+            ServerShare {
+                path: format!("\\\\{}\\{}", server, name),
+                name,
+                remark,
+                special: (entry.shi1_type.0 & STYPE_SPECIAL.0) != 0,
+            }
+        })
+        .collect();
+    let _ = unsafe { NetApiBufferFree(Some(buffer as *const _)) };
+
+    shares.sort_by_key(|s| s.name.to_lowercase());
+    Ok(shares)
+}
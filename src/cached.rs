@@ -0,0 +1,86 @@
+//! Clearing credentials that Windows has cached for a server.
+//!
+//! Whether the Credential Manager holds credentials for a server can be checked as well, to report
+//! where the credentials used for a connection came from.
+//!
+//! After a password change, connecting may keep failing because Windows keeps using the old
+//! password. It is remembered in two places: credentials saved in the Credential Manager (e.g. by
+//! ticking "Remember my credentials") and sessions that are still open to the server, either
+//! through other connected shares or through its `IPC$` share. Both are removed here before
+//! connecting anew.
+//!
+//! Only entries stored for exactly the server name of the path (or the credential target given
+//! instead, see [`ShareRequest::credential_target`](crate::ShareRequest::credential_target)) are
+//! removed; credentials saved for another name of the same server (e.g. its fully qualified name)
+//! are left alone.
+
+
+use std::ptr::null_mut;
+
+use windows::core::PCWSTR;
+use windows::Win32::Security::Credentials::{
+    CRED_TYPE, CRED_TYPE_DOMAIN_CERTIFICATE, CRED_TYPE_DOMAIN_PASSWORD, CREDENTIALW, CredDeleteW,
+    CredFree, CredReadW,
+};
+
+use crate::connection::{disconnect, EnumerationOptions, find_server_connections};
+use crate::log::{log, Logger};
+use crate::wstr::str_to_wcstring;
+
+
+/// The types of Credential Manager entries used when connecting to shares.
+const SHARE_CREDENTIAL_TYPES: [(CRED_TYPE, &str); 2] = [
+    (CRED_TYPE_DOMAIN_PASSWORD, "password"),
+    (CRED_TYPE_DOMAIN_CERTIFICATE, "certificate"),
+];
+
+
+/// Whether the Credential Manager holds an entry for exactly the given target name (usually the
+/// server name).
+pub(crate) fn has_stored_credential(target: &str) -> bool {
+    let target_windows = str_to_wcstring(target);
+    SHARE_CREDENTIAL_TYPES.iter().any(|(cred_type, _)| {
+        let mut credential: *mut CREDENTIALW = null_mut();
+        let found = unsafe { CredReadW(PCWSTR(target_windows.as_ptr()), *cred_type, 0, &mut credential) }.is_ok();
+        if !credential.is_null() {
+            unsafe { CredFree(credential as *const _) };
+        }
+        found
+    })
+}
+
+/// Removes the Credential Manager entries for the given target name (usually the server name) and
+/// disconnects every connection to the server, logging each removal.
+///
+/// Connections that cannot be removed (e.g. because files are open on them) are reported and
+/// skipped.
+pub(crate) fn clear_cached_credentials(server: &str, target: &str, options: &EnumerationOptions, logger: Logger<'_>) {
+    let mut cleared = false;
+
+    let target_windows = str_to_wcstring(target);
+    for (cred_type, description) in SHARE_CREDENTIAL_TYPES {
+        if unsafe { CredDeleteW(PCWSTR(target_windows.as_ptr()), cred_type, 0) }.is_ok() {
+            log!(logger, Info, Connect, "removed the stored {} credential for {}", description, target);
+            cleared = true;
+        }
+    }
+
+    for connection in find_server_connections(server, options, logger) {
+        let name = connection.local_name.as_deref().unwrap_or(&connection.remote_name);
+        log!(logger, Info, Connect, "disconnecting {} from {}...", name, connection.remote_name);
+        match disconnect(name, false, false, logger) {
+            Ok(()) => cleared = true,
+            Err(e) => log!(logger, Error, Connect, code = e.win32_code(), "{}", e),
+        }
+    }
+
+    let ipc_path = format!("\\\\{}\\IPC$", server);
+    if disconnect(&ipc_path, false, false, logger).is_ok() {
+        log!(logger, Info, Connect, "closed the session to {}", ipc_path);
+        cleared = true;
+    }
+
+    if !cleared {
+        log!(logger, Info, Connect, "no cached credentials or connections found for {}", server);
+    }
+}
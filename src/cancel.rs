@@ -0,0 +1,126 @@
+//! Cancelling the processing of shares from another thread.
+//!
+//! The Win32 calls made by the library (connecting in particular) cannot be interrupted, so a
+//! [`CancellationToken`] is checked between the phases of processing a share and while waiting,
+//! e.g. for a newly mapped drive to appear. Processing then stops with
+//! [`OpenShareError::Cancelled`].
+
+
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::thread::sleep;
+use std::time::{Duration, Instant};
+
+use crate::error::OpenShareError;
+
+
+/// How long a cancellable wait sleeps at most before checking the token again.
+const CANCEL_POLL_INTERVAL: Duration = Duration::from_millis(50);
+
+
+/// A flag that cancels the processing of shares once set.
+///
+/// ```no_run
+/// use std::sync::Arc;
+/// use open_share::{CancellationToken, ShareRequest};
+///
+/// let token = Arc::new(CancellationToken::new());
+/// let worker_token = Arc::clone(&token);
+/// let worker = std::thread::spawn(move || {
+///     let request = ShareRequest::builder("\\\\server\\share").build();
+///     open_share::open_share(&request, Some(&worker_token))
+/// });
+/// token.cancel();
+/// let _ = worker.join();
+/// ```
+#[derive(Debug, Default)]
+pub struct CancellationToken {
+    cancelled: AtomicBool,
+}
+impl CancellationToken {
+    pub const fn new() -> Self {
+        Self {
+            cancelled: AtomicBool::new(false),
+        }
+    }
+
+    /// Requests cancellation. Processing stops before its next phase.
+    pub fn cancel(&self) {
+        self.cancelled.store(true, Ordering::SeqCst);
+    }
+
+    /// Whether cancellation has been requested.
+    pub fn is_cancelled(&self) -> bool {
+        self.cancelled.load(Ordering::SeqCst)
+    }
+}
+
+
+/// Fails with [`OpenShareError::Cancelled`] if cancellation has been requested.
+pub(crate) fn check(cancel: Option<&CancellationToken>) -> Result<(), OpenShareError> {
+    if cancel.is_some_and(|c| c.is_cancelled()) {
+        Err(OpenShareError::Cancelled)
+    } else {
+        Ok(())
+    }
+}
+
+/// Sleeps for the given duration, waking up early to fail if cancellation is requested.
+pub(crate) fn sleep_cancellable(cancel: Option<&CancellationToken>, duration: Duration) -> Result<(), OpenShareError> {
+    let end = Instant::now() + duration;
+    loop {
+        check(cancel)?;
+        let now = Instant::now();
+        if now >= end {
+            return Ok(());
+        }
+        sleep((end - now).min(CANCEL_POLL_INTERVAL));
+    }
+}
+
+/// Checks `condition` every `interval` until it holds (returning `true`) or `timeout` elapses
+/// (returning `false`), failing if cancellation is requested in the meantime.
+pub(crate) fn poll_until<F: FnMut() -> bool>(
+    cancel: Option<&CancellationToken>,
+    timeout: Duration,
+    interval: Duration,
+    mut condition: F,
+) -> Result<bool, OpenShareError> {
+    let start = Instant::now();
+    loop {
+        if condition() {
+            return Ok(true);
+        }
+        if start.elapsed() >= timeout {
+            return Ok(false);
+        }
+        sleep_cancellable(cancel, interval)?;
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::thread;
+
+    #[test]
+    fn cancellation_aborts_polling_promptly() {
+        let token = CancellationToken::new();
+        let start = Instant::now();
+        let outcome = thread::scope(|s| {
+            s.spawn(|| {
+                sleep(Duration::from_millis(100));
+                token.cancel();
+            });
+            poll_until(Some(&token), Duration::from_secs(30), Duration::from_secs(5), || false)
+        });
+        assert!(matches!(outcome, Err(OpenShareError::Cancelled)));
+        assert!(start.elapsed() < Duration::from_secs(2));
+    }
+
+    #[test]
+    fn polling_without_token_times_out() {
+        let outcome = poll_until(None, Duration::from_millis(20), Duration::from_millis(5), || false);
+        assert!(matches!(outcome, Ok(false)));
+    }
+}
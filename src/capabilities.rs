@@ -0,0 +1,48 @@
+//! The `--capabilities` query, which tells wrapping tools which features this build supports.
+
+
+use serde::Serialize;
+
+use crate::args::{OPEN_OPTIONS, SUBCOMMANDS};
+
+
+#[derive(Serialize)]
+struct SubcommandCapabilities {
+    name: &'static str,
+    options: &'static [&'static str],
+}
+
+#[derive(Serialize)]
+struct Capabilities {
+    version: &'static str,
+    features: Vec<&'static str>,
+    options: &'static [&'static str],
+    subcommands: Vec<SubcommandCapabilities>,
+}
+
+
+/// The optional cargo features and whether this build has them.
+const FEATURES: [(&str, bool); 4] = [
+    ("credential-manager", cfg!(feature = "credential-manager")),
+    ("modern-prompt", cfg!(feature = "modern-prompt")),
+    ("smartcard", cfg!(feature = "smartcard")),
+    ("offline-files", cfg!(feature = "offline-files")),
+];
+
+
+/// Prints the capabilities as JSON to stdout. Returns the exit code.
+pub(crate) fn run() -> i32 {
+    let capabilities = Capabilities {
+        version: env!("CARGO_PKG_VERSION"),
+        features: FEATURES.iter()
+            .filter(|(_, enabled)| *enabled)
+            .map(|(name, _)| *name)
+            .collect(),
+        options: OPEN_OPTIONS,
+        subcommands: SUBCOMMANDS.iter()
+            .map(|(name, options)| SubcommandCapabilities { name, options })
+            .collect(),
+    };
+    println!("{}", serde_json::to_string(&capabilities).unwrap());
+    0
+}
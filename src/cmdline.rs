@@ -0,0 +1,108 @@
+//! Building command lines for programs started with a path as an argument.
+//!
+//! Most programs split their command line using the rules of `CommandLineToArgvW`: arguments are
+//! separated by whitespace unless quoted, and backslashes are only special in front of a quote. A
+//! path ending in a backslash (such as the root of a drive) therefore cannot simply be wrapped in
+//! quotes, as `"X:\"` escapes the closing quote.
+
+
+/// Quotes an argument so that `CommandLineToArgvW` turns it back into the same string.
+pub(crate) fn quote_argument(arg: &str) -> String {
+    let mut quoted = String::with_capacity(arg.len() + 2);
+    quoted.push('"');
+    let mut backslashes = 0;
+    for c in arg.chars() {
+        if c == '\\' {
+            backslashes += 1;
+            continue;
+        }
+        // backslashes are only special in front of a quote, which must be escaped itself
+        let escapes = if c == '"' { 2 * backslashes + 1 } else { backslashes };
+        quoted.extend(std::iter::repeat_n('\\', escapes));
+        quoted.push(c);
+        backslashes = 0;
+    }
+    // backslashes in front of the closing quote must be doubled too
+    quoted.extend(std::iter::repeat_n('\\', 2 * backslashes));
+    quoted.push('"');
+    quoted
+}
+
+/// Builds the parameters for the program given by
+/// [`ShareRequest::open_with`](crate::ShareRequest::open_with): the arguments with the quoted path
+/// in place of the `{}` placeholder, or appended to them if there is none.
+pub(crate) fn open_with_parameters(args: Option<&str>, path: &str) -> String {
+    let quoted = quote_argument(path);
+    match args {
+        Some(args) if args.contains("{}") => args.replacen("{}", &quoted, 1),
+        Some(args) if !args.trim().is_empty() => format!("{} {}", args.trim_end(), quoted),
+        _ => quoted,
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::ffi::c_void;
+
+    use windows::core::PCWSTR;
+    use windows::Win32::Foundation::{HLOCAL, LocalFree};
+    use windows::Win32::UI::Shell::CommandLineToArgvW;
+
+    use crate::wstr::str_to_wcstring;
+
+    /// Splits the arguments using `CommandLineToArgvW`, placing them after a program name (which
+    /// is split using different rules).
+    fn split_arguments(arguments: &str) -> Vec<String> {
+        let command_line = str_to_wcstring(&format!("program {}", arguments));
+        let mut count = 0;
+        let argv = unsafe { CommandLineToArgvW(PCWSTR(command_line.as_ptr()), &mut count) };
+        assert!(!argv.is_null(), "CommandLineToArgvW failed for {:?}", arguments);
+        let args = (1..count.try_into().unwrap())
+            .map(|i| unsafe { (*argv.add(i)).to_string() }.unwrap())
+            .collect();
+        let _ = unsafe { LocalFree(HLOCAL(argv as *mut c_void)) };
+        args
+    }
+
+    fn round_trip(arg: &str) -> Vec<String> {
+        split_arguments(&format!("-d {} --next", quote_argument(arg)))
+    }
+
+    #[test]
+    fn quotes_spaces() {
+        assert_eq!(round_trip("\\\\server\\my share"), ["-d", "\\\\server\\my share", "--next"]);
+    }
+
+    #[test]
+    fn keeps_ampersands_and_percent_signs() {
+        assert_eq!(round_trip("\\\\server\\R&D"), ["-d", "\\\\server\\R&D", "--next"]);
+        assert_eq!(round_trip("\\\\server\\100%\\%USERNAME%"), ["-d", "\\\\server\\100%\\%USERNAME%", "--next"]);
+    }
+
+    #[test]
+    fn doubles_trailing_backslashes() {
+        assert_eq!(quote_argument("X:\\"), "\"X:\\\\\"");
+        assert_eq!(round_trip("X:\\"), ["-d", "X:\\", "--next"]);
+    }
+
+    #[test]
+    fn escapes_quotes() {
+        assert_eq!(round_trip("say \"hi\"\\"), ["-d", "say \"hi\"\\", "--next"]);
+        assert_eq!(round_trip("a\\\"b"), ["-d", "a\\\"b", "--next"]);
+    }
+
+    #[test]
+    fn inserts_path_at_placeholder_or_end() {
+        let path = "\\\\server\\my share";
+        assert_eq!(split_arguments(&open_with_parameters(Some("--tab {} --focus"), path)), ["--tab", path, "--focus"]);
+        assert_eq!(split_arguments(&open_with_parameters(Some("--new-window "), path)), ["--new-window", path]);
+        assert_eq!(split_arguments(&open_with_parameters(None, path)), [path]);
+    }
+
+    #[test]
+    fn quotes_empty_argument() {
+        assert_eq!(round_trip(""), ["-d", "", "--next"]);
+    }
+}
@@ -0,0 +1,76 @@
+//! Coloring of success and failure lines on the console.
+//!
+//! Color is used on a stream only if it is attached to a console, the `NO_COLOR` environment
+//! variable is unset or empty and `--no-color` has not been passed. The console is switched to
+//! virtual terminal processing so that it interprets the ANSI escape sequences; if that fails,
+//! no color is used.
+
+
+use std::env;
+use std::sync::OnceLock;
+
+use windows::Win32::System::Console::{
+    CONSOLE_MODE, ENABLE_VIRTUAL_TERMINAL_PROCESSING, GetConsoleMode, GetStdHandle, SetConsoleMode,
+    STD_ERROR_HANDLE, STD_HANDLE, STD_OUTPUT_HANDLE,
+};
+
+
+/// Whether color is used on stdout and stderr, respectively.
+static ENABLED: OnceLock<(bool, bool)> = OnceLock::new();
+
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub(crate) enum Stream {
+    Stdout,
+    Stderr,
+}
+
+
+fn enable_on_console(std_handle: STD_HANDLE) -> bool {
+    let handle = match unsafe { GetStdHandle(std_handle) } {
+        Ok(h) => h,
+        Err(_) => return false,
+    };
+    let mut mode = CONSOLE_MODE::default();
+    if unsafe { GetConsoleMode(handle, &mut mode) }.is_err() {
+        // not a console (e.g. redirected to a file or a pipe)
+        return false;
+    }
+    unsafe { SetConsoleMode(handle, mode | ENABLE_VIRTUAL_TERMINAL_PROCESSING) }.is_ok()
+}
+
+/// Decides whether color is used. Must be called before anything is colored; otherwise, no color
+/// is used.
+pub(crate) fn init(no_color: bool) {
+    let no_color = no_color
+        || env::var_os("NO_COLOR").map(|v| !v.is_empty()).unwrap_or(false);
+    let enabled = if no_color {
+        (false, false)
+    } else {
+        (enable_on_console(STD_OUTPUT_HANDLE), enable_on_console(STD_ERROR_HANDLE))
+    };
+    let _ = ENABLED.set(enabled);
+}
+
+fn paint(stream: Stream, code: &str, text: &str) -> String {
+    let (stdout, stderr) = ENABLED.get().copied().unwrap_or((false, false));
+    let enabled = match stream {
+        Stream::Stdout => stdout,
+        Stream::Stderr => stderr,
+    };
+    if enabled {
+        format!("\x1B[{}m{}\x1B[0m", code, text)
+    } else {
+        text.to_owned()
+    }
+}
+
+/// Marks text as describing a success, if color is used on the stream.
+pub(crate) fn success(stream: Stream, text: &str) -> String {
+    paint(stream, "32", text)
+}
+
+/// Marks text as describing a failure, if color is used on the stream.
+pub(crate) fn failure(stream: Stream, text: &str) -> String {
+    paint(stream, "31", text)
+}
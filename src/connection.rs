@@ -0,0 +1,1079 @@
+use std::cell::Cell;
+use std::ffi::c_void;
+use std::io::Error as IoError;
+use std::mem::{align_of, size_of, size_of_val};
+use std::ptr::null_mut;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use serde::Serialize;
+use windows::core::{PCWSTR, PWSTR};
+use windows::Win32::Foundation::{
+    ERROR_ACCESS_DENIED, ERROR_ALREADY_ASSIGNED, ERROR_BAD_DEVICE, ERROR_CONNECTION_UNAVAIL,
+    ERROR_MORE_DATA, ERROR_NO_MORE_ITEMS, ERROR_REQ_NOT_ACCEP, ERROR_TIMEOUT, ERROR_TOO_MANY_SESS,
+    HANDLE, NO_ERROR, WIN32_ERROR,
+};
+#[cfg(feature = "modern-prompt")]
+use windows::Win32::Foundation::ERROR_CANCELLED;
+use windows::Win32::NetworkManagement::WNet::{
+    CONNECT_INTERACTIVE, CONNECT_PROMPT, CONNECT_UPDATE_PROFILE, NETRESOURCEW, NET_CONNECT_FLAGS,
+    NET_RESOURCE_SCOPE, NET_RESOURCE_TYPE, RESOURCETYPE_ANY, RESOURCETYPE_DISK, RESOURCETYPE_PRINT,
+    RESOURCE_CONNECTED, RESOURCE_GLOBALNET, RESOURCE_REMEMBERED, WNET_OPEN_ENUM_USAGE,
+    WNetAddConnection2W, WNetCancelConnection2W, WNetCloseEnum, WNetEnumResourceW,
+    WNetGetConnectionW, WNetGetUserW, WNetOpenEnumW,
+};
+
+#[cfg(feature = "credential-manager")]
+use crate::cached::has_stored_credential;
+use crate::dfs::dfs_target_paths;
+use crate::drive::drive_letter_index;
+use crate::error::{ConnectContext, OpenShareError};
+use crate::log::{log, Logger};
+#[cfg(feature = "modern-prompt")]
+use crate::prompt::{default_prompt_message, default_prompt_title, prompt_credentials};
+use crate::request::ShareRequest;
+#[cfg(feature = "smartcard")]
+use crate::smartcard::smartcard_username;
+use crate::summary::CredentialSource;
+use crate::template::expand_comment;
+use crate::unc::{normalize_path, normalized_share_root, UncPath};
+use crate::wstr::{str_to_wcstring, wcstr_to_string};
+
+
+/// The initial size of the buffer that connections are enumerated into, in KiB, unless specified
+/// otherwise.
+pub const DEFAULT_ENUMERATION_BUFFER_KIB: usize = 16;
+
+/// Errors returned when the server refuses further sessions: `ERROR_REQ_NOT_ACCEP` from servers
+/// that have reached their connection limit (e.g. the 20 sessions of client editions of Windows)
+/// and `ERROR_TOO_MANY_SESS` from NetBIOS transports.
+const SERVER_CONNECTION_LIMIT_CODES: [WIN32_ERROR; 2] = [ERROR_REQ_NOT_ACCEP, ERROR_TOO_MANY_SESS];
+
+
+/// An existing connection to a network resource.
+#[derive(Clone, Debug, Eq, PartialEq, Serialize)]
+pub struct Connection {
+    /// The local device the resource is mapped to (e.g. `X:`), or `None` for a deviceless
+    /// connection.
+    pub local_name: Option<String>,
+
+    /// The remote name of the resource as reported by the network provider.
+    pub remote_name: String,
+}
+
+
+/// The type of a connected network resource.
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum ResourceType {
+    /// A shared directory, e.g. a share on a file server.
+    Disk,
+
+    /// A shared printer.
+    Print,
+
+    /// A resource of another type, identified by its `dwType` value.
+    Other(u32),
+}
+
+/// An existing connection to a network resource, as enumerated by
+/// [`enumerate_connections`](crate::enumerate_connections).
+#[derive(Clone, Debug, Eq, PartialEq, Serialize)]
+pub struct ConnectionInfo {
+    /// The remote name of the resource as reported by the network provider.
+    pub remote_name: Option<String>,
+
+    /// The local device the resource is mapped to (e.g. `X:` or `LPT1`), or `None` for a
+    /// deviceless connection.
+    pub local_name: Option<String>,
+
+    /// The name of the network provider that owns the connection.
+    pub provider: Option<String>,
+
+    /// The type of the resource.
+    pub resource_type: ResourceType,
+}
+
+
+/// Which resources are enumerated as the existing connections, e.g. when checking whether a share
+/// is connected already or when listing the connections; see [`EnumerationOptions::scope`].
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub enum ConnectionScope {
+    /// The connections that are currently established (`RESOURCE_CONNECTED`).
+    #[default]
+    Connected,
+
+    /// The connections remembered in the user's profile to be restored at logon, whether they are
+    /// currently established or not (`RESOURCE_REMEMBERED`).
+    Remembered,
+
+    /// The top level of the network, i.e. the network providers and their containers, instead of
+    /// any connections (`RESOURCE_GLOBALNET`).
+    GlobalNet,
+}
+impl ConnectionScope {
+    fn net_resource_scope(self) -> NET_RESOURCE_SCOPE {
+        match self {
+            Self::Connected => RESOURCE_CONNECTED,
+            Self::Remembered => RESOURCE_REMEMBERED,
+            Self::GlobalNet => RESOURCE_GLOBALNET,
+        }
+    }
+}
+
+
+/// How the existing connections are enumerated, e.g. when checking whether a share is connected
+/// already or when listing the connections; see
+/// [`ShareRequest::enumeration`](crate::ShareRequest::enumeration).
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct EnumerationOptions {
+    /// Log every resource encountered with all its fields, at
+    /// [`LogLevel::Debug`](crate::LogLevel::Debug).
+    pub dump: bool,
+
+    /// The initial size of the buffer that the connections are enumerated into, in KiB (default:
+    /// [`DEFAULT_ENUMERATION_BUFFER_KIB`]). Larger buffers need fewer calls if there are many
+    /// connections; the buffer grows if a single entry does not fit.
+    pub buffer_kib: usize,
+
+    /// How long an enumeration may take; `None` (the default) removes the limit. An enumeration
+    /// that takes longer is abandoned with a warning, and a share not found by then counts as not
+    /// connected.
+    pub max_time: Option<Duration>,
+
+    /// Which resources are enumerated (default: [`ConnectionScope::Connected`]). This affects
+    /// checking whether a share is connected already (with [`ConnectionScope::Remembered`], a
+    /// remembered mapping counts as connected even if it is not currently established), counting
+    /// the connections for [`ShareRequest::max_connections`](crate::ShareRequest::max_connections),
+    /// finding the holder of a drive and [`enumerate_connections`](crate::enumerate_connections).
+    /// Connecting itself is not affected, as `WNetAddConnection2W` ignores the scope; whether a
+    /// mapping is remembered is up to the `CONNECT_UPDATE_PROFILE` flag. With
+    /// [`ConnectionScope::GlobalNet`], the network providers are enumerated instead of
+    /// connections, which is only useful for listing them.
+    pub scope: ConnectionScope,
+}
+impl Default for EnumerationOptions {
+    fn default() -> Self {
+        Self {
+            dump: false,
+            buffer_kib: DEFAULT_ENUMERATION_BUFFER_KIB,
+            max_time: None,
+            scope: ConnectionScope::Connected,
+        }
+    }
+}
+
+
+/// Serializes enumerations of the existing connections when shares are connected concurrently.
+static ENUMERATION_LOCK: Mutex<()> = Mutex::new(());
+
+
+/// An open enumeration handle, closed when dropped; failing to close it is logged to the logger.
+struct EnumHandle<'a>(HANDLE, Logger<'a>);
+impl Drop for EnumHandle<'_> {
+    fn drop(&mut self) {
+        let logger = self.1;
+        let result = unsafe { WNetCloseEnum(self.0) };
+        if result != NO_ERROR {
+            log!(logger, Warning, Check, code = result.0, "failed to close existing connection enumeration! {}", IoError::from_raw_os_error(result.0 as i32));
+        }
+    }
+}
+
+/// A wide password buffer that is zeroed when dropped, so that the password does not linger in
+/// freed memory however connecting ends.
+struct ZeroedOnDrop(Option<Vec<u16>>);
+impl Drop for ZeroedOnDrop {
+    fn drop(&mut self) {
+        if let Some(buffer) = &mut self.0 {
+            buffer.fill(0);
+        }
+    }
+}
+
+
+fn opt_pwstr(buf: &mut Option<Vec<u16>>) -> PWSTR {
+    match buf {
+        Some(b) => PWSTR(b.as_mut_ptr()),
+        None => PWSTR(null_mut()),
+    }
+}
+
+fn opt_pcwstr(buf: &Option<Vec<u16>>) -> PCWSTR {
+    match buf {
+        Some(b) => PCWSTR(b.as_ptr()),
+        None => PCWSTR::null(),
+    }
+}
+
+fn pwstr_debug(s: PWSTR) -> String {
+    if s.0.is_null() {
+        "NULL".to_owned()
+    } else {
+        format!("{:?}", wcstr_to_string(s.0))
+    }
+}
+
+fn describe_resource(net_resource: &NETRESOURCEW) -> String {
+    let lines = [
+        "NETRESOURCEW {".to_owned(),
+        format!("  dwScope: 0x{:08X}", net_resource.dwScope.0),
+        format!("  dwType: 0x{:08X}", net_resource.dwType.0),
+        format!("  dwDisplayType: 0x{:08X}", net_resource.dwDisplayType),
+        format!("  dwUsage: 0x{:08X}", net_resource.dwUsage),
+        format!("  lpLocalName: {}", pwstr_debug(net_resource.lpLocalName)),
+        format!("  lpRemoteName: {}", pwstr_debug(net_resource.lpRemoteName)),
+        format!("  lpComment: {}", pwstr_debug(net_resource.lpComment)),
+        format!("  lpProvider: {}", pwstr_debug(net_resource.lpProvider)),
+        "}".to_owned(),
+    ];
+    lines.join("\n")
+}
+
+fn dump_netresource(net_resource: &NETRESOURCEW, username: &Option<Vec<u16>>, password: &Option<Vec<u16>>, flags: NET_CONNECT_FLAGS, logger: Logger<'_>) {
+    let username_debug = match username {
+        Some(u) => format!("{:?}", wcstr_to_string(u.as_ptr())),
+        None => "NULL".to_owned(),
+    };
+    log!(
+        logger, Debug, Connect,
+        "{}\nlpUserName: {}\nlpPassword: {}\ndwFlags: 0x{:08X}",
+        describe_resource(net_resource),
+        username_debug,
+        if password.is_some() { "(redacted)" } else { "NULL" },
+        flags.0,
+    );
+}
+
+
+/// Views the first `count` entries of a buffer filled by `WNetEnumResourceW`.
+///
+/// The buffer is allocated as `NETRESOURCEW`s (rather than bytes) so that the entries are aligned
+/// for their pointer fields; the strings they point to are stored after them in the same buffer.
+fn enumerated_resources(buffer: &[NETRESOURCEW], count: u32) -> &[NETRESOURCEW] {
+    debug_assert_eq!(buffer.as_ptr() as usize % align_of::<NETRESOURCEW>(), 0);
+    let count: usize = count.try_into().unwrap();
+    assert!(count <= buffer.len(), "provider reported {} entries for a buffer of {}", count, buffer.len());
+    &buffer[..count]
+}
+
+
+/// Calls `visit` with the entries of each batch obtained from `next_batch` until it returns `true`.
+///
+/// `next_batch` fills the buffer like `WNetEnumResourceW`, returning its result and the number of
+/// entries written. The enumeration ends with `ERROR_NO_MORE_ITEMS`; a batch of zero entries
+/// returned with `NO_ERROR` is skipped, as more entries may follow in the next batch. If the buffer
+/// is too small for even one entry, `next_batch` returns `ERROR_MORE_DATA` with the required size
+/// in bytes instead of the number of entries; the buffer is then enlarged and the batch retried.
+///
+/// Returns the Win32 error code if a batch could not be obtained; the reason is also logged.
+fn drain_batches<B, F>(buffer: &mut Vec<NETRESOURCEW>, mut next_batch: B, mut visit: F, logger: Logger<'_>) -> Result<(), u32>
+where
+    B: FnMut(&mut [NETRESOURCEW]) -> (WIN32_ERROR, u32),
+    F: FnMut(&NETRESOURCEW) -> bool,
+{
+    loop {
+        let (result, count) = next_batch(buffer);
+        if result == ERROR_NO_MORE_ITEMS {
+            return Ok(());
+        } else if result == ERROR_MORE_DATA {
+            let required: usize = count.try_into().unwrap();
+            let new_len = (required.div_ceil(size_of::<NETRESOURCEW>())).max(buffer.len() * 2);
+            buffer.resize(new_len, NETRESOURCEW::default());
+            continue;
+        } else if result != NO_ERROR {
+            log!(logger, Error, Check, code = result.0, "failed to obtain more connection enumeration results! {}", IoError::from_raw_os_error(result.0 as i32));
+            return Err(result.0);
+        }
+
+        for resource in enumerated_resources(buffer, count) {
+            if visit(resource) {
+                return Ok(());
+            }
+        }
+    }
+}
+
+/// Calls `visit` with each connected resource of the given type until it returns `true`.
+///
+/// Returns the Win32 error code if the connections could not be enumerated (completely); the
+/// reason is also logged.
+fn visit_resources<F: FnMut(&NETRESOURCEW) -> bool>(
+    resource_type: NET_RESOURCE_TYPE,
+    options: &EnumerationOptions,
+    mut visit: F,
+    logger: Logger<'_>,
+) -> Result<(), u32> {
+    // a poisoned lock only means another enumeration panicked; the lock protects no data
+    let _guard = ENUMERATION_LOCK.lock()
+        .unwrap_or_else(|e| e.into_inner());
+
+    let mut enum_handle = HANDLE(null_mut());
+    let result = unsafe {
+        WNetOpenEnumW(
+            options.scope.net_resource_scope(),
+            resource_type,
+            WNET_OPEN_ENUM_USAGE(0),
+            None,
+            &mut enum_handle,
+        )
+    };
+    if result != NO_ERROR {
+        log!(logger, Error, Check, code = result.0, "failed to enumerate existing connections! {}", IoError::from_raw_os_error(result.0 as i32));
+        return Err(result.0);
+    }
+    let enum_handle = EnumHandle(enum_handle, logger);
+
+    let deadline = options.max_time.map(|limit| Instant::now() + limit);
+    let timed_out = Cell::new(false);
+    let past_deadline = || {
+        if deadline.is_some_and(|d| Instant::now() >= d) {
+            timed_out.set(true);
+        }
+        timed_out.get()
+    };
+
+    let buffer_bytes = options.buffer_kib * 1024;
+    let mut buffer = vec![NETRESOURCEW::default(); buffer_bytes / size_of::<NETRESOURCEW>()];
+    let outcome = drain_batches(
+        &mut buffer,
+        |batch| {
+            if past_deadline() {
+                return (ERROR_NO_MORE_ITEMS, 0);
+            }
+            let mut count = -1i32 as u32;
+            let mut buf_size: u32 = size_of_val(batch).try_into().unwrap();
+            let result = unsafe {
+                WNetEnumResourceW(
+                    enum_handle.0,
+                    &mut count,
+                    batch.as_mut_ptr() as *mut c_void,
+                    &mut buf_size,
+                )
+            };
+            if result == ERROR_MORE_DATA { (result, buf_size) } else { (result, count) }
+        },
+        |resource| {
+            if past_deadline() {
+                return true;
+            }
+            if options.dump {
+                log!(logger, Debug, Check, "{}", describe_resource(resource));
+            }
+            visit(resource)
+        },
+        logger,
+    );
+    drop(enum_handle);
+
+    if timed_out.get() {
+        log!(logger, Warning, Check, code = ERROR_TIMEOUT.0, "stopped enumerating existing connections after {:?}; the result is incomplete", options.max_time.unwrap_or_default());
+        return Err(ERROR_TIMEOUT.0);
+    }
+    outcome
+}
+
+/// Calls `visit` with each connected disk resource until it returns `true`.
+///
+/// Returns `false` if the connections could not be enumerated (completely); the reason is also
+/// logged.
+fn visit_connections<F: FnMut(&NETRESOURCEW) -> bool>(options: &EnumerationOptions, visit: F, logger: Logger<'_>) -> bool {
+    visit_resources(RESOURCETYPE_DISK, options, visit, logger).is_ok()
+}
+
+
+/// Looks for an existing connection to the given path, returning its remote name as reported by
+/// the network provider.
+///
+/// Paths are compared by their [share root](normalized_share_root), so a connection to a share
+/// also counts for a path inside it and vice versa. If `path` is a DFS path, a
+/// connection to any of its link targets also counts; see the [`dfs`](crate::dfs) module. If
+/// `drive` is given, the connection must also be mapped to that local device.
+pub(crate) fn find_existing_connection(path: &str, drive: Option<&str>, options: &EnumerationOptions, logger: Logger<'_>) -> Option<String> {
+    let mut candidates = vec![normalized_share_root(path)];
+    candidates.extend(dfs_target_paths(path).iter().map(|p| normalized_share_root(p)));
+    let drive_lower = drive.map(|d| d.to_lowercase());
+
+    // check if a connection exists already
+    let mut found = None;
+    let enumerated = visit_connections(options, |st| {
+        if st.lpRemoteName.0.is_null() {
+            return false;
+        }
+        let remote_path = wcstr_to_string(st.lpRemoteName.0);
+        let remote_root = normalized_share_root(&remote_path);
+        log!(logger, Debug, Check, "testing against path: {:?}", remote_root);
+        if !candidates.contains(&remote_root) {
+            return false;
+        }
+        if let Some(dl) = &drive_lower {
+            if st.lpLocalName.0.is_null() {
+                return false;
+            }
+            let local_name_lower = wcstr_to_string(st.lpLocalName.0)
+                .to_lowercase();
+            if &local_name_lower != dl {
+                return false;
+            }
+        }
+
+        // we know this path!
+        found = Some(remote_path);
+        true
+    }, logger);
+    if !enumerated && found.is_none() {
+        log!(logger, Info, Check, "assuming connection is not yet open...");
+    }
+
+    found
+}
+
+/// Finds the connections matching the given name, which is either a local device (e.g. `X:`) or a
+/// remote name compared in its [normalized](normalize_path) form.
+pub(crate) fn find_matching_connections(name: &str, options: &EnumerationOptions, logger: Logger<'_>) -> Vec<Connection> {
+    let is_device = drive_letter_index(name).is_some();
+    let name_normalized = normalize_path(name);
+    let mut matches = Vec::new();
+    visit_connections(options, |st| {
+        if st.lpRemoteName.0.is_null() {
+            return false;
+        }
+        let local_name = if st.lpLocalName.0.is_null() {
+            None
+        } else {
+            Some(wcstr_to_string(st.lpLocalName.0))
+        };
+        let remote_name = wcstr_to_string(st.lpRemoteName.0);
+        let is_match = if is_device {
+            local_name.as_deref().map(|l| l.eq_ignore_ascii_case(name)).unwrap_or(false)
+        } else {
+            normalize_path(&remote_name) == name_normalized
+        };
+        if is_match {
+            matches.push(Connection {
+                local_name,
+                remote_name,
+            });
+        }
+        false
+    }, logger);
+    matches
+}
+
+/// Finds the existing disk connections to any share on the given server.
+pub(crate) fn find_server_connections(server: &str, options: &EnumerationOptions, logger: Logger<'_>) -> Vec<Connection> {
+    let mut matches = Vec::new();
+    visit_connections(options, |st| {
+        if st.lpRemoteName.0.is_null() {
+            return false;
+        }
+        let remote_name = wcstr_to_string(st.lpRemoteName.0);
+        let is_match = UncPath::parse(&remote_name)
+            .map(|unc| unc.server.eq_ignore_ascii_case(server))
+            .unwrap_or(false);
+        if is_match {
+            let local_name = if st.lpLocalName.0.is_null() {
+                None
+            } else {
+                Some(wcstr_to_string(st.lpLocalName.0))
+            };
+            matches.push(Connection {
+                local_name,
+                remote_name,
+            });
+        }
+        false
+    }, logger);
+    matches
+}
+
+fn opt_wcstr_to_string(s: PWSTR) -> Option<String> {
+    if s.0.is_null() {
+        None
+    } else {
+        Some(wcstr_to_string(s.0))
+    }
+}
+
+/// Describes an enumerated resource.
+fn connection_info(resource: &NETRESOURCEW) -> ConnectionInfo {
+    let resource_type = match resource.dwType {
+        RESOURCETYPE_DISK => ResourceType::Disk,
+        RESOURCETYPE_PRINT => ResourceType::Print,
+        other => ResourceType::Other(other.0),
+    };
+    ConnectionInfo {
+        remote_name: opt_wcstr_to_string(resource.lpRemoteName),
+        local_name: opt_wcstr_to_string(resource.lpLocalName),
+        provider: opt_wcstr_to_string(resource.lpProvider),
+        resource_type,
+    }
+}
+
+/// The key that [`sort_connections`] orders connections by.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub enum ConnectionSort {
+    /// By server, then share, then local name.
+    #[default]
+    Remote,
+
+    /// By local name (connections without one last), then as with [`Remote`](Self::Remote).
+    Local,
+
+    /// By provider, then as with [`Remote`](Self::Remote).
+    Provider,
+}
+
+/// The server, share and local name of a connection, compared case-insensitively. Remote names
+/// that are not UNC paths are compared as a whole.
+fn remote_sort_key(info: &ConnectionInfo) -> (String, String, String) {
+    let remote = info.remote_name.as_deref().unwrap_or("");
+    let (server, share) = match UncPath::parse(remote) {
+        Some(unc) => (unc.server.to_lowercase(), unc.share.to_lowercase()),
+        None => (remote.to_lowercase(), String::new()),
+    };
+    let local = info.local_name.as_deref().unwrap_or("").to_lowercase();
+    (server, share, local)
+}
+
+/// Sorts the connections by the given key.
+///
+/// `WNetEnumResourceW` returns the connections in no particular order, which may change between
+/// calls; sorting makes listings comparable.
+pub fn sort_connections(connections: &mut [ConnectionInfo], key: ConnectionSort) {
+    match key {
+        ConnectionSort::Remote => connections.sort_by_cached_key(remote_sort_key),
+        ConnectionSort::Local => connections.sort_by_cached_key(|c| (
+            c.local_name.is_none(),
+            c.local_name.as_deref().unwrap_or("").to_lowercase(),
+            remote_sort_key(c),
+        )),
+        ConnectionSort::Provider => connections.sort_by_cached_key(|c| (
+            c.provider.as_deref().unwrap_or("").to_lowercase(),
+            remote_sort_key(c),
+        )),
+    }
+}
+
+/// Lists all existing connections, of any resource type, sorted by [`ConnectionSort::Remote`].
+pub(crate) fn enumerate_connections(options: &EnumerationOptions, logger: Logger<'_>) -> Result<Vec<ConnectionInfo>, OpenShareError> {
+    let mut connections = Vec::new();
+    visit_resources(RESOURCETYPE_ANY, options, |resource| {
+        connections.push(connection_info(resource));
+        false
+    }, logger).map_err(|code| OpenShareError::Enumerate { code })?;
+    sort_connections(&mut connections, ConnectionSort::Remote);
+    Ok(connections)
+}
+
+/// Counts the existing disk connections, both mapped to drives and deviceless ones.
+///
+/// Returns `None` if the connections could not be enumerated.
+pub(crate) fn count_connections(options: &EnumerationOptions, logger: Logger<'_>) -> Option<usize> {
+    let mut count = 0;
+    let enumerated = visit_connections(options, |_| {
+        count += 1;
+        false
+    }, logger);
+    if enumerated { Some(count) } else { None }
+}
+
+/// Looks for the connection mapped to the given local device, returning its remote name.
+pub(crate) fn find_device_connection(device: &str, options: &EnumerationOptions, logger: Logger<'_>) -> Option<String> {
+    let mut found = None;
+    visit_connections(options, |st| {
+        if st.lpLocalName.0.is_null() || st.lpRemoteName.0.is_null() {
+            return false;
+        }
+        if !wcstr_to_string(st.lpLocalName.0).eq_ignore_ascii_case(device) {
+            return false;
+        }
+        found = Some(wcstr_to_string(st.lpRemoteName.0));
+        true
+    }, logger);
+    found
+}
+
+/// What a local device is connected to.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub(crate) enum DeviceConnection {
+    /// The device is not connected.
+    None,
+
+    /// The device is connected to the given remote name.
+    Connected(String),
+
+    /// The device is remembered as connected to the given remote name, but the connection is
+    /// currently not established.
+    Unavailable(String),
+}
+
+/// Finds out what the given local device (e.g. `X:`) is connected to.
+pub(crate) fn device_connection(device: &str) -> DeviceConnection {
+    let device_windows = str_to_wcstring(device);
+    let mut buffer = vec![0u16; 1024];
+    let mut length: u32 = buffer.len().try_into().unwrap();
+    let result = unsafe {
+        WNetGetConnectionW(
+            PCWSTR(device_windows.as_ptr()),
+            PWSTR(buffer.as_mut_ptr()),
+            &mut length,
+        )
+    };
+    if result == NO_ERROR {
+        DeviceConnection::Connected(wcstr_to_string(buffer.as_ptr()))
+    } else if result == ERROR_CONNECTION_UNAVAIL {
+        DeviceConnection::Unavailable(wcstr_to_string(buffer.as_ptr()))
+    } else {
+        DeviceConnection::None
+    }
+}
+
+/// The user name that the connection of the given local device or remote name was made with, as
+/// reported by `WNetGetUserW`, or `None` if it cannot be determined.
+pub fn connection_user(name: &str) -> Option<String> {
+    let name_windows = str_to_wcstring(name);
+    let mut buffer = vec![0u16; 256];
+    loop {
+        let mut length: u32 = buffer.len().try_into().unwrap();
+        let result = unsafe {
+            WNetGetUserW(
+                PCWSTR(name_windows.as_ptr()),
+                PWSTR(buffer.as_mut_ptr()),
+                &mut length,
+            )
+        };
+        if result == NO_ERROR {
+            let user = wcstr_to_string(buffer.as_ptr());
+            return (!user.is_empty()).then_some(user);
+        }
+        let required = usize::try_from(length).unwrap();
+        if result != ERROR_MORE_DATA || required <= buffer.len() {
+            return None;
+        }
+        buffer.resize(required, 0);
+    }
+}
+
+/// Removes the connection of the given local device or remote name. If `forget` is set, a
+/// persistent connection is also no longer restored at logon. Unless `force` is set, this fails if
+/// files are open on the connection.
+pub(crate) fn disconnect(name: &str, forget: bool, force: bool, logger: Logger<'_>) -> Result<(), OpenShareError> {
+    let name_windows = str_to_wcstring(name);
+    let cancel_flags = if forget { CONNECT_UPDATE_PROFILE } else { NET_CONNECT_FLAGS(0) };
+    let result = unsafe {
+        WNetCancelConnection2W(PCWSTR(name_windows.as_ptr()), cancel_flags, force)
+    };
+    if result != NO_ERROR {
+        return Err(OpenShareError::Disconnect { code: result.0 });
+    }
+    log!(logger, Success, Disconnect, "disconnected!");
+    Ok(())
+}
+
+/// Determines where the credentials used to connect for the request come from; see
+/// [`CredentialSource`]. `prompted` is whether they have been asked for already, `stored` whether
+/// the Credential Manager holds credentials for the server.
+fn classify_credentials(request: &ShareRequest, prompted: bool, stored: bool) -> CredentialSource {
+    if request.smartcard() {
+        return CredentialSource::Smartcard;
+    }
+    if prompted {
+        return CredentialSource::Prompt;
+    }
+    match (request.username(), request.password()) {
+        (Some(""), Some("")) => CredentialSource::Guest,
+        (Some(username), _) if username.starts_with("@@") => CredentialSource::MarshalledCredential,
+        (_, Some(_)) => request.credential_source(),
+        _ if stored => CredentialSource::CredentialManager,
+        (None, None) => CredentialSource::CurrentUser,
+        (Some(_), None) if (request.flags().0 & CONNECT_INTERACTIVE.0) != 0 => CredentialSource::Prompt,
+        (Some(_), None) => request.credential_source(),
+    }
+}
+
+/// Whether connecting for the request may use credentials stored in the Credential Manager, as no
+/// password is given but credentials are stored for the server.
+fn uses_stored_credential(request: &ShareRequest) -> bool {
+    if request.password().is_some() {
+        return false;
+    }
+    match request.credential_target() {
+        Some(target) => has_stored_credential(target),
+        None => UncPath::parse(request.path()).is_some_and(|unc| has_stored_credential(unc.server)),
+    }
+}
+
+/// Determines where the credentials will come from when connecting for the request, assuming that
+/// Windows does not prompt on its own.
+pub(crate) fn expected_credential_source(request: &ShareRequest) -> CredentialSource {
+    let prompted = request.modern_prompt() && !request.smartcard() && request.password().is_none();
+    classify_credentials(request, prompted, uses_stored_credential(request))
+}
+
+/// Turns a failure of `WNetAddConnection2W` into the matching error.
+fn add_connection_error(result: WIN32_ERROR, context: ConnectContext) -> OpenShareError {
+    if SERVER_CONNECTION_LIMIT_CODES.contains(&result) {
+        return OpenShareError::ServerConnectionLimit { code: result.0 };
+    }
+    if let (ERROR_BAD_DEVICE, Some(drive)) = (result, &context.drive) {
+        return OpenShareError::InvalidDrive { drive: drive.clone() };
+    }
+    OpenShareError::Connect { code: result.0, context: Some(context) }
+}
+
+/// Asks for the username and password using the credential dialog of current versions of Windows;
+/// see [`ShareRequest::modern_prompt`].
+#[cfg(feature = "modern-prompt")]
+fn prompt_modern(request: &ShareRequest) -> Result<(String, String), OpenShareError> {
+    let title = request.prompt_title()
+        .map(|t| t.to_owned())
+        .unwrap_or_else(|| default_prompt_title(request.path()));
+    let message = request.prompt_message()
+        .map(|m| m.to_owned())
+        .unwrap_or_else(|| default_prompt_message(request.path()));
+    let credentials = prompt_credentials(&title, &message, request.username())?
+        .ok_or(OpenShareError::Connect { code: ERROR_CANCELLED.0, context: None })?;
+    Ok((credentials.username, credentials.password))
+}
+
+#[cfg(not(feature = "modern-prompt"))]
+fn prompt_modern(_request: &ShareRequest) -> Result<(String, String), OpenShareError> {
+    Err(OpenShareError::NotCompiledIn { feature: "modern-prompt" })
+}
+
+#[cfg(not(feature = "smartcard"))]
+fn smartcard_username() -> Result<String, OpenShareError> {
+    Err(OpenShareError::NotCompiledIn { feature: "smartcard" })
+}
+
+/// Without access to the Credential Manager, stored credentials cannot be recognized.
+#[cfg(not(feature = "credential-manager"))]
+fn has_stored_credential(_target: &str) -> bool {
+    false
+}
+
+/// Connects to the requested share, returning where the credentials came from.
+pub(crate) fn connect_to_share(request: &ShareRequest, logger: Logger<'_>) -> Result<CredentialSource, OpenShareError> {
+    if let Some(drive) = request.drive() {
+        // WNetAddConnection2W only reports ERROR_BAD_DEVICE for drives such as "X" or "X:\"
+        if drive_letter_index(drive).is_none() {
+            return Err(OpenShareError::InvalidDrive { drive: drive.to_owned() });
+        }
+    }
+    let mut path_windows = Some(str_to_wcstring(request.path()));
+    let mut drive_windows = request.drive().map(str_to_wcstring);
+    let mut provider_windows = request.provider().map(str_to_wcstring);
+    let mut comment_windows = request.comment()
+        .map(|c| str_to_wcstring(&expand_comment(c)));
+    let mut username_windows = if request.smartcard() {
+        Some(str_to_wcstring(&smartcard_username()?))
+    } else {
+        request.username().map(str_to_wcstring)
+    };
+    let mut password_windows = ZeroedOnDrop(request.password().map(str_to_wcstring));
+    let mut flags = request.flags();
+    let mut prompted = false;
+
+    if request.modern_prompt() && !request.smartcard() && request.password().is_none() {
+        let (username, password) = prompt_modern(request)?;
+        username_windows = Some(str_to_wcstring(&username));
+        password_windows = ZeroedOnDrop(Some(str_to_wcstring(&password)));
+
+        // the credentials have been asked for already
+        flags.0 &= !(CONNECT_INTERACTIVE.0 | CONNECT_PROMPT.0);
+        prompted = true;
+    }
+
+    let net_resource = NETRESOURCEW {
+        dwType: RESOURCETYPE_DISK,
+        lpLocalName: opt_pwstr(&mut drive_windows),
+        lpRemoteName: opt_pwstr(&mut path_windows),
+        lpProvider: opt_pwstr(&mut provider_windows),
+
+        dwDisplayType: 0,
+        dwUsage: 0,
+        // ignored by WNetAddConnection2W, so the enumeration scope does not apply here
+        dwScope: NET_RESOURCE_SCOPE(0),
+        lpComment: opt_pwstr(&mut comment_windows),
+    };
+
+    if request.dump_netresource() {
+        dump_netresource(&net_resource, &username_windows, &password_windows.0, flags, logger);
+    }
+
+    let add_connection = || unsafe {
+        WNetAddConnection2W(
+            &net_resource,
+            opt_pcwstr(&password_windows.0),
+            opt_pcwstr(&username_windows),
+            flags,
+        )
+    };
+    let mut result = add_connection();
+    if let (true, Some(drive)) = (result == ERROR_ALREADY_ASSIGNED, request.drive()) {
+        let holder = find_device_connection(drive, request.enumeration(), logger);
+        if !request.force() {
+            return Err(OpenShareError::DriveInUse { drive: drive.to_owned(), holder });
+        }
+        log!(
+            logger, Info, Connect,
+            "{} is mapped to {}; disconnecting it...",
+            drive, holder.as_deref().unwrap_or("another share"),
+        );
+        disconnect(drive, true, false, logger)?;
+        result = add_connection();
+    }
+    if result != NO_ERROR {
+        let is_admin_share = UncPath::parse(request.path())
+            .map(|unc| unc.is_administrative_share())
+            .unwrap_or(false);
+        if result == ERROR_ACCESS_DENIED && is_admin_share {
+            log!(logger, Info, Connect, "hint: administrative shares (ending in $) require credentials of an administrator of the target computer");
+        }
+        let context = ConnectContext::new(request, username_windows.is_some(), password_windows.0.is_some(), flags);
+        return Err(add_connection_error(result, context));
+    }
+    log!(logger, Success, Connect, "connected!");
+
+    let source = classify_credentials(request, prompted, uses_stored_credential(request));
+    log!(logger, Debug, Connect, "credentials: {}", source);
+    Ok(source)
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn bad_device_becomes_invalid_drive() {
+        let request = ShareRequest::builder("\\\\server\\share")
+            .drive("X:\\")
+            .build();
+        let context = ConnectContext::new(&request, false, false, request.flags());
+        assert!(matches!(
+            add_connection_error(ERROR_BAD_DEVICE, context.clone()),
+            OpenShareError::InvalidDrive { drive } if drive == "X:\\"
+        ));
+        assert!(matches!(
+            add_connection_error(ERROR_ACCESS_DENIED, context),
+            OpenShareError::Connect { code, .. } if code == ERROR_ACCESS_DENIED.0
+        ));
+
+        // without a drive, the error is about something else
+        let request = ShareRequest::builder("\\\\server\\share").build();
+        let context = ConnectContext::new(&request, false, false, request.flags());
+        assert!(matches!(
+            add_connection_error(ERROR_BAD_DEVICE, context),
+            OpenShareError::Connect { code, .. } if code == ERROR_BAD_DEVICE.0
+        ));
+    }
+
+    #[test]
+    fn enumerated_resources_reads_first_entry() {
+        let mut buffer = vec![NETRESOURCEW::default(); 4];
+        let name = str_to_wcstring("\\\\server\\share");
+
+        // store the string behind the entry, as the provider would
+        let string_ptr = buffer[1..].as_mut_ptr() as *mut u16;
+        unsafe { string_ptr.copy_from_nonoverlapping(name.as_ptr(), name.len()) };
+        buffer[0].dwType = RESOURCETYPE_DISK;
+        buffer[0].lpRemoteName = PWSTR(string_ptr);
+
+        let entries = enumerated_resources(&buffer, 1);
+        assert_eq!(entries.len(), 1);
+        assert_eq!(entries[0].dwType, RESOURCETYPE_DISK);
+        assert!(entries[0].lpLocalName.0.is_null());
+        assert_eq!(wcstr_to_string(entries[0].lpRemoteName.0), "\\\\server\\share");
+    }
+
+    /// Simulates a provider returning the given batches, each a result and the remote names of its
+    /// entries, and collects the visited remote names.
+    fn drain_mock_batches(batches: &[(WIN32_ERROR, &[&str])]) -> (Result<(), u32>, Vec<String>) {
+        let mut strings: Vec<Vec<u16>> = Vec::new();
+        let mut remaining = batches.iter();
+        let mut buffer = vec![NETRESOURCEW::default(); 4];
+        let mut visited = Vec::new();
+        let outcome = drain_batches(
+            &mut buffer,
+            |batch| {
+                let (result, names) = remaining.next().expect("enumerated past the last batch");
+                for (entry, name) in batch.iter_mut().zip(names.iter()) {
+                    strings.push(str_to_wcstring(name));
+                    entry.lpRemoteName = PWSTR(strings.last_mut().unwrap().as_mut_ptr());
+                }
+                (*result, names.len().try_into().unwrap())
+            },
+            |resource| {
+                visited.push(wcstr_to_string(resource.lpRemoteName.0));
+                false
+            },
+            None,
+        );
+        (outcome, visited)
+    }
+
+    #[test]
+    fn drain_batches_skips_empty_batch_before_more_items() {
+        let (outcome, visited) = drain_mock_batches(&[
+            (NO_ERROR, &[]),
+            (NO_ERROR, &["\\\\server\\one", "\\\\server\\two"]),
+            (ERROR_NO_MORE_ITEMS, &[]),
+        ]);
+        assert_eq!(outcome, Ok(()));
+        assert_eq!(visited, ["\\\\server\\one", "\\\\server\\two"]);
+    }
+
+    #[test]
+    fn drain_batches_accepts_empty_batch_before_end() {
+        let (outcome, visited) = drain_mock_batches(&[
+            (NO_ERROR, &[]),
+            (ERROR_NO_MORE_ITEMS, &[]),
+        ]);
+        assert_eq!(outcome, Ok(()));
+        assert!(visited.is_empty());
+    }
+
+    #[test]
+    fn drain_batches_reports_error_after_empty_batch() {
+        let (outcome, visited) = drain_mock_batches(&[
+            (NO_ERROR, &[]),
+            (ERROR_ACCESS_DENIED, &[]),
+        ]);
+        assert_eq!(outcome, Err(ERROR_ACCESS_DENIED.0));
+        assert!(visited.is_empty());
+    }
+
+    #[test]
+    fn drain_batches_grows_buffer_on_more_data() {
+        let mut buffer = vec![NETRESOURCEW::default(); 1];
+        let required: u32 = (3 * size_of::<NETRESOURCEW>()).try_into().unwrap();
+        let mut batch_lens = Vec::new();
+        let outcome = drain_batches(
+            &mut buffer,
+            |batch| {
+                batch_lens.push(batch.len());
+                match batch_lens.len() {
+                    1 => (ERROR_MORE_DATA, required),
+                    2 => (NO_ERROR, 1),
+                    _ => (ERROR_NO_MORE_ITEMS, 0),
+                }
+            },
+            |_| false,
+            None,
+        );
+        assert_eq!(outcome, Ok(()));
+        assert_eq!(batch_lens, [1, 3, 3]);
+    }
+
+    #[test]
+    fn connection_info_converts_fields() {
+        let mut local = str_to_wcstring("X:");
+        let mut remote = str_to_wcstring("\\\\server\\share");
+        let resource = NETRESOURCEW {
+            dwType: RESOURCETYPE_DISK,
+            lpLocalName: PWSTR(local.as_mut_ptr()),
+            lpRemoteName: PWSTR(remote.as_mut_ptr()),
+            ..Default::default()
+        };
+        assert_eq!(
+            connection_info(&resource),
+            ConnectionInfo {
+                remote_name: Some("\\\\server\\share".to_owned()),
+                local_name: Some("X:".to_owned()),
+                provider: None,
+                resource_type: ResourceType::Disk,
+            },
+        );
+    }
+
+    #[test]
+    fn connection_info_keeps_unknown_type() {
+        let resource = NETRESOURCEW {
+            dwType: NET_RESOURCE_TYPE(0x8000_0000),
+            ..Default::default()
+        };
+        let info = connection_info(&resource);
+        assert_eq!(info.resource_type, ResourceType::Other(0x8000_0000));
+        assert_eq!(info.remote_name, None);
+    }
+
+    fn info(remote: &str, local: Option<&str>, provider: &str) -> ConnectionInfo {
+        ConnectionInfo {
+            remote_name: Some(remote.to_owned()),
+            local_name: local.map(|l| l.to_owned()),
+            provider: Some(provider.to_owned()),
+            resource_type: ResourceType::Disk,
+        }
+    }
+
+    fn remote_and_local(connections: &[ConnectionInfo]) -> Vec<(&str, Option<&str>)> {
+        connections.iter()
+            .map(|c| (c.remote_name.as_deref().unwrap(), c.local_name.as_deref()))
+            .collect()
+    }
+
+    #[test]
+    fn sort_connections_by_each_key() {
+        let mut connections = vec![
+            info("\\\\beta\\docs", Some("Y:"), "WebDAV"),
+            info("\\\\Alpha\\share", None, "SMB"),
+            info("\\\\alpha\\data", Some("Z:"), "SMB"),
+            info("\\\\alpha\\data", Some("X:"), "SMB"),
+        ];
+
+        sort_connections(&mut connections, ConnectionSort::Remote);
+        assert_eq!(remote_and_local(&connections), [
+            ("\\\\alpha\\data", Some("X:")),
+            ("\\\\alpha\\data", Some("Z:")),
+            ("\\\\Alpha\\share", None),
+            ("\\\\beta\\docs", Some("Y:")),
+        ]);
+
+        sort_connections(&mut connections, ConnectionSort::Local);
+        assert_eq!(remote_and_local(&connections), [
+            ("\\\\alpha\\data", Some("X:")),
+            ("\\\\beta\\docs", Some("Y:")),
+            ("\\\\alpha\\data", Some("Z:")),
+            ("\\\\Alpha\\share", None),
+        ]);
+
+        sort_connections(&mut connections, ConnectionSort::Provider);
+        assert_eq!(remote_and_local(&connections), [
+            ("\\\\alpha\\data", Some("X:")),
+            ("\\\\alpha\\data", Some("Z:")),
+            ("\\\\Alpha\\share", None),
+            ("\\\\beta\\docs", Some("Y:")),
+        ]);
+    }
+
+    #[test]
+    fn credential_source_for_each_way_of_connecting() {
+        let base = || ShareRequest::builder("\\\\server\\share");
+        let cases = [
+            (base().smartcard(true).build(), false, CredentialSource::Smartcard),
+            (base().modern_prompt(true).build(), true, CredentialSource::Prompt),
+            (base().username("").password("").build(), false, CredentialSource::Guest),
+            (base().username("@@BAAAAAAA").build(), false, CredentialSource::MarshalledCredential),
+            (base().username("user").password("secret").build(), false, CredentialSource::Explicit),
+            (
+                base().username("user").password("secret").credential_source(CredentialSource::Stdin).build(),
+                false,
+                CredentialSource::Stdin,
+            ),
+            (base().build(), false, CredentialSource::CurrentUser),
+            (base().username("user").build(), false, CredentialSource::Prompt),
+            (base().username("user").flags(NET_CONNECT_FLAGS(0)).build(), false, CredentialSource::Explicit),
+        ];
+        for (request, prompted, expected) in cases {
+            assert_eq!(classify_credentials(&request, prompted, false), expected, "{:?}", request.username());
+        }
+
+        let stored = base().username("user").build();
+        assert_eq!(classify_credentials(&stored, false, true), CredentialSource::CredentialManager);
+        let explicit = base().username("user").password("secret").build();
+        assert_eq!(classify_credentials(&explicit, false, true), CredentialSource::Explicit);
+    }
+
+}
@@ -0,0 +1,99 @@
+//! Copying a local file into a connected share.
+
+
+use std::ffi::c_void;
+use std::path::Path;
+
+use windows::core::PCWSTR;
+use windows::Win32::Foundation::{ERROR_INVALID_NAME, HANDLE};
+use windows::Win32::Storage::FileSystem::{CopyFileExW, LPPROGRESS_ROUTINE_CALLBACK_REASON};
+use windows::Win32::System::WindowsProgramming::{COPY_FILE_FAIL_IF_EXISTS, PROGRESS_CONTINUE};
+
+use crate::error::{OpenShareError, win32_code};
+use crate::unc::{normalized_share_root, UncPath};
+use crate::wstr::str_to_wcstring;
+
+
+/// Receives the progress of [`copy_into_share`]: the number of bytes copied so far and the size
+/// of the file.
+type ProgressCallback<'a> = dyn FnMut(u64, u64) + 'a;
+
+
+/// Determines where [`copy_into_share`] copies the given file: into the root of the share of the
+/// given path, under the file's name. Returns `None` if the destination would not be within that
+/// share, e.g. because the file name is `..` or `share` is not a UNC path.
+pub(crate) fn copy_destination(source: &Path, share: &str) -> Option<String> {
+    let name = source.file_name()?.to_str()?;
+    if name == "." || name == ".." || name.contains(['\\', '/', ':']) {
+        return None;
+    }
+    let unc = UncPath::parse(share)?;
+    let destination = format!("\\\\{}\\{}\\{}", unc.server, unc.share, name);
+    if normalized_share_root(&destination) != normalized_share_root(share) {
+        return None;
+    }
+    Some(destination)
+}
+
+unsafe extern "system" fn report_progress(
+    total_size: i64,
+    transferred: i64,
+    _stream_size: i64,
+    _stream_transferred: i64,
+    _stream_number: u32,
+    _reason: LPPROGRESS_ROUTINE_CALLBACK_REASON,
+    _source: HANDLE,
+    _destination: HANDLE,
+    data: *const c_void,
+) -> u32 {
+    let callback = &mut *(data as *mut &mut ProgressCallback<'_>);
+    callback(transferred.try_into().unwrap_or(0), total_size.try_into().unwrap_or(0));
+    PROGRESS_CONTINUE
+}
+
+/// Copies a local file into the root of the share of the given path, which must be connected.
+/// Returns the path of the copy.
+///
+/// An existing file of the same name is only replaced if `overwrite` is set; otherwise, copying
+/// fails with `ERROR_FILE_EXISTS`. `progress` is called repeatedly while copying.
+pub fn copy_into_share<F: FnMut(u64, u64)>(
+    source: &Path,
+    share: &str,
+    overwrite: bool,
+    mut progress: F,
+) -> Result<String, OpenShareError> {
+    let destination = copy_destination(source, share)
+        .ok_or(OpenShareError::CopyIn { code: ERROR_INVALID_NAME.0 })?;
+    let source_windows = str_to_wcstring(&source.to_string_lossy());
+    let destination_windows = str_to_wcstring(&destination);
+    let flags = if overwrite { 0 } else { COPY_FILE_FAIL_IF_EXISTS };
+
+    let mut callback: &mut ProgressCallback<'_> = &mut progress;
+    unsafe {
+        CopyFileExW(
+            PCWSTR(source_windows.as_ptr()),
+            PCWSTR(destination_windows.as_ptr()),
+            Some(report_progress),
+            Some(&mut callback as *mut &mut ProgressCallback<'_> as *const c_void),
+            None,
+            flags,
+        )
+    }.map_err(|e| OpenShareError::CopyIn { code: win32_code(&e) })?;
+    Ok(destination)
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn copy_destination_is_share_root() {
+        assert_eq!(
+            copy_destination(Path::new("setup.msi"), "\\\\server\\share\\sub\\dir").as_deref(),
+            Some("\\\\server\\share\\setup.msi"),
+        );
+        assert_eq!(copy_destination(Path::new(".."), "\\\\server\\share"), None);
+        assert_eq!(copy_destination(Path::new("setup.msi"), "C:\\temp"), None);
+    }
+}
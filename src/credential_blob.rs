@@ -0,0 +1,65 @@
+//! Decoding of marshalled credentials passed using `--credential-blob`.
+//!
+//! The expected format is the standard Base64 encoding (RFC 4648 alphabet, padding optional) of
+//! the UTF-8 bytes of a string produced by `CredMarshalCredentialW`, i.e. a string starting with
+//! `@@` such as the marshalled form of a `CERT_CREDENTIAL_INFO` or a
+//! `USERNAME_TARGET_CREDENTIAL_INFO`. After decoding, the string must be accepted by `CredUnmarshalCredentialW`; it is then passed
+//! unchanged as the username to `WNetAddConnection2W`, which recognizes marshalled credentials.
+
+
+use std::ffi::c_void;
+use std::ptr::null_mut;
+
+use windows::core::HSTRING;
+use windows::Win32::Security::Credentials::{
+    CRED_MARSHAL_TYPE, CredFree, CredUnmarshalCredentialW,
+};
+
+
+fn base64_value(c: u8) -> Option<u32> {
+    match c {
+        b'A'..=b'Z' => Some(u32::from(c - b'A')),
+        b'a'..=b'z' => Some(u32::from(c - b'a') + 26),
+        b'0'..=b'9' => Some(u32::from(c - b'0') + 52),
+        b'+' => Some(62),
+        b'/' => Some(63),
+        _ => None,
+    }
+}
+
+fn decode_base64(text: &str) -> Option<Vec<u8>> {
+    let digits = text.trim_end_matches('=').as_bytes();
+    if digits.len() % 4 == 1 {
+        return None;
+    }
+
+    let mut bytes = Vec::with_capacity(digits.len() * 3 / 4);
+    for chunk in digits.chunks(4) {
+        let mut accumulator = 0u32;
+        for &digit in chunk {
+            accumulator = (accumulator << 6) | base64_value(digit)?;
+        }
+        // left-align partial chunks as if they had been padded with zeroes
+        accumulator <<= 6 * (4 - chunk.len());
+        let decoded = accumulator.to_be_bytes();
+        bytes.extend_from_slice(&decoded[1..chunk.len()]);
+    }
+    Some(bytes)
+}
+
+/// Decodes a `--credential-blob` value into the marshalled credential string it contains.
+pub(crate) fn decode_credential_blob(blob: &str) -> Result<String, String> {
+    let bytes = decode_base64(blob)
+        .ok_or_else(|| "--credential-blob is not valid Base64".to_owned())?;
+    let marshalled = String::from_utf8(bytes)
+        .map_err(|_| "--credential-blob does not decode to UTF-8 text".to_owned())?;
+
+    let marshalled_windows = HSTRING::from(marshalled.as_str());
+    let mut cred_type = CRED_MARSHAL_TYPE::default();
+    let mut credential: *mut c_void = null_mut();
+    unsafe { CredUnmarshalCredentialW(&marshalled_windows, &mut cred_type, &mut credential) }
+        .map_err(|e| format!("--credential-blob does not contain a marshalled credential: {}", e.message()))?;
+    unsafe { CredFree(credential) };
+
+    Ok(marshalled)
+}
@@ -0,0 +1,74 @@
+//! Awareness of DFS (Distributed File System) namespaces.
+//!
+//! A path such as `\\domain\dfsroot\link` does not name a share on a specific server; the DFS
+//! client resolves it to one of the link's targets (e.g. `\\fileserver\share`) using referrals.
+//! Depending on the provider, a connection made through such a path may be listed under the
+//! resolved target instead of the path that was passed when connecting.
+//!
+//! The heuristic used here: ask the DFS client (`NetDfsGetClientInfo`) which link the path belongs
+//! to and which targets that link has. For each target, the part of the path beyond the link is
+//! appended to `\\server\share` of the target. An existing connection to any of the resulting
+//! paths is considered a connection to the DFS path. If the path is not a DFS path, or the DFS
+//! client knows nothing about it, no alternatives are produced.
+
+
+use std::ptr::null_mut;
+
+use windows::core::PCWSTR;
+use windows::Win32::NetworkManagement::NetManagement::{NERR_Success, NetApiBufferFree};
+use windows::Win32::Storage::DistributedFileSystem::{DFS_INFO_3, NetDfsGetClientInfo};
+
+use crate::unc::normalize_path;
+use crate::wstr::{str_to_wcstring, wcstr_to_string};
+
+
+/// Returns the paths that the given DFS path resolves to, in [normalized](normalize_path) form.
+///
+/// Returns an empty list if the path is not a DFS path known to the DFS client.
+pub(crate) fn dfs_target_paths(path: &str) -> Vec<String> {
+    let path_windows = str_to_wcstring(path);
+    let mut buffer: *mut u8 = null_mut();
+    let result = unsafe {
+        NetDfsGetClientInfo(
+            PCWSTR(path_windows.as_ptr()),
+            PCWSTR::null(),
+            PCWSTR::null(),
+            3,
+            &mut buffer,
+        )
+    };
+    if result != NERR_Success || buffer.is_null() {
+        return Vec::new();
+    }
+
+    let info = unsafe { &*(buffer as *const DFS_INFO_3) };
+    let mut targets = Vec::new();
+    if !info.EntryPath.0.is_null() {
+        // the entry path is occasionally reported with a single leading backslash
+        let mut entry_path = normalize_path(&wcstr_to_string(info.EntryPath.0));
+        if !entry_path.starts_with("\\\\") {
+            entry_path.insert(0, '\\');
+        }
+
+        let path_normalized = normalize_path(path);
+        if let Some(rest) = path_normalized.strip_prefix(&entry_path) {
+            let storage_count: usize = info.NumberOfStorages.try_into().unwrap();
+            for i in 0..storage_count {
+                let storage = unsafe { &*info.Storage.add(i) };
+                if storage.ServerName.0.is_null() || storage.ShareName.0.is_null() {
+                    continue;
+                }
+                let target = format!(
+                    "\\\\{}\\{}{}",
+                    wcstr_to_string(storage.ServerName.0),
+                    wcstr_to_string(storage.ShareName.0),
+                    rest,
+                );
+                targets.push(normalize_path(&target));
+            }
+        }
+    }
+
+    let _ = unsafe { NetApiBufferFree(Some(buffer as *const _)) };
+    targets
+}
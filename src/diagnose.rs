@@ -0,0 +1,203 @@
+use std::ffi::c_void;
+use std::mem::size_of;
+use std::ptr::null_mut;
+use std::slice;
+
+use windows::core::PSTR;
+use windows::Win32::Foundation::{
+    CloseHandle, ERROR_NETWORK_UNREACHABLE, ERROR_NO_NETWORK, ERROR_NO_NET_OR_BAD_PATH,
+    ERROR_SERVICE_NOT_ACTIVE, HANDLE, LUID,
+};
+use windows::Win32::Security::{
+    GetTokenInformation, IsWellKnownSid, TOKEN_QUERY, TOKEN_USER, TokenUser, WinLocalSystemSid,
+};
+use windows::Win32::System::Threading::{GetCurrentProcess, OpenProcessToken};
+use windows::Win32::Security::Authentication::Identity::{
+    KERB_QUERY_TKT_CACHE_REQUEST, KERB_QUERY_TKT_CACHE_RESPONSE, KerbQueryTicketCacheMessage,
+    LSA_STRING, LsaCallAuthenticationPackage, LsaConnectUntrusted, LsaDeregisterLogonProcess,
+    LsaFreeReturnBuffer, LsaLookupAuthenticationPackage,
+};
+
+use crate::service::{query_service_state, ServiceState, WORKSTATION_SERVICE};
+use crate::unc::UncPath;
+
+
+/// Returned by the network APIs when the Workstation service is not running.
+const NERR_WKSTA_NOT_STARTED: u32 = 2138;
+
+/// Errors that are typically returned when connecting while the Workstation service is stopped,
+/// but that may have other causes as well.
+const NO_NETWORK_CODES: [u32; 4] = [
+    ERROR_NO_NETWORK.0, ERROR_NETWORK_UNREACHABLE.0, ERROR_SERVICE_NOT_ACTIVE.0,
+    ERROR_NO_NET_OR_BAD_PATH.0,
+];
+
+
+/// Checks whether the current logon session holds a Kerberos ticket-granting ticket.
+///
+/// Returns `None` if the Kerberos ticket cache could not be queried.
+pub fn has_kerberos_tgt() -> Option<bool> {
+    let mut lsa_handle = HANDLE(null_mut());
+    let status = unsafe { LsaConnectUntrusted(&mut lsa_handle) };
+    if status.is_err() {
+        return None;
+    }
+
+    let result = query_tgt(lsa_handle);
+
+    let _ = unsafe { LsaDeregisterLogonProcess(lsa_handle) };
+    result
+}
+
+fn query_tgt(lsa_handle: HANDLE) -> Option<bool> {
+    let mut package_name_bytes = *b"Kerberos";
+    let package_name = LSA_STRING {
+        Length: package_name_bytes.len().try_into().unwrap(),
+        MaximumLength: package_name_bytes.len().try_into().unwrap(),
+        Buffer: PSTR(package_name_bytes.as_mut_ptr()),
+    };
+    let mut package = 0u32;
+    let status = unsafe {
+        LsaLookupAuthenticationPackage(lsa_handle, &package_name, &mut package)
+    };
+    if status.is_err() {
+        return None;
+    }
+
+    // a zero LogonId means "the caller's logon session"
+    let request = KERB_QUERY_TKT_CACHE_REQUEST {
+        MessageType: KerbQueryTicketCacheMessage,
+        LogonId: LUID::default(),
+    };
+    let mut response: *mut c_void = null_mut();
+    let mut response_length = 0u32;
+    let mut protocol_status = 0i32;
+    let status = unsafe {
+        LsaCallAuthenticationPackage(
+            lsa_handle,
+            package,
+            &request as *const KERB_QUERY_TKT_CACHE_REQUEST as *const c_void,
+            size_of::<KERB_QUERY_TKT_CACHE_REQUEST>().try_into().unwrap(),
+            Some(&mut response),
+            Some(&mut response_length),
+            Some(&mut protocol_status),
+        )
+    };
+    if status.is_err() || protocol_status < 0 || response.is_null() {
+        if !response.is_null() {
+            let _ = unsafe { LsaFreeReturnBuffer(response) };
+        }
+        return None;
+    }
+
+    let mut found = false;
+    unsafe {
+        let cache = &*(response as *const KERB_QUERY_TKT_CACHE_RESPONSE);
+        let count: usize = cache.CountOfTickets.try_into().unwrap();
+        let tickets = slice::from_raw_parts(cache.Tickets.as_ptr(), count);
+        for ticket in tickets {
+            if ticket.ServerName.Buffer.0.is_null() {
+                continue;
+            }
+            let name_slice = slice::from_raw_parts(
+                ticket.ServerName.Buffer.0,
+                usize::from(ticket.ServerName.Length) / 2,
+            );
+            let name = String::from_utf16_lossy(name_slice);
+            if name.to_lowercase().starts_with("krbtgt/") {
+                found = true;
+                break;
+            }
+        }
+        let _ = LsaFreeReturnBuffer(response);
+    }
+    Some(found)
+}
+
+/// Collects hints as to why connecting to the given path failed with `ERROR_LOGON_FAILURE`.
+pub fn logon_failure_hints(path: &str) -> Vec<String> {
+    let mut hints = Vec::new();
+
+    match UncPath::parse(path) {
+        Some(unc) if unc.server_is_ip_address() => {
+            hints.push(format!(
+                "the server {:?} is specified as an IP address, which forces NTLM authentication; \
+                connect using its host name if the account requires Kerberos",
+                unc.server,
+            ));
+        },
+        Some(unc) => {
+            hints.push(format!(
+                "the server {:?} is specified as a host name, so Kerberos is attempted first; \
+                if the server's SPN (cifs/{}) is missing or duplicated, authentication may fail",
+                unc.server, unc.server,
+            ));
+        },
+        None => {
+            hints.push(format!("{:?} is not a UNC path of the form \\\\server\\share", path));
+        },
+    }
+
+    match has_kerberos_tgt() {
+        Some(true) => hints.push(
+            "this logon session holds a Kerberos ticket-granting ticket; \
+            if Kerberos is in use, check the username and password or try `klist purge`".to_owned()
+        ),
+        Some(false) => hints.push(
+            "this logon session holds no Kerberos ticket-granting ticket; \
+            only NTLM is available unless the domain controller is reachable".to_owned()
+        ),
+        None => hints.push(
+            "the Kerberos ticket cache could not be queried".to_owned()
+        ),
+    }
+
+    hints.push(
+        "verify the username (including any DOMAIN\\ prefix) and password".to_owned()
+    );
+    hints
+}
+
+/// Whether a connection most likely failed with the given Win32 error code because the Workstation
+/// service is not running, as is common shortly after boot.
+///
+/// `NERR_WkstaNotStarted` is conclusive. For the more general "no network" errors, the state of the
+/// service is queried and must be known to be something other than running.
+pub fn workstation_appears_stopped(code: u32) -> bool {
+    if code == NERR_WKSTA_NOT_STARTED {
+        return true;
+    }
+    if !NO_NETWORK_CODES.contains(&code) {
+        return false;
+    }
+    matches!(query_service_state(WORKSTATION_SERVICE), Ok(state) if state != ServiceState::Running)
+}
+
+/// Whether the process runs as the LocalSystem account (e.g. as a service or a scheduled task set
+/// to run as SYSTEM). Network resources are then accessed using the computer's domain account,
+/// which usually has no access to user shares.
+///
+/// Returns `None` if the token of the process could not be inspected.
+pub fn running_as_system() -> Option<bool> {
+    let mut token = HANDLE(null_mut());
+    unsafe { OpenProcessToken(GetCurrentProcess(), TOKEN_QUERY, &mut token) }.ok()?;
+
+    // TOKEN_USER is followed by the SID it points to
+    let mut length = 0u32;
+    let _ = unsafe { GetTokenInformation(token, TokenUser, None, 0, &mut length) };
+    let mut buffer = vec![0u64; usize::try_from(length).unwrap().div_ceil(size_of::<u64>())];
+    let queried = unsafe {
+        GetTokenInformation(
+            token,
+            TokenUser,
+            Some(buffer.as_mut_ptr() as *mut c_void),
+            length,
+            &mut length,
+        )
+    };
+    let _ = unsafe { CloseHandle(token) };
+    queried.ok()?;
+
+    let user = unsafe { &*(buffer.as_ptr() as *const TOKEN_USER) };
+    Some(unsafe { IsWellKnownSid(user.User.Sid, WinLocalSystemSid) }.as_bool())
+}
@@ -0,0 +1,56 @@
+use std::ffi::c_void;
+use std::mem::size_of;
+
+use windows::core::PCWSTR;
+use windows::Win32::Foundation::{CloseHandle, HANDLE};
+use windows::Win32::Storage::FileSystem::{
+    CreateFileW, FILE_FLAG_BACKUP_SEMANTICS, FILE_REMOTE_PROTOCOL_INFO, FILE_SHARE_DELETE,
+    FILE_SHARE_READ, FILE_SHARE_WRITE, FileRemoteProtocolInfo, GetFileInformationByHandleEx,
+    OPEN_EXISTING,
+};
+
+use crate::provider::WNNC_NET_LANMAN;
+use crate::wstr::str_to_wcstring;
+
+
+/// Determines the SMB dialect negotiated for the connection that the given path is accessed
+/// through, e.g. `3.1.1`.
+///
+/// The dialect is obtained from the `FileRemoteProtocolInfo` of a handle to the path. Returns
+/// `None` if the path cannot be opened or is not accessed using SMB.
+pub(crate) fn smb_dialect(path: &str) -> Option<String> {
+    let path_windows = str_to_wcstring(path);
+    let handle: HANDLE = unsafe {
+        CreateFileW(
+            PCWSTR(path_windows.as_ptr()),
+            0,
+            FILE_SHARE_READ | FILE_SHARE_WRITE | FILE_SHARE_DELETE,
+            None,
+            OPEN_EXISTING,
+            // required to open directories
+            FILE_FLAG_BACKUP_SEMANTICS,
+            None,
+        )
+    }.ok()?;
+
+    let mut info = FILE_REMOTE_PROTOCOL_INFO::default();
+    let result = unsafe {
+        GetFileInformationByHandleEx(
+            handle,
+            FileRemoteProtocolInfo,
+            &mut info as *mut FILE_REMOTE_PROTOCOL_INFO as *mut c_void,
+            size_of::<FILE_REMOTE_PROTOCOL_INFO>().try_into().unwrap(),
+        )
+    };
+    let _ = unsafe { CloseHandle(handle) };
+    result.ok()?;
+
+    if info.Protocol != WNNC_NET_LANMAN {
+        return None;
+    }
+    let mut dialect = format!("{}.{}", info.ProtocolMajorVersion, info.ProtocolMinorVersion);
+    if info.ProtocolRevision != 0 {
+        dialect.push_str(&format!(".{}", info.ProtocolRevision));
+    }
+    Some(dialect)
+}
@@ -0,0 +1,63 @@
+use std::time::Duration;
+
+use windows::Win32::Storage::FileSystem::GetLogicalDrives;
+
+use crate::cancel::{CancellationToken, poll_until};
+use crate::connection::{device_connection, DeviceConnection};
+use crate::error::OpenShareError;
+
+
+/// How often the set of logical drives is checked while waiting for a drive to appear.
+const DRIVE_POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+
+/// Returns the index of the drive letter (0 for `A:`) of a device name such as `X:`.
+pub(crate) fn drive_letter_index(drive: &str) -> Option<u32> {
+    let mut chars = drive.chars();
+    let letter = chars.next()?.to_ascii_uppercase();
+    if !letter.is_ascii_uppercase() || chars.next() != Some(':') || chars.next().is_some() {
+        return None;
+    }
+    Some(u32::from(letter) - u32::from('A'))
+}
+
+/// Whether the given drive currently exists.
+pub(crate) fn drive_present(drive: &str) -> bool {
+    let index = match drive_letter_index(drive) {
+        Some(i) => i,
+        None => return false,
+    };
+    let drives = unsafe { GetLogicalDrives() };
+    drives & (1 << index) != 0
+}
+
+/// Whether the given drive is neither present nor remembered as a mapping that is currently
+/// unavailable, i.e. whether a share can be mapped to it.
+pub(crate) fn drive_free(drive: &str) -> bool {
+    !drive_present(drive) && matches!(device_connection(drive), DeviceConnection::None)
+}
+
+/// Waits until the given drive exists or the timeout elapses.
+///
+/// Returns whether the drive appeared in time.
+pub(crate) fn wait_for_drive(drive: &str, timeout: Duration, cancel: Option<&CancellationToken>) -> Result<bool, OpenShareError> {
+    poll_until(cancel, timeout, DRIVE_POLL_INTERVAL, || drive_present(drive))
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn drive_letter_index_accepts_only_letter_and_colon() {
+        assert_eq!(drive_letter_index("A:"), Some(0));
+        assert_eq!(drive_letter_index("x:"), Some(23));
+        assert_eq!(drive_letter_index("X"), None);
+        assert_eq!(drive_letter_index("X:\\"), None);
+        assert_eq!(drive_letter_index("XY:"), None);
+        assert_eq!(drive_letter_index("1:"), None);
+        assert_eq!(drive_letter_index("\u{c4}:"), None);
+        assert_eq!(drive_letter_index(""), None);
+    }
+}
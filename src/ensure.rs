@@ -0,0 +1,50 @@
+use crate::{OpenShareError, ShareLogger, ShareRequest};
+use crate::connection::{
+    connect_to_share, device_connection, disconnect, DeviceConnection, find_existing_connection,
+};
+
+
+/// The action taken by [`ensure_mapped`].
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum EnsureAction {
+    /// The drive was already mapped to the share; nothing was done.
+    AlreadyMapped,
+
+    /// The drive was mapped to a different share (or its mapping was remembered but not
+    /// connected) and has been mapped to the requested share instead.
+    Remapped { previous: String },
+
+    /// The drive was not mapped and has been mapped to the requested share.
+    Mapped,
+}
+
+
+/// Ensures that the request's drive is mapped to the request's share, doing as little as
+/// possible. Nothing is opened in the shell.
+///
+/// # Panics
+///
+/// Panics if the request has no [`drive`](ShareRequest::drive).
+pub fn ensure_mapped(request: &ShareRequest, logger: Option<&dyn ShareLogger>) -> Result<EnsureAction, OpenShareError> {
+    let drive = request.drive()
+        .expect("ensure_mapped requires a request with a drive");
+
+    if find_existing_connection(request.path(), Some(drive), request.enumeration(), logger).is_some() {
+        return Ok(EnsureAction::AlreadyMapped);
+    }
+
+    let previous = match device_connection(drive) {
+        DeviceConnection::None => None,
+        DeviceConnection::Connected(p) | DeviceConnection::Unavailable(p) => {
+            // forget the previous mapping too, otherwise it blocks the new one or returns at logon
+            disconnect(drive, true, false, logger)?;
+            Some(p)
+        },
+    };
+
+    connect_to_share(request, logger)?;
+    Ok(match previous {
+        Some(previous) => EnsureAction::Remapped { previous },
+        None => EnsureAction::Mapped,
+    })
+}
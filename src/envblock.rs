@@ -0,0 +1,108 @@
+//! Environment blocks for `CreateProcessW`.
+//!
+//! Passing a block to a child process gives it additional variables without changing the
+//! environment of this process, which other threads (and other callers of the library) share.
+
+
+use std::env;
+use std::ffi::{OsStr, OsString};
+use std::os::windows::ffi::OsStrExt;
+
+
+/// Builds an environment block (for `CREATE_UNICODE_ENVIRONMENT`) consisting of the environment of
+/// this process with the given variables added or replaced.
+///
+/// Names are compared case-insensitively, as Windows does; the block is sorted by name in the same
+/// way, as `CreateProcessW` expects. Names and values that are not valid Unicode are kept as they
+/// are.
+pub fn environment_block(overrides: &[(&str, &OsStr)]) -> Vec<u16> {
+    block_from(env::vars_os(), overrides)
+}
+
+fn block_from<I: IntoIterator<Item = (OsString, OsString)>>(vars: I, overrides: &[(&str, &OsStr)]) -> Vec<u16> {
+    let mut vars: Vec<(OsString, OsString)> = vars.into_iter()
+        .filter(|(name, _)| !overrides.iter().any(|(o, _)| name_key(name) == name_key(OsStr::new(o))))
+        .chain(overrides.iter().map(|(name, value)| (OsString::from(name), value.to_os_string())))
+        .collect();
+    vars.sort_by_cached_key(|(name, _)| name_key(name));
+
+    let mut block = Vec::new();
+    for (name, value) in &vars {
+        block.extend(name.encode_wide());
+        block.push(u16::from(b'='));
+        block.extend(value.encode_wide());
+        block.push(0);
+    }
+    if vars.is_empty() {
+        // even an empty block ends with two terminators
+        block.push(0);
+    }
+    block.push(0);
+    block
+}
+
+/// The name in upper case, by which Windows compares and orders variable names.
+fn name_key(name: &OsStr) -> Vec<u16> {
+    name.encode_wide().map(upper_case_unit).collect()
+}
+
+/// The upper-case form of a UTF-16 code unit, if it is a single code unit; otherwise (e.g. for a
+/// surrogate) the code unit itself.
+fn upper_case_unit(unit: u16) -> u16 {
+    let Some(c) = char::from_u32(unit.into()) else {
+        return unit;
+    };
+    let mut upper = c.to_uppercase();
+    match (upper.next(), upper.next()) {
+        (Some(u), None) => u16::try_from(u32::from(u)).unwrap_or(unit),
+        _ => unit,
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::os::windows::ffi::OsStringExt;
+
+    fn vars(pairs: &[(&str, &str)]) -> Vec<(OsString, OsString)> {
+        pairs.iter()
+            .map(|(n, v)| (OsString::from(n), OsString::from(v)))
+            .collect()
+    }
+
+    fn entries(block: &[u16]) -> Vec<String> {
+        assert_eq!(block.last(), Some(&0));
+        block[..block.len()-1].split(|u| *u == 0)
+            .filter(|entry| !entry.is_empty())
+            .map(String::from_utf16_lossy)
+            .collect()
+    }
+
+    #[test]
+    fn sorts_names_ignoring_case() {
+        let block = block_from(vars(&[("windir", "C:\\Windows"), ("Path", "C:\\"), ("APPDATA", "x"), ("_z", "1")]), &[]);
+        assert_eq!(entries(&block), ["APPDATA=x", "Path=C:\\", "windir=C:\\Windows", "_z=1"]);
+    }
+
+    #[test]
+    fn overrides_replace_variables_of_any_case() {
+        let block = block_from(
+            vars(&[("open_share_path", "old"), ("TEMP", "t")]),
+            &[("OPEN_SHARE_PATH", OsStr::new("\\\\server\\share"))],
+        );
+        assert_eq!(entries(&block), ["OPEN_SHARE_PATH=\\\\server\\share", "TEMP=t"]);
+    }
+
+    #[test]
+    fn keeps_values_that_are_not_unicode() {
+        let unpaired = OsString::from_wide(&[u16::from(b'a'), 0xD800]);
+        let block = block_from([(OsString::from("ODD"), unpaired)], &[]);
+        assert_eq!(block, [u16::from(b'O'), u16::from(b'D'), u16::from(b'D'), u16::from(b'='), u16::from(b'a'), 0xD800, 0, 0]);
+    }
+
+    #[test]
+    fn empty_environment_is_terminated() {
+        assert_eq!(block_from(Vec::new(), &[]), [0, 0]);
+    }
+}
@@ -0,0 +1,171 @@
+//! Ephemeral sessions (`--ephemeral`), in which the shares connected by open-share are
+//! disconnected again once the user is done with them.
+//!
+//! The session lasts until Enter is pressed. With `--disconnect-after SECONDS`, it also ends once
+//! that time has passed; `--disconnect-after` without `--ephemeral` runs a session that only ends
+//! then. With `--keepalive SECONDS`, the connections are checked at that interval (using the
+//! enumeration of existing connections) and re-established if they have dropped, so that the final
+//! disconnect finds them. Shares that were already connected before open-share was started are left
+//! alone. With `--post-disconnect COMMAND`, the command is run after each share has been
+//! disconnected.
+//!
+//! In an `--ephemeral` session, closing the console window (as well as logging off or shutting
+//! down) still disconnects the shares before the process is terminated, although the
+//! `--post-disconnect` command is not run then. Otherwise, ending the process early (including
+//! closing the window during a `--disconnect-after` session) skips the disconnect.
+
+
+use std::io::BufRead;
+use std::sync::Mutex;
+use std::sync::mpsc::{channel, RecvTimeoutError};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use open_share::ShareRequest;
+use windows::Win32::Foundation::{BOOL, FALSE, FILETIME, SYSTEMTIME, TRUE};
+use windows::Win32::Storage::FileSystem::FileTimeToLocalFileTime;
+use windows::Win32::System::Console::{
+    CTRL_CLOSE_EVENT, CTRL_LOGOFF_EVENT, CTRL_SHUTDOWN_EVENT, SetConsoleCtrlHandler,
+};
+use windows::Win32::System::SystemInformation::GetSystemTimeAsFileTime;
+use windows::Win32::System::Time::FileTimeToSystemTime;
+
+use crate::report::ErrorReporter;
+use crate::report::{LOGGER, progress};
+use crate::run_post_disconnect_hook;
+
+
+/// The shares disconnected by [`handle_close`] if the console window is closed during an
+/// `--ephemeral` session.
+static GUARDED: Mutex<Vec<ShareRequest>> = Mutex::new(Vec::new());
+
+/// The number of 100ns intervals in a second, the unit of `FILETIME`.
+const FILETIME_TICKS_PER_SECOND: u64 = 10_000_000;
+
+
+unsafe extern "system" fn handle_close(ctrl_type: u32) -> BOOL {
+    if ctrl_type == CTRL_CLOSE_EVENT || ctrl_type == CTRL_LOGOFF_EVENT || ctrl_type == CTRL_SHUTDOWN_EVENT {
+        let requests = std::mem::take(&mut *GUARDED.lock().unwrap_or_else(|e| e.into_inner()));
+        for request in &requests {
+            let _ = open_share::disconnect(request, LOGGER);
+        }
+    }
+    // let the default handler terminate the process
+    FALSE
+}
+
+/// Disconnects the given shares if the console window is closed, until dropped.
+struct CloseGuard;
+impl CloseGuard {
+    fn install(requests: &[&ShareRequest]) -> Self {
+        *GUARDED.lock().unwrap_or_else(|e| e.into_inner()) = requests.iter()
+            .map(|&request| request.clone())
+            .collect();
+        let _ = unsafe { SetConsoleCtrlHandler(Some(handle_close), TRUE) };
+        Self
+    }
+}
+impl Drop for CloseGuard {
+    fn drop(&mut self) {
+        let _ = unsafe { SetConsoleCtrlHandler(Some(handle_close), FALSE) };
+        GUARDED.lock().unwrap_or_else(|e| e.into_inner()).clear();
+    }
+}
+
+
+/// Formats the local time at which the given duration will have passed as HH:MM:SS.
+fn local_time_after(duration: Duration) -> Option<String> {
+    let now = unsafe { GetSystemTimeAsFileTime() };
+    let ticks = (u64::from(now.dwHighDateTime) << 32 | u64::from(now.dwLowDateTime))
+        .checked_add(duration.as_secs().checked_mul(FILETIME_TICKS_PER_SECOND)?)?;
+    let then = FILETIME { dwLowDateTime: ticks as u32, dwHighDateTime: (ticks >> 32) as u32 };
+    let mut local = FILETIME::default();
+    unsafe { FileTimeToLocalFileTime(&then, &mut local) }.ok()?;
+    let mut time = SYSTEMTIME::default();
+    unsafe { FileTimeToSystemTime(&local, &mut time) }.ok()?;
+    Some(format!("{:02}:{:02}:{:02}", time.wHour, time.wMinute, time.wSecond))
+}
+
+
+/// Keeps the session open until Enter is pressed (if `end_on_enter`) or `disconnect_after` has
+/// passed, then disconnects the shares. Returns the exit code.
+pub(crate) fn run_session(requests: &[&ShareRequest], keepalive: Option<Duration>, disconnect_after: Option<Duration>, end_on_enter: bool, post_disconnect: Option<&str>, reporter: &mut ErrorReporter) -> i32 {
+    if requests.is_empty() {
+        return 0;
+    }
+
+    let (sender, receiver) = channel();
+    // without the stdin thread, the sender is kept here so that receiving only times out
+    let _unused_sender = if end_on_enter {
+        thread::spawn(move || {
+            let si = std::io::stdin();
+            let mut sil = si.lock();
+            let mut buf = String::new();
+            // end the session on Enter as well as on EOF or a read error
+            let _ = sil.read_line(&mut buf);
+            let _ = sender.send(());
+        });
+        None
+    } else {
+        Some(sender)
+    };
+    let _close_guard = end_on_enter.then(|| CloseGuard::install(requests));
+
+    let deadline = disconnect_after.map(|after| Instant::now() + after);
+    if let Some(after) = disconnect_after {
+        match local_time_after(after) {
+            Some(time) => progress(format!("disconnecting at {}", time)),
+            None => progress(format!("disconnecting in {} seconds", after.as_secs())),
+        }
+    }
+    if end_on_enter {
+        progress("press Enter to disconnect");
+    }
+
+    loop {
+        let remaining = deadline.map(|d| d.saturating_duration_since(Instant::now()));
+        if remaining == Some(Duration::ZERO) {
+            break;
+        }
+        let wait = match (keepalive, remaining) {
+            (Some(interval), Some(remaining)) => Some(interval.min(remaining)),
+            (interval, remaining) => interval.or(remaining),
+        };
+        let received = match wait {
+            Some(wait) => receiver.recv_timeout(wait),
+            None => receiver.recv().map_err(|_| RecvTimeoutError::Disconnected),
+        };
+        match received {
+            Ok(()) | Err(RecvTimeoutError::Disconnected) => break,
+            Err(RecvTimeoutError::Timeout) if keepalive.is_none() => {},
+            Err(RecvTimeoutError::Timeout) => {
+                for request in requests {
+                    if open_share::is_connected(request, LOGGER) {
+                        continue;
+                    }
+                    progress(format!("{}: connection dropped; reconnecting...", request.path()));
+                    if let Err(e) = open_share::connect(request, LOGGER) {
+                        reporter.error(format!("{}: {}", request.path(), e));
+                    }
+                }
+            },
+        }
+    }
+
+    let mut exit_code = 0;
+    for request in requests {
+        if !open_share::is_connected(request, LOGGER) {
+            continue;
+        }
+        if let Err(e) = open_share::disconnect(request, LOGGER) {
+            reporter.error(format!("{}: {}", request.path(), e));
+            exit_code = 1;
+            continue;
+        }
+        if let Some(command) = post_disconnect {
+            let hook_exit_code = run_post_disconnect_hook(command, request.path(), request.drive(), reporter);
+            exit_code = exit_code.max(hook_exit_code);
+        }
+    }
+    exit_code
+}
@@ -0,0 +1,312 @@
+use std::fmt;
+use std::io::Error as IoError;
+use std::time::Duration;
+
+use windows::Win32::Foundation::{
+    ERROR_ACCESS_DENIED, ERROR_ACCOUNT_DISABLED, ERROR_ACCOUNT_EXPIRED, ERROR_ACCOUNT_RESTRICTION,
+    ERROR_ALREADY_ASSIGNED, ERROR_BAD_DEV_TYPE, ERROR_BAD_DEVICE, ERROR_BAD_NET_NAME,
+    ERROR_BAD_NETPATH, ERROR_BAD_PROVIDER, ERROR_BAD_USERNAME, ERROR_BUSY, ERROR_CANCELLED,
+    ERROR_CONNECTION_UNAVAIL, ERROR_DEVICE_ALREADY_REMEMBERED, ERROR_DEVICE_IN_USE,
+    ERROR_EXTENDED_ERROR, ERROR_FILE_EXISTS, ERROR_FILE_NOT_FOUND, ERROR_INVALID_NAME,
+    ERROR_INVALID_PARAMETER, ERROR_INVALID_PASSWORD, ERROR_LOGON_FAILURE,
+    ERROR_LOGON_TYPE_NOT_GRANTED, ERROR_MORE_DATA, ERROR_NETNAME_DELETED, ERROR_NETWORK_UNREACHABLE,
+    ERROR_NO_LOGON_SERVERS, ERROR_NO_NET_OR_BAD_PATH, ERROR_NO_NETWORK, ERROR_NOT_CONNECTED,
+    ERROR_NOT_FOUND, ERROR_NOT_SUPPORTED, ERROR_OPEN_FILES, ERROR_PASSWORD_EXPIRED,
+    ERROR_PASSWORD_MUST_CHANGE, ERROR_PATH_NOT_FOUND, ERROR_REQ_NOT_ACCEP, ERROR_SEM_TIMEOUT,
+    ERROR_SESSION_CREDENTIAL_CONFLICT, ERROR_TIMEOUT, ERROR_TOO_MANY_NAMES, ERROR_TOO_MANY_SESS,
+    ERROR_UNEXP_NET_ERR, WIN32_ERROR,
+};
+use windows::Win32::NetworkManagement::WNet::NET_CONNECT_FLAGS;
+
+use crate::log::LogPhase;
+use crate::request::ShareRequest;
+
+
+/// Extracts the Win32 error code from a `windows` crate error, falling back to its HRESULT.
+pub(crate) fn win32_code(error: &windows::core::Error) -> u32 {
+    WIN32_ERROR::from_error(error)
+        .map(|e| e.0)
+        .unwrap_or(error.code().0 as u32)
+}
+
+/// Pairs each Win32 error constant with its name.
+macro_rules! named_errors {
+    ($($name:ident),+ $(,)?) => {
+        &[$(($name.0, stringify!($name))),+]
+    };
+}
+
+/// The Win32 errors commonly encountered when connecting to and opening shares, by name.
+const ERROR_NAMES: &[(u32, &str)] = named_errors!(
+    ERROR_ACCESS_DENIED, ERROR_ACCOUNT_DISABLED, ERROR_ACCOUNT_EXPIRED, ERROR_ACCOUNT_RESTRICTION,
+    ERROR_ALREADY_ASSIGNED, ERROR_BAD_DEV_TYPE, ERROR_BAD_DEVICE, ERROR_BAD_NET_NAME,
+    ERROR_BAD_NETPATH, ERROR_BAD_PROVIDER, ERROR_BAD_USERNAME, ERROR_BUSY, ERROR_CANCELLED,
+    ERROR_CONNECTION_UNAVAIL, ERROR_DEVICE_ALREADY_REMEMBERED, ERROR_DEVICE_IN_USE,
+    ERROR_EXTENDED_ERROR, ERROR_FILE_EXISTS, ERROR_FILE_NOT_FOUND, ERROR_INVALID_NAME,
+    ERROR_INVALID_PARAMETER, ERROR_INVALID_PASSWORD, ERROR_LOGON_FAILURE,
+    ERROR_LOGON_TYPE_NOT_GRANTED, ERROR_MORE_DATA, ERROR_NETNAME_DELETED, ERROR_NETWORK_UNREACHABLE,
+    ERROR_NO_LOGON_SERVERS, ERROR_NO_NET_OR_BAD_PATH, ERROR_NO_NETWORK, ERROR_NOT_CONNECTED,
+    ERROR_NOT_FOUND, ERROR_NOT_SUPPORTED, ERROR_OPEN_FILES, ERROR_PASSWORD_EXPIRED,
+    ERROR_PASSWORD_MUST_CHANGE, ERROR_PATH_NOT_FOUND, ERROR_REQ_NOT_ACCEP, ERROR_SEM_TIMEOUT,
+    ERROR_SESSION_CREDENTIAL_CONFLICT, ERROR_TIMEOUT, ERROR_TOO_MANY_NAMES, ERROR_TOO_MANY_SESS,
+    ERROR_UNEXP_NET_ERR,
+);
+
+/// Returns the symbolic name of a Win32 error code (e.g. `ERROR_BAD_NETPATH` for 53), if it is one
+/// of the errors commonly encountered when connecting to shares.
+pub fn win32_error_name(code: u32) -> Option<&'static str> {
+    ERROR_NAMES.iter()
+        .find(|(c, _)| *c == code)
+        .map(|(_, name)| *name)
+}
+
+
+/// What was passed to `WNetAddConnection2W` when connecting failed, to help reproduce the failure.
+///
+/// Only records whether credentials were given, never the credentials themselves.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct ConnectContext {
+    pub path: String,
+    pub drive: Option<String>,
+    pub provider: Option<String>,
+    pub has_username: bool,
+    pub has_password: bool,
+
+    /// The `NET_CONNECT_FLAGS` actually passed, which may differ from those of the request (e.g.
+    /// without the prompt flags after the modern credential dialog has been shown).
+    pub flags: u32,
+}
+impl ConnectContext {
+    pub(crate) fn new(request: &ShareRequest, has_username: bool, has_password: bool, flags: NET_CONNECT_FLAGS) -> Self {
+        Self {
+            path: request.path().to_owned(),
+            drive: request.drive().map(|d| d.to_owned()),
+            provider: request.provider().map(|p| p.to_owned()),
+            has_username,
+            has_password,
+            flags: flags.0,
+        }
+    }
+}
+impl fmt::Display for ConnectContext {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let given = |g: bool| if g { "given" } else { "none" };
+        write!(f, "path {}", self.path)?;
+        if let Some(drive) = &self.drive {
+            write!(f, ", drive {}", drive)?;
+        }
+        if let Some(provider) = &self.provider {
+            write!(f, ", provider {}", provider)?;
+        }
+        write!(
+            f, ", username {}, password {}, flags 0x{:08X}",
+            given(self.has_username), given(self.has_password), self.flags,
+        )
+    }
+}
+
+
+/// An error that aborted the check/connect/open pipeline.
+#[derive(Debug)]
+pub enum OpenShareError {
+    /// The connection to the share could not be established. The context is missing if the failure
+    /// happened before `WNetAddConnection2W` was called (e.g. in the credential dialog).
+    Connect { code: u32, context: Option<ConnectContext> },
+
+    /// The connected path could not be opened in the shell.
+    Open { code: u32 },
+
+    /// Opening the connected share did not finish within the open timeout.
+    OpenTimeout { timeout: Duration },
+
+    /// The requested drive is already mapped to another share (whose remote name is given if it
+    /// could be found).
+    DriveInUse { drive: String, holder: Option<String> },
+
+    /// The requested drive is not a valid local device name of the form `X:`.
+    InvalidDrive { drive: String },
+
+    /// Connecting was refused because at least `limit` connections exist already.
+    ConnectionLimit { count: usize, limit: usize },
+
+    /// The server refused the connection because it has reached its limit of concurrent sessions.
+    ServerConnectionLimit { code: u32 },
+
+    /// A mapping could not be registered to be restored at the next logon.
+    Register { code: u32 },
+
+    /// The existing connections could not be enumerated.
+    Enumerate { code: u32 },
+
+    /// The shares of a server could not be listed.
+    Browse { code: u32 },
+
+    /// An existing connection could not be removed.
+    Disconnect { code: u32 },
+
+    /// A file could not be copied into the share (`--copy-in`).
+    CopyIn { code: u32 },
+
+    /// The share was connected, but writing to it failed (see
+    /// [`ShareRequest::require_write`](crate::ShareRequest::require_write)).
+    NotWritable { code: u32 },
+
+    /// Connecting (or authenticating to the server) succeeded, but the server has no share of this
+    /// name, which usually means that the share name is misspelled rather than that the credentials
+    /// are wrong.
+    ShareNotFound { server: String, share: String },
+
+    /// The same path was requested for different users (see
+    /// [`deduplicate_requests`](crate::deduplicate_requests)).
+    ConflictingCredentials { path: String, first: Option<String>, second: Option<String> },
+
+    /// Processing was cancelled using a [`CancellationToken`](crate::CancellationToken).
+    Cancelled,
+
+    /// No certificate residing on a smartcard was found in the personal certificate store.
+    NoSmartcardCertificate,
+
+    /// The smartcard certificate could not be turned into a credential.
+    Smartcard { code: u32 },
+
+    /// The request needs a capability whose cargo feature (e.g. `smartcard`) was disabled when
+    /// building the library.
+    NotCompiledIn { feature: &'static str },
+}
+impl OpenShareError {
+    /// The Win32 error code that caused this error.
+    pub fn win32_code(&self) -> u32 {
+        match self {
+            Self::Connect { code, .. } => *code,
+            Self::Open { code } => *code,
+            Self::OpenTimeout { .. } => ERROR_TIMEOUT.0,
+            Self::DriveInUse { .. } => ERROR_ALREADY_ASSIGNED.0,
+            Self::InvalidDrive { .. } => ERROR_BAD_DEVICE.0,
+            Self::ConnectionLimit { .. } => ERROR_TOO_MANY_NAMES.0,
+            Self::ServerConnectionLimit { code } => *code,
+            Self::Register { code } => *code,
+            Self::Enumerate { code } => *code,
+            Self::Browse { code } => *code,
+            Self::Disconnect { code } => *code,
+            Self::CopyIn { code } => *code,
+            Self::NotWritable { code } => *code,
+            Self::ShareNotFound { .. } => ERROR_BAD_NET_NAME.0,
+            Self::ConflictingCredentials { .. } => ERROR_SESSION_CREDENTIAL_CONFLICT.0,
+            Self::Cancelled => ERROR_CANCELLED.0,
+            Self::NoSmartcardCertificate => ERROR_NOT_FOUND.0,
+            Self::Smartcard { code } => *code,
+            Self::NotCompiledIn { .. } => ERROR_NOT_SUPPORTED.0,
+        }
+    }
+
+    /// The part of the processing that failed.
+    pub fn phase(&self) -> LogPhase {
+        match self {
+            Self::Connect { .. } | Self::DriveInUse { .. } | Self::InvalidDrive { .. }
+                | Self::ConnectionLimit { .. } | Self::ServerConnectionLimit { .. }
+                | Self::Register { .. } | Self::NoSmartcardCertificate | Self::Smartcard { .. }
+                | Self::Cancelled | Self::NotCompiledIn { .. }
+                => LogPhase::Connect,
+            Self::Open { .. } | Self::OpenTimeout { .. } | Self::CopyIn { .. } | Self::NotWritable { .. }
+                | Self::ShareNotFound { .. }
+                => LogPhase::Open,
+            Self::Enumerate { .. } | Self::Browse { .. } | Self::ConflictingCredentials { .. }
+                => LogPhase::Check,
+            Self::Disconnect { .. } => LogPhase::Disconnect,
+        }
+    }
+}
+/// Describes the user of a request for an error message.
+fn describe_user(username: Option<&str>) -> String {
+    match username {
+        Some("") => "guest access".to_owned(),
+        Some(u) => format!("user {}", u),
+        None => "the current user".to_owned(),
+    }
+}
+
+impl fmt::Display for OpenShareError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Connect { code, context: None }
+                => write!(f, "failed to connect! {}", IoError::from_raw_os_error(*code as i32)),
+            Self::Connect { code, context: Some(context) }
+                => write!(f, "failed to connect! {} ({})", IoError::from_raw_os_error(*code as i32), context),
+            Self::Open { code }
+                => write!(f, "failed to open share! {}", IoError::from_raw_os_error(*code as i32)),
+            Self::OpenTimeout { timeout }
+                => write!(f, "failed to open share! not finished within {:?}", timeout),
+            Self::DriveInUse { drive, holder: Some(holder) }
+                => write!(f, "failed to connect! {} is already mapped to {}", drive, holder),
+            Self::DriveInUse { drive, holder: None }
+                => write!(f, "failed to connect! {} is already in use", drive),
+            Self::InvalidDrive { drive }
+                => write!(f, "failed to connect! invalid drive specification {:?} (expected a letter and a colon, e.g. X:)", drive),
+            Self::ConnectionLimit { count, limit }
+                => write!(f, "refusing to connect! {} connections exist already (limit: {})", count, limit),
+            Self::ServerConnectionLimit { code }
+                => write!(f, "failed to connect! the server has reached its connection limit ({})", IoError::from_raw_os_error(*code as i32)),
+            Self::Register { code }
+                => write!(f, "failed to register the mapping! {}", IoError::from_raw_os_error(*code as i32)),
+            Self::Enumerate { code }
+                => write!(f, "failed to enumerate connections! {}", IoError::from_raw_os_error(*code as i32)),
+            Self::Browse { code }
+                => write!(f, "failed to list the shares! {}", IoError::from_raw_os_error(*code as i32)),
+            Self::Disconnect { code }
+                => write!(f, "failed to disconnect! {}", IoError::from_raw_os_error(*code as i32)),
+            Self::CopyIn { code }
+                => write!(f, "failed to copy into the share! {}", IoError::from_raw_os_error(*code as i32)),
+            Self::NotWritable { code }
+                => write!(f, "connected, but cannot write to the share! {}", IoError::from_raw_os_error(*code as i32)),
+            Self::ShareNotFound { server, share }
+                => write!(f, "authenticated to the server {}, but the share name {:?} is invalid!", server, share),
+            Self::ConflictingCredentials { first, second, .. }
+                => write!(
+                    f, "refusing to connect! the path is requested for both {} and {}",
+                    describe_user(first.as_deref()), describe_user(second.as_deref()),
+                ),
+            Self::Cancelled
+                => write!(f, "cancelled!"),
+            Self::NoSmartcardCertificate
+                => write!(f, "no smartcard certificate found; is the smartcard inserted?"),
+            Self::Smartcard { code }
+                => write!(f, "failed to obtain smartcard credential! {}", IoError::from_raw_os_error(*code as i32)),
+            Self::NotCompiledIn { feature }
+                => write!(f, "feature not compiled in! this build lacks the {} feature", feature),
+        }
+    }
+}
+impl std::error::Error for OpenShareError {
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use windows::Win32::Foundation::ERROR_LOGON_FAILURE;
+
+    #[test]
+    fn connect_error_omits_password() {
+        let request = ShareRequest::builder("\\\\server\\share")
+            .username("DOMAIN\\user")
+            .password("hunter2-correct-horse")
+            .drive("X:")
+            .build();
+        let context = ConnectContext::new(&request, true, true, request.flags());
+        let error = OpenShareError::Connect { code: ERROR_LOGON_FAILURE.0, context: Some(context) };
+
+        let formatted = format!("{}", error);
+        assert!(formatted.contains("\\\\server\\share"));
+        assert!(formatted.contains("drive X:"));
+        assert!(formatted.contains("password given"));
+        assert!(!formatted.contains("hunter2"));
+        assert!(!format!("{:?}", error).contains("hunter2"));
+    }
+
+    #[test]
+    fn win32_error_names() {
+        assert_eq!(win32_error_name(53), Some("ERROR_BAD_NETPATH"));
+        assert_eq!(win32_error_name(ERROR_LOGON_FAILURE.0), Some("ERROR_LOGON_FAILURE"));
+        assert_eq!(win32_error_name(0xDEAD), None);
+    }
+}
@@ -0,0 +1,200 @@
+//! Exporting the current connections to a configuration file (`export-config FILE`), e.g. to
+//! capture the mappings of one machine and set them up on another.
+//!
+//! The file is TOML with a table under `alias` for each connected share, named after the share
+//! (lowercased, followed by `-2`, `-3` and so on if several connections are to shares of the same
+//! name):
+//!
+//! ```toml
+//! [alias.projects]
+//! path = '\\fileserver\projects'
+//! drive = "P:"
+//! persistent = true
+//! username = 'CORP\alice'
+//! ```
+//!
+//! * `path`: the remote name of the connection as reported by the network provider.
+//! * `drive`: the drive the share is mapped to; missing for deviceless connections.
+//! * `persistent`: whether the mapping is remembered in the user's profile and restored at logon.
+//! * `username`: the user that the connection was made as; only included if `WNetGetUserW`
+//!   reports it.
+//!
+//! Passwords are never exported (Windows does not reveal them anyway). Printer connections are
+//! skipped.
+
+
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::Path;
+
+use open_share::{ConnectionInfo, ConnectionScope, EnumerationOptions, normalize_path, ResourceType, UncPath};
+use serde::Serialize;
+
+use crate::report::{ErrorReporter, LOGGER, progress};
+
+
+/// The contents of an exported configuration file.
+#[derive(Debug, Serialize)]
+struct Config {
+    alias: BTreeMap<String, Alias>,
+}
+
+/// An exported connection.
+#[derive(Debug, Eq, PartialEq, Serialize)]
+struct Alias {
+    path: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    drive: Option<String>,
+    persistent: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    username: Option<String>,
+}
+
+
+/// Whether the connection is among the remembered ones, comparing case-insensitively.
+fn is_remembered(connection: &ConnectionInfo, remembered: &[ConnectionInfo]) -> bool {
+    let Some(local_name) = &connection.local_name else {
+        // only mappings to devices are remembered
+        return false;
+    };
+    let remote_name = connection.remote_name.as_deref().map(normalize_path);
+    remembered.iter().any(|r| {
+        r.local_name.as_deref().is_some_and(|l| l.eq_ignore_ascii_case(local_name))
+            && r.remote_name.as_deref().map(normalize_path) == remote_name
+    })
+}
+
+/// The aliases for the connected disk shares. `user` returns the user name that the connection of
+/// the given local device or remote name was made with.
+fn aliases<U: Fn(&str) -> Option<String>>(connections: &[ConnectionInfo], remembered: &[ConnectionInfo], user: U) -> BTreeMap<String, Alias> {
+    let mut aliases = BTreeMap::new();
+    for connection in connections {
+        let Some(path) = &connection.remote_name else {
+            continue;
+        };
+        if connection.resource_type != ResourceType::Disk {
+            continue;
+        }
+
+        let base_name = UncPath::parse(path)
+            .map(|unc| unc.share.to_lowercase())
+            .unwrap_or_else(|| "connection".to_owned());
+        let mut name = base_name.clone();
+        let mut counter = 1;
+        while aliases.contains_key(&name) {
+            counter += 1;
+            name = format!("{}-{}", base_name, counter);
+        }
+
+        let alias = Alias {
+            path: path.clone(),
+            drive: connection.local_name.clone(),
+            persistent: is_remembered(connection, remembered),
+            username: user(connection.local_name.as_deref().unwrap_or(path)),
+        };
+        aliases.insert(name, alias);
+    }
+    aliases
+}
+
+/// Writes the current connections to the given file. Returns the exit code.
+pub(crate) fn run(file: &Path, reporter: &mut ErrorReporter) -> i32 {
+    let connections = match open_share::enumerate_connections(&EnumerationOptions::default(), LOGGER) {
+        Ok(c) => c,
+        Err(e) => {
+            reporter.error(e.to_string());
+            return 1;
+        },
+    };
+    let remembered_options = EnumerationOptions { scope: ConnectionScope::Remembered, ..EnumerationOptions::default() };
+    let remembered = match open_share::enumerate_connections(&remembered_options, LOGGER) {
+        Ok(r) => r,
+        Err(e) => {
+            progress(format!("warning: cannot enumerate the remembered connections; exporting none as persistent: {}", e));
+            Vec::new()
+        },
+    };
+
+    let config = Config { alias: aliases(&connections, &remembered, open_share::connection_user) };
+    let text = match toml::to_string(&config) {
+        Ok(t) => t,
+        Err(e) => {
+            reporter.error(format!("cannot format the configuration: {}", e));
+            return 1;
+        },
+    };
+    if let Err(e) = fs::write(file, format!("# exported by open-share export-config\n\n{}", text)) {
+        reporter.error(format!("{}: {}", file.display(), e));
+        return 1;
+    }
+    progress(format!("exported {} connections to {}", config.alias.len(), file.display()));
+    0
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn connection(local_name: Option<&str>, remote_name: &str, resource_type: ResourceType) -> ConnectionInfo {
+        ConnectionInfo {
+            remote_name: Some(remote_name.to_owned()),
+            local_name: local_name.map(|l| l.to_owned()),
+            provider: Some("Microsoft Windows Network".to_owned()),
+            resource_type,
+        }
+    }
+
+    fn user(name: &str) -> Option<String> {
+        (name == "P:").then(|| "CORP\\alice".to_owned())
+    }
+
+    #[test]
+    fn exports_disk_connections() {
+        let connections = [
+            connection(Some("P:"), "\\\\fileserver\\projects", ResourceType::Disk),
+            connection(None, "\\\\other\\Projects", ResourceType::Disk),
+            connection(Some("LPT1"), "\\\\printserver\\laser", ResourceType::Print),
+            connection(Some("Q:"), "\\\\nas\\media", ResourceType::Disk),
+        ];
+        let remembered = [connection(Some("p:"), "\\\\FILESERVER\\projects\\", ResourceType::Disk)];
+        let aliases = aliases(&connections, &remembered, user);
+
+        let names: Vec<&str> = aliases.keys().map(|k| k.as_str()).collect();
+        assert_eq!(names, ["media", "projects", "projects-2"]);
+        assert_eq!(aliases["projects"], Alias {
+            path: "\\\\fileserver\\projects".to_owned(),
+            drive: Some("P:".to_owned()),
+            persistent: true,
+            username: Some("CORP\\alice".to_owned()),
+        });
+        assert_eq!(aliases["projects-2"], Alias {
+            path: "\\\\other\\Projects".to_owned(),
+            drive: None,
+            persistent: false,
+            username: None,
+        });
+        assert!(!aliases["media"].persistent);
+    }
+
+    #[test]
+    fn omits_unknown_fields_from_the_file() {
+        let connections = [
+            connection(Some("P:"), "\\\\fileserver\\projects", ResourceType::Disk),
+            connection(None, "\\\\nas\\media", ResourceType::Disk),
+        ];
+        let config = Config { alias: aliases(&connections, &[], user) };
+        let text = toml::to_string(&config).unwrap();
+        assert_eq!(text, concat!(
+            "[alias.media]\n",
+            "path = '\\\\nas\\media'\n",
+            "persistent = false\n",
+            "\n",
+            "[alias.projects]\n",
+            "path = '\\\\fileserver\\projects'\n",
+            "drive = \"P:\"\n",
+            "persistent = false\n",
+            "username = 'CORP\\alice'\n",
+        ));
+    }
+}
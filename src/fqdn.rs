@@ -0,0 +1,81 @@
+//! Rewriting the server of a path to its fully qualified domain name.
+//!
+//! Kerberos looks up the service principal `cifs/<server>` for the server name exactly as it
+//! appears in the path. Principals are usually registered for the fully qualified name, so a short
+//! name (`\\fileserver\share`) may make Windows fall back to NTLM or fail. Resolving the name first
+//! (`\\fileserver.corp.example\share`) avoids this.
+//!
+//! Caveats:
+//!
+//! * The canonical name follows DNS aliases (CNAME records), so an alias is replaced by the name of
+//!   the host it points to. This must not be used for names that are only valid for the share,
+//!   such as those of clustered file servers.
+//! * The connection is made (and listed) under the new name; it does not match an existing
+//!   connection made using the short name.
+//! * IP addresses are left unchanged, as Kerberos cannot be used with them anyway.
+
+
+use std::mem::zeroed;
+use std::ptr::null_mut;
+
+use windows::core::PCWSTR;
+use windows::Win32::Networking::WinSock::{
+    ADDRINFOW, AF_UNSPEC, AI_CANONNAME, FreeAddrInfoW, GetAddrInfoW, WSACleanup, WSADATA,
+    WSAStartup,
+};
+
+use crate::unc::UncPath;
+use crate::wstr::{str_to_wcstring, wcstr_to_string};
+
+
+/// The Winsock version requested by [`canonical_host_name`] (2.2).
+const WINSOCK_VERSION: u16 = 0x0202;
+
+
+/// Asks the resolver for the canonical name of the given host.
+fn canonical_host_name(host: &str) -> Option<String> {
+    let mut wsa_data: WSADATA = unsafe { zeroed() };
+    if unsafe { WSAStartup(WINSOCK_VERSION, &mut wsa_data) } != 0 {
+        return None;
+    }
+
+    let host_windows = str_to_wcstring(host);
+    let hints = ADDRINFOW {
+        ai_flags: AI_CANONNAME as i32,
+        ai_family: i32::from(AF_UNSPEC.0),
+        ..Default::default()
+    };
+    let mut result: *mut ADDRINFOW = null_mut();
+    let status = unsafe {
+        GetAddrInfoW(PCWSTR(host_windows.as_ptr()), PCWSTR::null(), Some(&hints), &mut result)
+    };
+    let canonical = if status == 0 && !result.is_null() {
+        let name = unsafe { (*result).ai_canonname };
+        if name.0.is_null() { None } else { Some(wcstr_to_string(name.0)) }
+    } else {
+        None
+    };
+    if !result.is_null() {
+        unsafe { FreeAddrInfoW(Some(result)) };
+    }
+    unsafe { WSACleanup() };
+    canonical.filter(|c| !c.is_empty())
+}
+
+/// Replaces the server of the given UNC path with its fully qualified domain name.
+///
+/// Returns `None` if the path is not a UNC path or its server name cannot be resolved. Paths whose
+/// server is an IP address are returned unchanged.
+pub fn path_with_fqdn(path: &str) -> Option<String> {
+    let unc = UncPath::parse(path)?;
+    if unc.server_is_ip_address() {
+        return Some(path.to_owned());
+    }
+    let fqdn = canonical_host_name(unc.server)?;
+    let mut rewritten = format!("\\\\{}\\{}", fqdn, unc.share);
+    if !unc.rest.is_empty() {
+        rewritten.push('\\');
+        rewritten.push_str(unc.rest);
+    }
+    Some(rewritten)
+}
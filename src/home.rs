@@ -0,0 +1,70 @@
+//! Finding the home directory share of the current user.
+//!
+//! The sources are consulted in this order:
+//!
+//! 1. The environment variables `HOMESHARE` and `HOMEPATH`, which Windows sets at logon if the
+//!    account has a home directory on a share. `HOMESHARE` is the share (e.g.
+//!    `\\fileserver\users$`); `HOMEPATH` is the directory within it, often just `\`.
+//! 2. The home directory stored in the user account (`NetUserGetInfo` on the server named by
+//!    `LOGONSERVER`, usually a domain controller), for sessions whose environment lacks it.
+//!
+//! Home directories that are not UNC paths (i.e. local directories) are ignored.
+
+
+use std::env;
+use std::ptr::null_mut;
+
+use windows::core::PCWSTR;
+use windows::Win32::NetworkManagement::NetManagement::{
+    NERR_Success, NetApiBufferFree, NetUserGetInfo, USER_INFO_1,
+};
+
+use crate::unc::UncPath;
+use crate::wstr::{str_to_wcstring, wcstr_to_string};
+
+
+fn home_from_environment() -> Option<String> {
+    let share = env::var("HOMESHARE").ok()?;
+    let path = env::var("HOMEPATH").unwrap_or_default();
+    let relative = path.trim_matches('\\');
+    if relative.is_empty() {
+        Some(share)
+    } else {
+        Some(format!("{}\\{}", share.trim_end_matches('\\'), relative))
+    }
+}
+
+fn home_from_account() -> Option<String> {
+    let server_windows = str_to_wcstring(&env::var("LOGONSERVER").ok()?);
+    let user_windows = str_to_wcstring(&env::var("USERNAME").ok()?);
+    let mut buffer: *mut u8 = null_mut();
+    let result = unsafe {
+        NetUserGetInfo(
+            PCWSTR(server_windows.as_ptr()),
+            PCWSTR(user_windows.as_ptr()),
+            1,
+            &mut buffer,
+        )
+    };
+    if result != NERR_Success || buffer.is_null() {
+        return None;
+    }
+
+    let info = unsafe { &*(buffer as *const USER_INFO_1) };
+    let home = if info.usri1_home_dir.0.is_null() {
+        None
+    } else {
+        Some(wcstr_to_string(info.usri1_home_dir.0))
+    };
+    let _ = unsafe { NetApiBufferFree(Some(buffer as *const _)) };
+    home
+}
+
+/// Determines the UNC path of the current user's home directory; see the module documentation for
+/// the sources consulted. Returns `None` if the user has no home directory on a share.
+pub fn home_share() -> Option<String> {
+    let sources: [fn() -> Option<String>; 2] = [home_from_environment, home_from_account];
+    sources.iter()
+        .filter_map(|source| source())
+        .find(|home| UncPath::parse(home).is_some())
+}
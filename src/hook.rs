@@ -0,0 +1,110 @@
+//! Running commands around connecting and disconnecting shares.
+//!
+//! * `--pre-connect` runs before anything is connected, once for each share, and is waited for.
+//!   If it cannot be started or exits with a non-zero code for any share, nothing is connected.
+//! * `--on-success` runs once a share has been connected and opened.
+//! * `--post-disconnect` runs after a share has been disconnected (at the end of an `--ephemeral`
+//!   session or by the `disconnect` subcommand) and is waited for. A failure is reported, but the
+//!   share stays disconnected.
+//!
+//! The command line is passed to `CreateProcessW` unchanged, so the first word names the program
+//! (searched for in the usual places) and quoting follows the conventions of the program being
+//! run. The command inherits the environment of open-share with the following additions:
+//!
+//! * `OPEN_SHARE_HOOK`: `pre-connect`, `on-success` or `post-disconnect`,
+//! * `OPEN_SHARE_PATH`: the UNC path of the share as passed on the command line (for the
+//!   `disconnect` subcommand: the remote name of the disconnected connection),
+//! * `OPEN_SHARE_OPENED_PATH`: the path that was opened in the shell (the drive root if the share
+//!   was mapped to a drive, otherwise the UNC path); only set for `--on-success` and
+//! * `OPEN_SHARE_DRIVE`: the drive the share is mapped to (e.g. `X:`); only set if there is one.
+
+
+use std::ffi::{c_void, OsStr};
+use std::iter::once;
+use std::mem::size_of;
+
+use open_share::{ShareRequest, Summary};
+use windows::core::{PCWSTR, PWSTR};
+use windows::Win32::Foundation::CloseHandle;
+use windows::Win32::System::Threading::{
+    CREATE_UNICODE_ENVIRONMENT, CreateProcessW, GetExitCodeProcess, INFINITE,
+    PROCESS_INFORMATION, STARTUPINFOW, WaitForSingleObject,
+};
+
+use crate::report::progress;
+
+
+fn environment_block(hook: &str, path: &str, drive: Option<&str>, opened_path: Option<&str>) -> Vec<u16> {
+    let mut variables = vec![
+        ("OPEN_SHARE_HOOK", OsStr::new(hook)),
+        ("OPEN_SHARE_PATH", OsStr::new(path)),
+    ];
+    if let Some(opened_path) = opened_path {
+        variables.push(("OPEN_SHARE_OPENED_PATH", OsStr::new(opened_path)));
+    }
+    if let Some(drive) = drive {
+        variables.push(("OPEN_SHARE_DRIVE", OsStr::new(drive)));
+    }
+    open_share::environment_block(&variables)
+}
+
+/// Runs the command with the given environment block, returning its exit code if `wait` is set,
+/// otherwise 0 once it has been started.
+fn run_command(command: &str, wait: bool, environment: &[u16]) -> Result<u32, String> {
+    let mut command_line: Vec<u16> = command.encode_utf16().chain(once(0)).collect();
+    let startup_info = STARTUPINFOW {
+        cb: size_of::<STARTUPINFOW>().try_into().unwrap(),
+        ..Default::default()
+    };
+    let mut process_info = PROCESS_INFORMATION::default();
+    unsafe {
+        CreateProcessW(
+            PCWSTR::null(),
+            PWSTR(command_line.as_mut_ptr()),
+            None,
+            None,
+            false,
+            CREATE_UNICODE_ENVIRONMENT,
+            Some(environment.as_ptr() as *const c_void),
+            PCWSTR::null(),
+            &startup_info,
+            &mut process_info,
+        )
+    }.map_err(|e| format!("failed to run {:?}: {}", command, e.message()))?;
+
+    let mut exit_code = 0;
+    if wait {
+        unsafe { WaitForSingleObject(process_info.hProcess, INFINITE) };
+        if let Err(e) = unsafe { GetExitCodeProcess(process_info.hProcess, &mut exit_code) } {
+            progress(format!("failed to obtain exit code of {:?}: {}", command, e.message()));
+        }
+    }
+
+    let _ = unsafe { CloseHandle(process_info.hThread) };
+    let _ = unsafe { CloseHandle(process_info.hProcess) };
+    Ok(exit_code)
+}
+
+/// Runs the command before a share is connected, waiting for it to finish and returning its exit
+/// code.
+pub(crate) fn run_pre_connect(command: &str, request: &ShareRequest) -> Result<u32, String> {
+    let environment = environment_block("pre-connect", request.path(), request.drive(), None);
+    run_command(command, true, &environment)
+}
+
+/// Runs the command for a share that has been opened successfully.
+///
+/// Returns the exit code of the command if `wait` is set, otherwise 0 once it has been started.
+pub(crate) fn run_on_success(command: &str, wait: bool, request: &ShareRequest, summary: &Summary) -> Result<u32, String> {
+    let environment = environment_block(
+        "on-success", request.path(), request.drive(), Some(&summary.opened_path),
+    );
+    run_command(command, wait, &environment)
+}
+
+/// Runs the command after a share has been disconnected, waiting for it to finish and returning
+/// its exit code.
+pub(crate) fn run_post_disconnect(command: &str, path: &str, drive: Option<&str>) -> Result<u32, String> {
+    let environment = environment_block("post-disconnect", path, drive, None);
+    run_command(command, true, &environment)
+}
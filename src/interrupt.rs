@@ -0,0 +1,42 @@
+//! Turning Ctrl+C into a cancellation of the shares being processed.
+//!
+//! While a [`CancelOnCtrlC`] exists, the first Ctrl+C (or Ctrl+Break) sets [`CANCEL`], so that the
+//! library stops processing before its next phase. A connection attempt that hangs cannot be
+//! cancelled that way, so a second Ctrl+C terminates the process as usual.
+
+
+use open_share::CancellationToken;
+use windows::Win32::Foundation::{BOOL, FALSE, TRUE};
+use windows::Win32::System::Console::{CTRL_BREAK_EVENT, CTRL_C_EVENT, SetConsoleCtrlHandler};
+
+use crate::report::progress;
+
+
+/// The token set by Ctrl+C.
+pub(crate) static CANCEL: CancellationToken = CancellationToken::new();
+
+
+unsafe extern "system" fn handle_ctrl(ctrl_type: u32) -> BOOL {
+    if (ctrl_type != CTRL_C_EVENT && ctrl_type != CTRL_BREAK_EVENT) || CANCEL.is_cancelled() {
+        // let the default handler terminate the process
+        return FALSE;
+    }
+    CANCEL.cancel();
+    progress("cancelling; press Ctrl+C again to exit immediately");
+    TRUE
+}
+
+
+/// Sets [`CANCEL`] on Ctrl+C until dropped.
+pub(crate) struct CancelOnCtrlC;
+impl CancelOnCtrlC {
+    pub fn install() -> Self {
+        let _ = unsafe { SetConsoleCtrlHandler(Some(handle_ctrl), TRUE) };
+        Self
+    }
+}
+impl Drop for CancelOnCtrlC {
+    fn drop(&mut self) {
+        let _ = unsafe { SetConsoleCtrlHandler(Some(handle_ctrl), FALSE) };
+    }
+}
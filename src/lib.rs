@@ -0,0 +1,613 @@
+mod batch;
+mod browse;
+#[cfg(feature = "credential-manager")]
+mod cached;
+mod cancel;
+mod cmdline;
+mod connection;
+mod copy;
+mod dfs;
+mod dialect;
+mod diagnose;
+mod drive;
+mod envblock;
+mod ensure;
+mod error;
+mod fqdn;
+mod home;
+mod log;
+mod longname;
+#[cfg(feature = "offline-files")]
+mod offline;
+mod probe;
+#[cfg(feature = "modern-prompt")]
+mod prompt;
+mod provider;
+mod register;
+mod registry;
+mod redirection;
+mod request;
+mod service;
+mod shell;
+mod space;
+mod stale;
+#[cfg(feature = "smartcard")]
+pub mod smartcard;
+mod summary;
+mod template;
+mod unc;
+mod writable;
+mod wstr;
+
+
+use std::io::Error as IoError;
+use std::time::Instant;
+
+use windows::Win32::Foundation::{
+    ERROR_ACCESS_DENIED, ERROR_BAD_NET_NAME, ERROR_FILE_NOT_FOUND, ERROR_PATH_NOT_FOUND,
+};
+
+use crate::connection::DeviceConnection;
+use crate::log::{log, Logger};
+
+
+pub use crate::batch::{BatchOptions, deduplicate_requests, open_shares};
+pub use crate::browse::{list_shares, ServerShare};
+pub use crate::cancel::CancellationToken;
+pub use crate::connection::{
+    Connection, ConnectionInfo, ConnectionScope, ConnectionSort, connection_user,
+    DEFAULT_ENUMERATION_BUFFER_KIB, EnumerationOptions, ResourceType, sort_connections,
+};
+pub use crate::copy::copy_into_share;
+pub use crate::diagnose::{
+    has_kerberos_tgt, logon_failure_hints, running_as_system, workstation_appears_stopped,
+};
+pub use crate::ensure::{ensure_mapped, EnsureAction};
+pub use crate::envblock::environment_block;
+pub use crate::error::{ConnectContext, OpenShareError, win32_error_name};
+pub use crate::fqdn::path_with_fqdn;
+pub use crate::home::home_share;
+pub use crate::log::{LogEvent, LogLevel, LogPhase, ShareLogger};
+#[cfg(feature = "offline-files")]
+pub use crate::offline::{Availability, share_availability};
+pub use crate::probe::{
+    AddressFamily, addresses_to_probe, probe_host, probe_host_with, ProbeError, Resolver, SMB_PORT,
+    SystemResolver,
+};
+pub use crate::provider::{network_providers, NetworkProvider, smb_provider_name};
+pub use crate::redirection::{RedirectedFolder, redirected_folders_on_share};
+pub use crate::register::register_mapping;
+pub use crate::request::{DEFAULT_CONNECT_FLAGS, DEFAULT_DRIVE_WAIT, ShareRequest, ShareRequestBuilder};
+pub use crate::service::{query_service_state, ServiceState, WORKSTATION_SERVICE};
+pub use crate::shell::explorer_opens_tabs;
+pub use crate::space::{query_space, ShareSpace};
+pub use crate::summary::{CredentialSource, Summary};
+pub use crate::template::{expand_comment, expand_tokens};
+pub use crate::unc::{normalize_path, server_root, UncPath};
+use crate::unc::normalized_share_root;
+
+
+/// The errors of opening a share with [`ShareRequest::assume_connected`] after which it is
+/// connected and opened again. `ShellExecuteW` reports other errors (e.g. a missing server) using
+/// these codes as well, in which case connecting fails with the actual error.
+const ASSUME_CONNECTED_FALLBACK_CODES: [u32; 3] = [
+    ERROR_FILE_NOT_FOUND.0, ERROR_PATH_NOT_FOUND.0, ERROR_ACCESS_DENIED.0,
+];
+
+/// The errors of opening a connected share after which the share itself is checked for existence;
+/// `ShellExecuteW` reports a missing share as a missing file or path, too.
+const SHARE_MISSING_CODES: [u32; 3] = [
+    ERROR_BAD_NET_NAME.0, ERROR_FILE_NOT_FOUND.0, ERROR_PATH_NOT_FOUND.0,
+];
+
+
+/// Connects to the requested share unless it is connected already, then opens it.
+///
+/// Once `cancel` is set (e.g. from another thread), processing stops before its next phase with
+/// [`OpenShareError::Cancelled`]; see [`CancellationToken`]. Progress and diagnostics are passed
+/// to `logger`, if any.
+pub fn open_share(request: &ShareRequest, cancel: Option<&CancellationToken>, logger: Option<&dyn ShareLogger>) -> Result<Summary, OpenShareError> {
+    if opens_optimistically(request) {
+        return open_assuming_connected(request, cancel, logger);
+    }
+    let connected = connect_share(request, cancel, logger)?;
+    open_connected_share(request, connected, cancel, logger)
+}
+
+
+/// Whether the requested share is currently connected (to the requested drive, if any).
+pub fn is_connected(request: &ShareRequest, logger: Option<&dyn ShareLogger>) -> bool {
+    connection::find_existing_connection(request.path(), request.drive(), request.enumeration(), logger).is_some()
+}
+
+/// Counts the existing disk connections, both mapped to drives and deviceless ones.
+///
+/// Returns `None` if the connections could not be enumerated.
+pub fn connection_count(options: &EnumerationOptions, logger: Option<&dyn ShareLogger>) -> Option<usize> {
+    connection::count_connections(options, logger)
+}
+
+/// Lists all existing connections: drives, deviceless connections and printers, sorted by server,
+/// share and local name (see [`sort_connections`] for other orders).
+///
+/// ```no_run
+/// for connection in open_share::enumerate_connections(&Default::default(), None)? {
+///     if let (Some(local), Some(remote)) = (&connection.local_name, &connection.remote_name) {
+///         println!("{} -> {}", local, remote);
+///     }
+/// }
+/// # Ok::<(), open_share::OpenShareError>(())
+/// ```
+pub fn enumerate_connections(options: &EnumerationOptions, logger: Option<&dyn ShareLogger>) -> Result<Vec<ConnectionInfo>, OpenShareError> {
+    connection::enumerate_connections(options, logger)
+}
+
+
+
+/// Connects to the requested share without checking whether it is connected already and without
+/// opening it.
+pub fn connect(request: &ShareRequest, logger: Option<&dyn ShareLogger>) -> Result<(), OpenShareError> {
+    connection::connect_to_share(request, logger).map(|_| ())
+}
+
+/// Determines where the credentials will come from when connecting for the request, without
+/// connecting; see [`Summary::credential_source`]. Whether Windows would ask for credentials on
+/// its own cannot be known beforehand, so a request with a username but without a password that
+/// allows prompting counts as [`CredentialSource::Prompt`].
+pub fn expected_credential_source(request: &ShareRequest) -> CredentialSource {
+    connection::expected_credential_source(request)
+}
+
+/// Opens the requested path (e.g. an `http://` or `https://` URL) using its shell verb without
+/// connecting to it first, so that a URL is handed to the default browser instead of being mounted.
+pub fn open_without_connecting(request: &ShareRequest, logger: Option<&dyn ShareLogger>) -> Result<(), OpenShareError> {
+    shell::open_path(request.path(), request.verb(), request.window(), logger)
+}
+
+/// Disconnects the requested share (or its drive, if any), failing if files are open on it. The
+/// connection is also removed from the profile so that it is not restored at logon.
+pub fn disconnect(request: &ShareRequest, logger: Option<&dyn ShareLogger>) -> Result<(), OpenShareError> {
+    let name = request.drive().unwrap_or(request.path());
+    connection::disconnect(name, true, false, logger)
+}
+
+/// Resolves a local device (e.g. `X:`) to the remote name it is mapped to.
+///
+/// Devices that are remembered but currently disconnected are resolved as well. Returns `None` if
+/// the device is not mapped to a network resource.
+pub fn device_remote_name(device: &str) -> Option<String> {
+    match connection::device_connection(device) {
+        DeviceConnection::Connected(remote) | DeviceConnection::Unavailable(remote) => Some(remote),
+        DeviceConnection::None => None,
+    }
+}
+
+/// The drive letters that [`free_drive`] picks from by default, in order: `Z:` down to `D:`, as
+/// letters at the end of the alphabet are the least likely to be taken by local drives.
+pub const DEFAULT_DRIVE_POOL: &str = "ZYXWVUTSRQPONMLKJIHGFED";
+
+/// Picks the first of the given drive letters (e.g. `"PQR"`) that is neither in use nor
+/// remembered as a mapping, returning it as a device name (e.g. `P:`).
+///
+/// Characters other than ASCII letters are skipped. Returns `None` if every letter is taken.
+pub fn free_drive(pool: &str) -> Option<String> {
+    pool.chars()
+        .filter(|c| c.is_ascii_alphabetic())
+        .map(|c| format!("{}:", c.to_ascii_uppercase()))
+        .find(|drive| drive::drive_free(drive))
+}
+
+/// Finds the existing connections matching the given name, which is either a local device (e.g.
+/// `X:`) or a remote name.
+pub fn find_connections(name: &str, options: &EnumerationOptions, logger: Option<&dyn ShareLogger>) -> Vec<Connection> {
+    connection::find_matching_connections(name, options, logger)
+}
+
+/// Finds the existing connections to any share on the given server (`server` or `\\server`),
+/// whether mapped to drives or deviceless.
+pub fn find_server_connections(server: &str, options: &EnumerationOptions, logger: Option<&dyn ShareLogger>) -> Vec<Connection> {
+    connection::find_server_connections(server.trim_start_matches('\\'), options, logger)
+}
+
+/// Disconnects the given local device or all connections to the given remote name, also removing
+/// them from the profile. Unless `force` is set, this fails if files are open on a connection.
+pub fn cancel_connection(name: &str, force: bool, logger: Option<&dyn ShareLogger>) -> Result<(), OpenShareError> {
+    connection::disconnect(name, true, force, logger)
+}
+
+
+/// The state of a share after [`connect_share`].
+#[derive(Clone, Debug)]
+pub(crate) struct Connected {
+    canonical: Option<String>,
+    already_connected: bool,
+
+    /// The path connected instead of the requested one because of
+    /// [`ShareRequest::admin_fallback`].
+    substitute: Option<String>,
+
+    credential_source: CredentialSource,
+}
+
+/// Connects to the requested share unless it is connected already.
+pub(crate) fn connect_share(request: &ShareRequest, cancel: Option<&CancellationToken>, logger: Logger<'_>) -> Result<Connected, OpenShareError> {
+    cancel::check(cancel)?;
+    if request.clear_cached_credentials() {
+        let server = UncPath::parse(request.path())
+            .map(|unc| unc.server)
+            .or_else(|| server_root(request.path()));
+        match server {
+            #[cfg(feature = "credential-manager")]
+            Some(server) => cached::clear_cached_credentials(server, request.credential_target().unwrap_or(server), request.enumeration(), logger),
+            #[cfg(not(feature = "credential-manager"))]
+            Some(_) => return Err(OpenShareError::NotCompiledIn { feature: "credential-manager" }),
+            None => log!(logger, Warning, Connect, "{} is not a UNC path; not clearing cached credentials", request.path()),
+        }
+    }
+
+    if let Some(server) = server_root(request.path()) {
+        return connect_server(request, server, cancel, logger);
+    }
+    if request.skip_check() {
+        return connect_unchecked(request, logger);
+    }
+
+    let mut canonical = connection::find_existing_connection(request.path(), request.drive(), request.enumeration(), logger);
+    if request.heal_stale() {
+        canonical = stale::heal(request, canonical, logger);
+    }
+    if request.reuse_any() {
+        if let Some(found) = &canonical {
+            log!(logger, Warning, Check, "reusing the existing connection to {}, which may belong to another identity than requested", found);
+        } else if let Some(other) = reusable_server_connection(request, logger) {
+            log!(
+                logger, Warning, Check,
+                "reusing the existing connection to {} for {}, which may belong to another identity than requested; not connecting",
+                other, request.path(),
+            );
+            return Ok(Connected {
+                canonical: None,
+                already_connected: true,
+                substitute: None,
+                credential_source: CredentialSource::ExistingConnection,
+            });
+        }
+    }
+    let already_connected = canonical.is_some();
+    let mut substitute = None;
+    let mut credential_source = CredentialSource::ExistingConnection;
+    if !already_connected {
+        if let Some(limit) = request.max_connections() {
+            match connection::count_connections(request.enumeration(), logger) {
+                Some(count) if count >= limit => return Err(OpenShareError::ConnectionLimit { count, limit }),
+                Some(_) => {},
+                None => log!(logger, Warning, Check, "cannot count the existing connections; ignoring the connection limit..."),
+            }
+        }
+        cancel::check(cancel)?;
+        (credential_source, substitute) = connect_with_admin_fallback(request, logger)?;
+        let connected_path = substitute.as_deref().unwrap_or(request.path());
+        canonical = connection::find_existing_connection(connected_path, request.drive(), request.enumeration(), logger);
+    }
+    Ok(Connected {
+        canonical,
+        already_connected,
+        substitute,
+        credential_source,
+    })
+}
+
+/// An existing connection to another share on the server of the request that
+/// [`ShareRequest::reuse_any`] allows opening the share through.
+fn reusable_server_connection(request: &ShareRequest, logger: Logger<'_>) -> Option<String> {
+    if request.drive().is_some() {
+        return None;
+    }
+    let unc = UncPath::parse(request.path())?;
+    connection::find_server_connections(unc.server, request.enumeration(), logger)
+        .into_iter()
+        .next()
+        .map(|c| c.remote_name)
+}
+
+/// Prepares opening a path naming a server (`\\server`), for which there is no share to connect.
+/// With [`ShareRequest::authenticate_server`], the server's `IPC$` share is connected (unless it is
+/// connected already) so that its shares are listed using the requested credentials.
+fn connect_server(request: &ShareRequest, server: &str, cancel: Option<&CancellationToken>, logger: Logger<'_>) -> Result<Connected, OpenShareError> {
+    if !request.authenticate_server() {
+        log!(logger, Info, Connect, "{} names a server; opening it without connecting", request.path());
+        return Ok(Connected {
+            canonical: None,
+            already_connected: false,
+            substitute: None,
+            credential_source: CredentialSource::CurrentUser,
+        });
+    }
+    let ipc_path = format!("\\\\{}\\IPC$", server);
+    log!(logger, Info, Connect, "authenticating to {} using {}...", server, ipc_path);
+    connect_share(&request.with_path(ipc_path), cancel, logger)
+}
+
+/// Connects to the requested share or, if it does not exist and
+/// [`ShareRequest::admin_fallback`] is set, to its administrative path. Returns where the
+/// credentials came from and the administrative path if it was connected instead.
+fn connect_with_admin_fallback(request: &ShareRequest, logger: Logger<'_>) -> Result<(CredentialSource, Option<String>), OpenShareError> {
+    let error = match connection::connect_to_share(request, logger) {
+        Ok(source) => return Ok((source, None)),
+        Err(e) => e,
+    };
+    let not_shared = matches!(error, OpenShareError::Connect { code, .. } if code == ERROR_BAD_NET_NAME.0);
+    if !not_shared || !request.admin_fallback() {
+        return Err(error);
+    }
+    let Some(fallback) = UncPath::parse(request.path())
+        .and_then(|unc| unc.administrative_path(request.admin_fallback_dir())) else {
+        return Err(error);
+    };
+    log!(logger, Warning, Connect, code = ERROR_BAD_NET_NAME.0, "{} is not shared; connecting to {} instead", request.path(), fallback);
+    let source = connection::connect_to_share(&request.with_path(fallback.clone()), logger)?;
+    Ok((source, Some(fallback)))
+}
+
+/// Connects to the requested share without checking whether it is connected already, treating a
+/// drive that is already mapped to it as connected.
+fn connect_unchecked(request: &ShareRequest, logger: Logger<'_>) -> Result<Connected, OpenShareError> {
+    let (already_connected, credential_source, substitute) = match connect_with_admin_fallback(request, logger) {
+        Ok((source, substitute)) => (false, source, substitute),
+        Err(OpenShareError::DriveInUse { holder: Some(holder), .. })
+            if normalized_share_root(&holder) == normalized_share_root(request.path()) => {
+            log!(logger, Info, Check, "{} is mapped to {} already", request.drive().unwrap_or(""), holder);
+            (true, CredentialSource::ExistingConnection, None)
+        },
+        Err(e) => return Err(e),
+    };
+    Ok(Connected {
+        canonical: None,
+        already_connected,
+        substitute,
+        credential_source,
+    })
+}
+
+/// Opens a share that has been connected using [`connect_share`].
+pub(crate) fn open_connected_share(request: &ShareRequest, connected: Connected, cancel: Option<&CancellationToken>, logger: Logger<'_>) -> Result<Summary, OpenShareError> {
+    cancel::check(cancel)?;
+    let Connected { canonical, already_connected, substitute, credential_source } = connected;
+    let substitute_request = substitute.map(|path| request.with_path(path));
+    let request = substitute_request.as_ref().unwrap_or(request);
+
+    let start = Instant::now();
+    let mut opened_path = request.open_target();
+    if let (false, Some(drive), Some(wait)) = (already_connected, request.drive(), request.drive_wait()) {
+        let bounded_wait = match request.open_timeout() {
+            Some(timeout) => wait.min(timeout),
+            None => wait,
+        };
+        if !drive::wait_for_drive(drive, bounded_wait, cancel)? {
+            if let Some(timeout) = request.open_timeout().filter(|t| *t < wait) {
+                return Err(OpenShareError::OpenTimeout { timeout });
+            }
+            log!(logger, Warning, Open, "drive {} did not appear within {:?}; opening {} instead", drive, wait, request.path());
+            opened_path = request.path().to_owned();
+        }
+    }
+
+    if request.require_write() {
+        let root = share_root(request.path());
+        writable::check_write_access(&root, logger)
+            .map_err(|code| check_share_exists(request, OpenShareError::NotWritable { code }))?;
+        log!(logger, Info, Open, "{} is writable", root);
+    }
+
+    let mut long_path = None;
+    if request.resolve_long_names() {
+        match longname::long_path_name(request.path()) {
+            Ok(long) => {
+                if opened_path == request.path() {
+                    opened_path = long.clone();
+                }
+                long_path = Some(long);
+            },
+            Err(code) => log!(
+                logger, Warning, Open, code = code,
+                "cannot resolve the long name of {}; keeping it as is: {}",
+                request.path(), IoError::from_raw_os_error(code as i32),
+            ),
+        }
+    }
+
+    cancel::check(cancel)?;
+    log!(logger, Info, Open, "launching...");
+    if request.terminal() {
+        shell::open_terminal(&opened_path, request.window(), logger)?;
+    } else if let Some(program) = request.open_with() {
+        let parameters = cmdline::open_with_parameters(request.open_with_args(), &opened_path);
+        shell::open_with(program, &parameters, request.window(), logger)?;
+    } else if request.properties() {
+        match shell::open_properties(&opened_path, start, request.open_timeout(), cancel, logger) {
+            Ok(()) => {},
+            Err(e @ (OpenShareError::OpenTimeout { .. } | OpenShareError::Cancelled)) => return Err(e),
+            Err(e) => {
+                log!(logger, Error, Open, code = e.win32_code(), "{}", e);
+                log!(logger, Info, Open, "cannot show the properties of {}; opening it instead...", opened_path);
+                shell::open_path(&opened_path, request.verb(), request.window(), logger)
+                    .map_err(|e| check_share_exists(request, e))?;
+            },
+        }
+    } else if !already_shown(request, &opened_path, logger) {
+        shell::open_path(&opened_path, request.verb(), request.window(), logger)
+            .map_err(|e| check_share_exists(request, e))?;
+    }
+
+    Ok(summarize(request, canonical, long_path.as_deref(), already_connected, credential_source, opened_path, logger))
+}
+
+/// The root of the share that the path is in (`\\server\share`), keeping the case of the path.
+fn share_root(path: &str) -> String {
+    match UncPath::parse(path) {
+        Some(unc) => format!("\\\\{}\\{}", unc.server, unc.share),
+        None => path.to_owned(),
+    }
+}
+
+/// Replaces an error of opening a connected share by [`OpenShareError::ShareNotFound`] if the
+/// error is one of [`SHARE_MISSING_CODES`] and the root of the share cannot be accessed because the
+/// server does not offer it. This happens if the share name is misspelled but connecting succeeded
+/// anyway, e.g. by reusing an existing connection to the server.
+fn check_share_exists(request: &ShareRequest, error: OpenShareError) -> OpenShareError {
+    let missing = matches!(
+        error,
+        OpenShareError::Open { code } | OpenShareError::NotWritable { code } if SHARE_MISSING_CODES.contains(&code)
+    );
+    let Some(unc) = UncPath::parse(request.path()).filter(|_| missing) else {
+        return error;
+    };
+    match stale::touch(&format!("\\\\{}\\{}\\", unc.server, unc.share)) {
+        Err(code) if code == ERROR_BAD_NET_NAME.0 => OpenShareError::ShareNotFound {
+            server: unc.server.to_owned(),
+            share: unc.share.to_owned(),
+        },
+        _ => error,
+    }
+}
+
+/// Whether opening the path can be skipped because an Explorer window already shows it; see
+/// [`ShareRequest::only_if_missing`].
+fn already_shown(request: &ShareRequest, path: &str, logger: Logger<'_>) -> bool {
+    if !request.only_if_missing() {
+        return false;
+    }
+    match shell::explorer_window_shows(path) {
+        Some(true) => {
+            log!(logger, Info, Open, "an Explorer window already shows {}; not opening it again", path);
+            true
+        },
+        Some(false) => false,
+        None => {
+            log!(logger, Warning, Open, "cannot enumerate the Explorer windows; opening {} anyway", path);
+            false
+        },
+    }
+}
+
+/// Whether the request is opened using [`open_assuming_connected`].
+pub(crate) fn opens_optimistically(request: &ShareRequest) -> bool {
+    request.assume_connected() && !request.terminal() && !request.properties() && request.open_with().is_none()
+}
+
+/// Opens the requested share right away, only connecting it if opening fails with one of
+/// [`ASSUME_CONNECTED_FALLBACK_CODES`]; see [`ShareRequest::assume_connected`].
+pub(crate) fn open_assuming_connected(request: &ShareRequest, cancel: Option<&CancellationToken>, logger: Logger<'_>) -> Result<Summary, OpenShareError> {
+    cancel::check(cancel)?;
+    let opened_path = request.open_target();
+    if already_shown(request, &opened_path, logger) {
+        return Ok(summarize(request, None, None, true, CredentialSource::ExistingConnection, opened_path, logger));
+    }
+    log!(logger, Info, Open, "launching without checking the connection...");
+    match shell::open_path(&opened_path, request.verb(), request.window(), logger) {
+        Ok(()) => Ok(summarize(request, None, None, true, CredentialSource::ExistingConnection, opened_path, logger)),
+        Err(OpenShareError::Open { code }) if ASSUME_CONNECTED_FALLBACK_CODES.contains(&code) => {
+            log!(
+                logger, Info, Open, code = code,
+                "cannot open {} ({}); connecting...",
+                opened_path, IoError::from_raw_os_error(code as i32),
+            );
+            let connected = connect_share(request, cancel, logger)?;
+            open_connected_share(request, connected, cancel, logger)
+        },
+        Err(e) => Err(e),
+    }
+}
+
+/// Collects the information about a share that has been opened, querying its space and SMB
+/// dialect.
+fn summarize(
+    request: &ShareRequest,
+    canonical: Option<String>,
+    long_path: Option<&str>,
+    already_connected: bool,
+    credential_source: CredentialSource,
+    opened_path: String,
+    logger: Logger<'_>,
+) -> Summary {
+    let space = if request.query_space() {
+        match space::query_space(&opened_path) {
+            Ok(s) => Some(s),
+            Err(code) => {
+                log!(logger, Error, Open, code = code, "failed to query the space of {}! {}", opened_path, IoError::from_raw_os_error(code as i32));
+                None
+            },
+        }
+    } else {
+        None
+    };
+
+    let smb_dialect = dialect::smb_dialect(&opened_path);
+    if smb_dialect.as_deref().map(|d| d.starts_with("1.")).unwrap_or(false) {
+        log!(logger, Warning, Open, "{} is connected using SMB1, which is insecure and deprecated", request.path());
+    }
+
+    Summary {
+        space,
+        smb_dialect,
+        ..summary_of(request, canonical, long_path, already_connected, credential_source, opened_path)
+    }
+}
+
+/// The summary of a share that has been opened, without the information that has to be queried
+/// from the system. `long_path` is the long form of the path if it was resolved; see
+/// [`ShareRequest::resolve_long_names`].
+fn summary_of(
+    request: &ShareRequest,
+    canonical: Option<String>,
+    long_path: Option<&str>,
+    already_connected: bool,
+    credential_source: CredentialSource,
+    opened_path: String,
+) -> Summary {
+    let administrative_share = UncPath::parse(request.path())
+        .map(|unc| unc.is_administrative_share())
+        .unwrap_or(false);
+    Summary {
+        input: request.path().to_owned(),
+        normalized: normalize_path(long_path.unwrap_or(request.path())),
+        canonical,
+        administrative_share,
+        already_connected,
+        opened_path,
+        credential_source,
+        space: None,
+        smb_dialect: None,
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn summary_keeps_input_normalized_and_canonical_apart() {
+        let request = ShareRequest::builder("//SERVER/Share/").build();
+        let summary = summary_of(
+            &request, Some("\\\\Server\\Share".to_owned()), None, false, CredentialSource::CurrentUser,
+            "//SERVER/Share/".to_owned(),
+        );
+        assert_eq!(summary.input, "//SERVER/Share/");
+        assert_eq!(summary.normalized, "\\\\server\\share");
+        assert_eq!(summary.canonical.as_deref(), Some("\\\\Server\\Share"));
+        assert!(!summary.administrative_share);
+    }
+
+    #[test]
+    fn summary_normalizes_the_long_path() {
+        let request = ShareRequest::builder("\\\\SERVER\\Share\\PROGRA~1").build();
+        let summary = summary_of(
+            &request, None, Some("\\\\SERVER\\Share\\Program Files"), true, CredentialSource::ExistingConnection,
+            "\\\\SERVER\\Share\\Program Files".to_owned(),
+        );
+        assert_eq!(summary.input, "\\\\SERVER\\Share\\PROGRA~1");
+        assert_eq!(summary.normalized, "\\\\server\\share\\program files");
+        assert_eq!(summary.canonical, None);
+    }
+}
@@ -0,0 +1,151 @@
+//! Reporting progress and diagnostics to the embedding program.
+//!
+//! The library does not write to stderr itself. Instead, it passes [`LogEvent`]s to the
+//! [`ShareLogger`] given to each call (e.g. [`open_share`](crate::open_share)); without one, the
+//! events are discarded. As the logger is passed per call rather than registered for the process,
+//! several callers in the same process can each receive their own events.
+
+
+use std::fmt;
+
+use serde::Serialize;
+
+
+/// How important a [`LogEvent`] is.
+#[derive(Clone, Copy, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub enum LogLevel {
+    /// Information for debugging, such as dumps of the structures passed to Windows.
+    Debug,
+
+    /// Progress information.
+    Info,
+
+    /// Progress information reporting that a step succeeded, e.g. connecting or launching.
+    Success,
+
+    /// Something that worked but may need attention.
+    Warning,
+
+    /// Something that failed.
+    Error,
+}
+
+/// The part of the processing that a [`LogEvent`] belongs to.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum LogPhase {
+    /// Checking the existing connections.
+    Check,
+
+    /// Connecting to a share.
+    Connect,
+
+    /// Opening a connected share.
+    Open,
+
+    /// Removing a connection.
+    Disconnect,
+}
+
+/// A message emitted by the library.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct LogEvent {
+    pub level: LogLevel,
+    pub phase: LogPhase,
+    pub message: String,
+
+    /// The Win32 error code that the message is about, if any.
+    pub code: Option<u32>,
+}
+
+/// Receives the [`LogEvent`]s emitted by the library.
+///
+/// Events may be emitted from several threads at once when shares are connected concurrently.
+pub trait ShareLogger: Send + Sync {
+    fn log(&self, event: &LogEvent);
+}
+
+
+/// The logger that the events of a call into the library are passed to; `None` discards them.
+pub(crate) type Logger<'a> = Option<&'a dyn ShareLogger>;
+
+
+/// Passes an event to the logger, if any. The message is only formatted if there is one.
+pub(crate) fn emit(logger: Logger<'_>, level: LogLevel, phase: LogPhase, code: Option<u32>, message: fmt::Arguments<'_>) {
+    if let Some(logger) = logger {
+        logger.log(&LogEvent {
+            level,
+            phase,
+            message: message.to_string(),
+            code,
+        });
+    }
+}
+
+/// Emits a [`LogEvent`] to a [`Logger`]: `log!(logger, Level, Phase, "format", args...)`,
+/// optionally with a Win32 error code: `log!(logger, Level, Phase, code = c, "format", args...)`.
+macro_rules! log {
+    ($logger:expr, $level:ident, $phase:ident, code = $code:expr, $($arg:tt)+) => {
+        $crate::log::emit(
+            $logger,
+            $crate::log::LogLevel::$level,
+            $crate::log::LogPhase::$phase,
+            Some($code),
+            format_args!($($arg)+),
+        )
+    };
+    ($logger:expr, $level:ident, $phase:ident, $($arg:tt)+) => {
+        $crate::log::emit(
+            $logger,
+            $crate::log::LogLevel::$level,
+            $crate::log::LogPhase::$phase,
+            None,
+            format_args!($($arg)+),
+        )
+    };
+}
+pub(crate) use log;
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Mutex;
+    use std::thread::scope;
+
+    use crate::{connect_share, ShareRequest};
+
+    #[derive(Default)]
+    struct RecordingLogger {
+        events: Mutex<Vec<LogEvent>>,
+    }
+    impl ShareLogger for RecordingLogger {
+        fn log(&self, event: &LogEvent) {
+            self.events.lock().unwrap().push(event.clone());
+        }
+    }
+
+    fn opening_server(server: &str) -> LogEvent {
+        LogEvent {
+            level: LogLevel::Info,
+            phase: LogPhase::Connect,
+            message: format!("\\\\{} names a server; opening it without connecting", server),
+            code: None,
+        }
+    }
+
+    #[test]
+    fn each_call_logs_to_its_own_logger() {
+        // connecting for a server path without authenticating makes no Win32 calls
+        let first = RecordingLogger::default();
+        let second = RecordingLogger::default();
+        scope(|s| {
+            s.spawn(|| connect_share(&ShareRequest::builder("\\\\alpha").build(), None, Some(&first)).unwrap());
+            s.spawn(|| connect_share(&ShareRequest::builder("\\\\beta").build(), None, Some(&second)).unwrap());
+        });
+        connect_share(&ShareRequest::builder("\\\\gamma").build(), None, None).unwrap();
+
+        assert_eq!(*first.events.lock().unwrap(), [opening_server("alpha")]);
+        assert_eq!(*second.events.lock().unwrap(), [opening_server("beta")]);
+    }
+}
@@ -0,0 +1,33 @@
+//! Resolving 8.3 short names (e.g. `PROJEC~1`) in paths to the long names.
+//!
+//! Only the server can tell which long name a short name stands for, so the path must exist and
+//! be accessible (i.e. the share must be connected). Server and share names have no short forms;
+//! only the components after the share are resolved.
+
+
+use windows::core::PCWSTR;
+use windows::Win32::Foundation::GetLastError;
+use windows::Win32::Storage::FileSystem::GetLongPathNameW;
+
+use crate::wstr::{str_to_wcstring, wcstr_to_string};
+
+
+/// Returns the given path with every short name replaced by the corresponding long name.
+///
+/// Returns the Win32 error code if the path cannot be resolved, e.g. because it does not exist.
+pub(crate) fn long_path_name(path: &str) -> Result<String, u32> {
+    let path_windows = str_to_wcstring(path);
+    let mut buffer = vec![0u16; path_windows.len()];
+    loop {
+        let length = unsafe { GetLongPathNameW(PCWSTR(path_windows.as_ptr()), Some(&mut buffer)) };
+        if length == 0 {
+            return Err(unsafe { GetLastError() }.0);
+        }
+        let length: usize = length.try_into().unwrap();
+        if length < buffer.len() {
+            return Ok(wcstr_to_string(buffer.as_ptr()));
+        }
+        // the buffer is too small; the length includes the terminating NUL
+        buffer.resize(length, 0);
+    }
+}
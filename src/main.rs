@@ -1,213 +1,797 @@
+mod args;
+mod audit;
+mod capabilities;
+mod color;
+#[cfg(feature = "credential-manager")]
+mod credential_blob;
+mod ephemeral;
+mod export;
+mod hook;
+mod interrupt;
+mod net_use;
+mod picker;
+mod report;
+mod selftest;
+mod toast;
+
+
 use std::env;
-use std::ffi::c_void;
-use std::io::{BufRead, Error as IoError, Read};
-use std::mem::size_of;
+use std::fs;
+use std::io::BufRead;
+use std::path::{Path, PathBuf};
 use std::process;
-use std::ptr::null_mut;
-use std::slice;
-
-use windows::core::{PCWSTR, PWSTR, w};
-use windows::Win32::Foundation::{ERROR_NO_MORE_ITEMS, HANDLE, HWND, NO_ERROR};
-use windows::Win32::UI::Shell::ShellExecuteW;
-use windows::Win32::UI::WindowsAndMessaging::SW_SHOWNORMAL;
-use windows::Win32::NetworkManagement::WNet::{
-    CONNECT_INTERACTIVE, CONNECT_PROMPT, CONNECT_TEMPORARY,  NETRESOURCEW, NET_RESOURCE_SCOPE,
-    RESOURCETYPE_DISK, RESOURCE_CONNECTED, WNET_OPEN_ENUM_USAGE, WNetAddConnection2W, WNetCloseEnum,
-    WNetEnumResourceW, WNetOpenEnumW,
+
+use open_share::{
+    BatchOptions, ConnectionScope, ConnectionSort, EnsureAction, EnumerationOptions, OpenShareError,
+    ShareRequest, Summary, UncPath,
 };
+use windows::Win32::Foundation::{
+    ERROR_ACCESS_DENIED, ERROR_DEVICE_IN_USE, ERROR_LOGON_FAILURE, ERROR_LOGON_TYPE_NOT_GRANTED,
+    ERROR_OPEN_FILES,
+};
+
+use crate::args::{Args, Command, OutputFormat, parse_command, USAGE_OPTIONS};
+use crate::color::Stream;
+use crate::report::{ErrorReporter, has_console, LOGGER};
+
+
+/// The exit code when a connection failed because the Workstation service is not running.
+const EXIT_WORKSTATION_STOPPED: i32 = 2;
+
+/// The exit code when connecting was refused because of `--max-connections`.
+const EXIT_CONNECTION_LIMIT: i32 = 3;
+
+/// The exit code when opening a connected share exceeded `--open-timeout`.
+const EXIT_OPEN_TIMEOUT: i32 = 4;
+
+/// The exit code when some shares were opened but others failed or were skipped.
+const EXIT_PARTIAL_SUCCESS: i32 = 5;
 
+/// The exit code when the server refused the connection because it has reached its own connection
+/// limit.
+const EXIT_SERVER_CONNECTION_LIMIT: i32 = 6;
+
+/// The exit code when the drive to map is not a valid local device name.
+const EXIT_INVALID_DRIVE: i32 = 7;
+
+/// The exit code when a share was connected, but `--require-write` could not write to it.
+const EXIT_NOT_WRITABLE: i32 = 8;
+
+/// The exit code when the server was reached and accepted the credentials, but has no share of the
+/// requested name.
+const EXIT_SHARE_NOT_FOUND: i32 = 9;
+
+/// The exit code when the arguments are invalid (`EX_USAGE` from `sysexits.h`).
+const EXIT_USAGE: i32 = 64;
+
+
+fn inner_main(args: &[String], reporter: &mut ErrorReporter) -> i32 {
+    let program_name: &str = match args.first() {
+        Some(pn) => pn,
+        None => "open-share",
+    };
+    let command = match parse_command(args.get(1..).unwrap_or(&[])) {
+        Ok(c) => c,
+        Err(e) => {
+            reporter.error(e);
+            reporter.error(format!("Usage: {} [OPTIONS] PATH [PATH...] USERNAME", program_name));
+            reporter.error(format!("       {} [OPTIONS] --smartcard|--credential-blob BLOB|--guest|--username-from-current PATH [PATH...]", program_name));
+            reporter.error(format!("       {} [OPTIONS] --stdin-kv < INPUT", program_name));
+            reporter.error(format!("       {} [DRIVE|*] PATH [PASSWORD|*] [/USER:USERNAME] [/PERSISTENT:YES|NO] [/SMARTCARD]", program_name));
+            reporter.error(format!("       {} DRIVE|PATH /DELETE [/Y]", program_name));
+            reporter.error(format!("       {} ensure [OPTIONS] PATH DRIVE [USERNAME]", program_name));
+            reporter.error(format!("       {} disconnect [--force] [--dry-run|--verify] [--post-disconnect COMMAND] PATH|DRIVE", program_name));
+            reporter.error(format!("       {} disconnect-server [--force] SERVER", program_name));
+            reporter.error(format!("       {} browse [--pick] SERVER", program_name));
+            reporter.error(format!("       {} export-config FILE", program_name));
+            reporter.error(format!("       {} list [--sort remote|local|provider] [--output text|json] [--scope connected|remembered|globalnet]", program_name));
+            reporter.error(format!("       {} selftest [--prefer-ipv4|--prefer-ipv6] [SERVER]", program_name));
+            reporter.error(format!("       {} --capabilities", program_name));
+            reporter.error(format!("       {} --print-unc DRIVE|PATH", program_name));
+            reporter.error(format!("       {} --list-providers [--output text|json|--json]", program_name));
+            reporter.error(USAGE_OPTIONS);
+            return EXIT_USAGE;
+        },
+    };
 
-fn wcstr_to_string(ptr: *const u16) -> String {
-    let mut moving_ptr = ptr;
-    let mut utf16_buf = Vec::new();
-    while unsafe { *moving_ptr } != 0x0000 {
-        utf16_buf.push(unsafe { *moving_ptr });
-        moving_ptr = moving_ptr.wrapping_add(1);
+    match command {
+        Command::Open(parsed) => run_open(&parsed, reporter),
+        Command::SelfTest { server, family } => selftest::run(server.as_deref(), family),
+        Command::Ensure(request) => run_ensure(&request, reporter),
+        Command::Disconnect { name, force, dry_run, verify, post_disconnect } => {
+            run_disconnect(&name, force, dry_run, verify, post_disconnect.as_deref(), reporter)
+        },
+        Command::Capabilities => capabilities::run(),
+        Command::PrintUnc { name } => run_print_unc(&name, reporter),
+        Command::ListProviders { output } => run_list_providers(output, reporter),
+        Command::Browse { server, pick } => run_browse(&server, pick, reporter),
+        Command::List { sort, output, scope } => run_list(sort, output, scope, reporter),
+        Command::DisconnectServer { server, force } => run_disconnect_server(&server, force, reporter),
+        Command::ExportConfig { file } => export::run(&file, reporter),
     }
-    String::from_utf16(&utf16_buf).unwrap()
 }
 
-fn str_to_wcstring(s: &str) -> Vec<u16> {
-    let mut ret = Vec::with_capacity(s.len() + 1);
-    for w in s.encode_utf16() {
-        ret.push(w);
+fn run_disconnect(name: &str, force: bool, dry_run: bool, verify: bool, post_disconnect: Option<&str>, reporter: &mut ErrorReporter) -> i32 {
+    let connections = open_share::find_connections(name, &EnumerationOptions::default(), LOGGER);
+    if connections.is_empty() {
+        reporter.error(format!("{}: no matching connection", name));
+        return 1;
     }
-    ret.push(0);
-    ret
-}
 
+    let action = if dry_run { "would disconnect" } else { "disconnecting" };
+    for connection in &connections {
+        println!(
+            "{} {} ({})",
+            action, connection.remote_name, connection.local_name.as_deref().unwrap_or("no drive"),
+        );
+    }
+    if dry_run {
+        return 0;
+    }
 
-fn is_connection_already_open(path: &str) -> bool {
-    let path_lower = path.to_lowercase();
+    if let Err(e) = open_share::cancel_connection(name, force, LOGGER) {
+        reporter.error(format!("{}: {}", name, e));
+        return 1;
+    }
+    if verify && !verify_disconnected(name, force, reporter) {
+        return 1;
+    }
 
-    // check if a connection exists already
-    let mut enum_handle = HANDLE(null_mut());
-    let result = unsafe {
-        WNetOpenEnumW(
-            RESOURCE_CONNECTED,
-            RESOURCETYPE_DISK,
-            WNET_OPEN_ENUM_USAGE(0),
-            None,
-            &mut enum_handle,
-        )
-    };
-    if result != NO_ERROR {
-        eprintln!("failed to enumerate existing connections! {}", IoError::from_raw_os_error(result.0 as i32));
-        eprintln!("assuming connection is not yet open...");
-        return false;
-    }
-
-    let mut buffer = vec![0u8; 16*1024];
-    let mut found = false;
-    loop {
-        let mut count = -1i32 as u32;
-        let mut buf_size: u32 = buffer.len().try_into().unwrap();
-        let result = unsafe {
-            WNetEnumResourceW(
-                enum_handle,
-                &mut count,
-                buffer.as_mut_ptr() as *mut c_void,
-                &mut buf_size,
-            )
-        };
+    let mut exit_code = 0;
+    if let Some(command) = post_disconnect {
+        for connection in &connections {
+            let hook_exit_code = run_post_disconnect_hook(
+                command, &connection.remote_name, connection.local_name.as_deref(), reporter,
+            );
+            exit_code = exit_code.max(hook_exit_code);
+        }
+    }
+    exit_code
+}
 
-        if result == ERROR_NO_MORE_ITEMS {
-            break;
-        } else if result != NO_ERROR {
-            eprintln!("failed to obtain more connection enumeration results! {}", IoError::from_raw_os_error(result.0 as i32));
-            eprintln!("assuming connection is not yet open...");
-            let _ = unsafe {
-                WNetCloseEnum(enum_handle)
-            };
-            return false;
+/// Disconnects every connection to a share of the server, reporting the outcome of each and a
+/// summary. Returns 0 if all of them were disconnected, 1 otherwise.
+fn run_disconnect_server(server: &str, force: bool, reporter: &mut ErrorReporter) -> i32 {
+    let connections = open_share::find_server_connections(server, &EnumerationOptions::default(), LOGGER);
+    if connections.is_empty() {
+        reporter.error(format!("{}: no connections to this server", server));
+        return 1;
+    }
+
+    let mut disconnected = 0;
+    for connection in &connections {
+        let name = connection.local_name.as_deref().unwrap_or(&connection.remote_name);
+        let description = match &connection.local_name {
+            Some(local_name) => format!("{} ({})", connection.remote_name, local_name),
+            None => connection.remote_name.clone(),
+        };
+        match open_share::cancel_connection(name, force, LOGGER) {
+            Ok(()) => {
+                println!("disconnected {}", description);
+                disconnected += 1;
+            },
+            Err(OpenShareError::Disconnect { code }) if code == ERROR_DEVICE_IN_USE.0 || code == ERROR_OPEN_FILES.0 => {
+                reporter.error(format!("{}: in use (files are open on it); pass --force to disconnect it anyway", description));
+            },
+            Err(e) => reporter.error(format!("{}: {}", description, e)),
         }
+    }
+    println!("disconnected {} of {} connections to {}", disconnected, connections.len(), server);
+    if disconnected == connections.len() { 0 } else { 1 }
+}
 
-        // read memory as struct
-        let struct_size = size_of::<NETRESOURCEW>();
-        let structs_read: usize = count.try_into().unwrap();
-        let mut structs = vec![NETRESOURCEW::default(); structs_read];
-        unsafe {
-            let structs_slice = slice::from_raw_parts_mut(
-                structs.as_mut_ptr() as *mut u8,
-                struct_size * structs_read,
-            );
-            buffer.as_slice().read_exact(structs_slice).unwrap();
+/// Checks that no connection matching the name remains after disconnecting it, retrying with
+/// `force` once if one does.
+fn verify_disconnected(name: &str, force: bool, reporter: &mut ErrorReporter) -> bool {
+    // WNetCancelConnection2W may report success while the connection lingers
+    let mut remaining = open_share::find_connections(name, &EnumerationOptions::default(), LOGGER);
+    if !remaining.is_empty() && !force {
+        report::progress(format!("{} is still connected; retrying with --force...", name));
+        if let Err(e) = open_share::cancel_connection(name, true, LOGGER) {
+            reporter.error(format!("{}: {}", name, e));
+        }
+        remaining = open_share::find_connections(name, &EnumerationOptions::default(), LOGGER);
+    }
+    if remaining.is_empty() {
+        println!("verified: no connection to {} remains", name);
+        true
+    } else {
+        for connection in &remaining {
+            reporter.error(format!(
+                "still connected: {} ({})",
+                connection.remote_name, connection.local_name.as_deref().unwrap_or("no drive"),
+            ));
         }
+        false
+    }
+}
+
+/// Runs the `--post-disconnect` command for a disconnected share. Returns the exit code.
+fn run_post_disconnect_hook(command: &str, path: &str, drive: Option<&str>, reporter: &mut ErrorReporter) -> i32 {
+    match hook::run_post_disconnect(command, path, drive) {
+        Ok(0) => 0,
+        Ok(code) => {
+            reporter.error(format!("{}: {:?} exited with {}", path, command, code));
+            1
+        },
+        Err(e) => {
+            reporter.error(format!("{}: {}", path, e));
+            1
+        },
+    }
+}
 
-        // extract path
-        for st in structs {
-            if st.lpRemoteName.0 == null_mut() {
-                continue;
+fn run_list(sort: ConnectionSort, output: OutputFormat, scope: ConnectionScope, reporter: &mut ErrorReporter) -> i32 {
+    let options = EnumerationOptions { scope, ..EnumerationOptions::default() };
+    let mut connections = match open_share::enumerate_connections(&options, LOGGER) {
+        Ok(c) => c,
+        Err(e) => {
+            reporter.error(e.to_string());
+            return 1;
+        },
+    };
+    open_share::sort_connections(&mut connections, sort);
+
+    match output {
+        OutputFormat::Json => println!("{}", serde_json::to_string(&connections).unwrap()),
+        OutputFormat::Text => {
+            for connection in &connections {
+                println!(
+                    "{}\t{}\t{}",
+                    connection.local_name.as_deref().unwrap_or("-"),
+                    connection.remote_name.as_deref().unwrap_or("-"),
+                    connection.provider.as_deref().unwrap_or("-"),
+                );
             }
-            let remote_path_lower = wcstr_to_string(st.lpRemoteName.0)
-                .to_lowercase();
-            eprintln!("testing against path: {:?}", remote_path_lower);
-            if remote_path_lower == path_lower {
-                // we know this path!
-                found = true;
-                break;
+        },
+    }
+    0
+}
+
+/// Prints the installed network providers in their order, so that the right name can be passed to
+/// `--provider`.
+fn run_list_providers(output: OutputFormat, reporter: &mut ErrorReporter) -> i32 {
+    let Some(providers) = open_share::network_providers() else {
+        reporter.error("cannot read the network provider order from the registry");
+        return 1;
+    };
+    match output {
+        OutputFormat::Json => println!("{}", serde_json::to_string(&providers).unwrap()),
+        OutputFormat::Text => {
+            for provider in &providers {
+                println!("{}\t{}", provider.service, provider.name.as_deref().unwrap_or("-"));
             }
-        }
+        },
     }
+    0
+}
 
-    let result = unsafe {
-        WNetCloseEnum(HANDLE(enum_handle.0))
+fn run_browse(server: &str, pick: bool, reporter: &mut ErrorReporter) -> i32 {
+    let shares = match open_share::list_shares(server) {
+        Ok(s) => s,
+        Err(e) => {
+            reporter.error(format!("{}: {}", server, e));
+            return 1;
+        },
     };
-    if result != NO_ERROR {
-        eprintln!("failed to close existing connection enumeration! {}", IoError::from_raw_os_error(result.0 as i32));
+    if shares.is_empty() {
+        reporter.error(format!("{}: no shares found", server));
+        return 1;
+    }
+
+    let lines: Vec<String> = shares.iter()
+        .map(|share| match &share.remark {
+            Some(remark) => format!("{}\t{}", share.path, remark),
+            None => share.path.clone(),
+        })
+        .collect();
+    if !pick {
+        for line in &lines {
+            println!("{}", line);
+        }
+        return 0;
     }
 
-    found
+    let picked = match picker::pick(&lines) {
+        Ok(Some(index)) => &shares[index],
+        Ok(None) => return 0,
+        Err(e) => {
+            reporter.error(e);
+            return 1;
+        },
+    };
+    let request = ShareRequest::builder(picked.path.clone())
+        .build();
+    match open_share::open_share(&request, None, LOGGER) {
+        Ok(_) => 0,
+        Err(e) => {
+            reporter.error(format!("{}: {}", request.path(), e));
+            1
+        },
+    }
 }
 
-fn connect_to_share(path: &str, username: &str) -> bool {
-    let mut path_windows = str_to_wcstring(path);
-    let path_pwstr = PWSTR(path_windows.as_mut_ptr());
+fn run_ensure(request: &ShareRequest, reporter: &mut ErrorReporter) -> i32 {
+    match open_share::ensure_mapped(request, LOGGER) {
+        Ok(EnsureAction::AlreadyMapped) => println!("{}", color::success(Stream::Stdout, "unchanged")),
+        Ok(EnsureAction::Remapped { previous }) => {
+            let text = format!("remapped (previously {})", previous);
+            println!("{}", color::success(Stream::Stdout, &text));
+        },
+        Ok(EnsureAction::Mapped) => println!("{}", color::success(Stream::Stdout, "mapped")),
+        Err(e) => {
+            reporter.error(format!("{}: {}", request.path(), e));
+            return 1;
+        },
+    }
+    0
+}
 
-    let username_windows = str_to_wcstring(username);
-    let username_pcwstr = PCWSTR(username_windows.as_ptr());
+/// Copies the local file into the root of the connected share, reporting the progress in steps of
+/// ten percent.
+fn copy_in(source: &Path, overwrite: bool, request: &ShareRequest) -> Result<(), OpenShareError> {
+    let mut reported_tenths = 0;
+    let destination = open_share::copy_into_share(source, request.path(), overwrite, |copied, total| {
+        let tenths = copied.saturating_mul(10).checked_div(total).unwrap_or(10);
+        if tenths > reported_tenths {
+            reported_tenths = tenths;
+            report::progress(format!("{}: copied {}%", source.display(), tenths * 10));
+        }
+    })?;
+    report::progress(format!("{}: copied to {}", source.display(), destination));
+    Ok(())
+}
 
-    let net_resource = NETRESOURCEW {
-        dwType: RESOURCETYPE_DISK,
-        lpLocalName: PWSTR(null_mut()),
-        lpRemoteName: path_pwstr,
-        lpProvider: PWSTR(null_mut()),
+/// Writes the drive to the given file (`--write-drive-to`) by writing a temporary file next to it
+/// and renaming that over it, so that readers never see a partially written file.
+fn write_drive_file(path: &Path, drive: &str) -> std::io::Result<()> {
+    let mut temporary_name = path.as_os_str().to_owned();
+    temporary_name.push(".tmp");
+    let temporary_path = PathBuf::from(temporary_name);
+    fs::write(&temporary_path, drive)?;
+    if let Err(e) = fs::rename(&temporary_path, path) {
+        let _ = fs::remove_file(&temporary_path);
+        return Err(e);
+    }
+    Ok(())
+}
 
-        dwDisplayType: 0,
-        dwUsage: 0,
-        dwScope: NET_RESOURCE_SCOPE(0),
-        lpComment: PWSTR(null_mut()),
+/// Prints what was resolved for the request (`--print-env`) as `KEY=VALUE` lines to stderr. The
+/// password and marshalled credentials are redacted; values that do not apply are left empty.
+fn print_env(request: &ShareRequest) {
+    let unc = UncPath::parse(request.path());
+    let username = match request.username() {
+        Some(username) if username.starts_with("@@") => "<redacted>",
+        Some(username) => username,
+        None => "",
     };
+    let source = serde_json::to_value(open_share::expected_credential_source(request)).unwrap();
+    let lines = [
+        ("PATH", request.path().to_owned()),
+        ("NORMALIZED_PATH", open_share::normalize_path(request.path())),
+        ("SERVER", unc.map(|u| u.server).or_else(|| open_share::server_root(request.path())).unwrap_or("").to_owned()),
+        ("SHARE", unc.map(|u| u.share).unwrap_or("").to_owned()),
+        ("DRIVE", request.drive().unwrap_or("").to_owned()),
+        ("OPEN_TARGET", request.open_target()),
+        ("USERNAME", username.to_owned()),
+        ("USERNAME_SOURCE", source.as_str().unwrap_or("").to_owned()),
+        ("PASSWORD", if request.password().is_some() { "<redacted>" } else { "" }.to_owned()),
+        ("FLAGS", format!("0x{:08X}", request.flags().0)),
+        ("PROVIDER", request.provider().unwrap_or("").to_owned()),
+    ];
+    for (key, value) in lines {
+        eprintln!("OPEN_SHARE_{}={}", key, value);
+    }
+}
 
-    let result = unsafe {
-        WNetAddConnection2W(
-            &net_resource,
-            None,
-            username_pcwstr,
-            CONNECT_INTERACTIVE | CONNECT_PROMPT | CONNECT_TEMPORARY,
-        )
+/// Reports the health of each share without connecting or opening it. Returns 0 if all of them are
+/// connected and online, 1 otherwise.
+///
+/// Without the `offline-files` feature, whether a connected share is online cannot be told, so it
+/// is reported as merely connected.
+fn run_check_only(requests: &[ShareRequest]) -> i32 {
+    let mut exit_code = 0;
+    for request in requests {
+        if !open_share::is_connected(request, LOGGER) {
+            println!("{}: not connected", request.path());
+            exit_code = 1;
+            continue;
+        }
+        #[cfg(feature = "offline-files")]
+        {
+            let availability = open_share::share_availability(request.path());
+            if availability != open_share::Availability::Online {
+                exit_code = 1;
+            }
+            println!("{}: {}", request.path(), availability);
+        }
+        #[cfg(not(feature = "offline-files"))]
+        println!("{}: connected", request.path());
+    }
+    exit_code
+}
+
+/// Replaces the server of each path with its fully qualified domain name if `--fqdn` was given.
+fn resolved_paths(parsed: &Args) -> Vec<String> {
+    if !parsed.use_fqdn {
+        return parsed.paths.clone();
+    }
+    parsed.paths.iter()
+        .map(|path| match open_share::path_with_fqdn(path) {
+            Some(rewritten) => {
+                report::progress(format!("using {} for {}", rewritten, path));
+                rewritten
+            },
+            None => {
+                report::progress(format!("cannot resolve the server of {}; using it unchanged", path));
+                path.clone()
+            },
+        })
+        .collect()
+}
+
+fn run_open(parsed: &Args, reporter: &mut ErrorReporter) -> i32 {
+    let requested = parsed.to_share_requests(&resolved_paths(parsed));
+    let requests = match open_share::deduplicate_requests(&requested) {
+        Ok((unique, collapsed)) => {
+            for path in collapsed {
+                report::progress(format!("{}: listed more than once; processing it once", path));
+            }
+            unique
+        },
+        Err(e) => {
+            let conflicting = match &e {
+                OpenShareError::ConflictingCredentials { path, .. }
+                    => requested.iter().find(|r| r.path() == path),
+                _ => None,
+            };
+            match conflicting {
+                Some(request) => return report_error(reporter, parsed, request, &e),
+                None => {
+                    reporter.error(e.to_string());
+                    return 1;
+                },
+            }
+        },
     };
-    if result != NO_ERROR {
-        eprintln!("failed to connect! {}", IoError::from_raw_os_error(result.0 as i32));
-        return false;
-    }
-    eprintln!("connected!");
-    true
-}
-
-fn open_path(path: &str) -> bool {
-    let path_windows = str_to_wcstring(path);
-
-    let result = unsafe {
-        ShellExecuteW(
-            HWND(null_mut()),
-            w!("open"),
-            PCWSTR(path_windows.as_ptr()),
-            None,
-            None,
-            SW_SHOWNORMAL,
-        )
+    if parsed.print_env {
+        for (i, request) in requests.iter().enumerate() {
+            if i > 0 {
+                eprintln!();
+            }
+            print_env(request);
+        }
+    }
+    if parsed.warn_redirection {
+        for request in &requests {
+            for folder in open_share::redirected_folders_on_share(request.path()) {
+                report::progress(format!(
+                    "warning: {}: your {} folder is redirected to this share ({}); connecting it again may interfere with Folder Redirection and Offline Files",
+                    request.path(), folder.name, folder.location,
+                ));
+            }
+        }
+    }
+    if parsed.register_only {
+        let mut exit_code = 0;
+        for request in &requests {
+            if let Err(e) = open_share::register_mapping(request, LOGGER) {
+                exit_code = exit_code.max(report_error(reporter, parsed, request, &e));
+            }
+        }
+        return exit_code;
+    }
+    if parsed.open_in_browser {
+        let mut exit_code = 0;
+        for request in &requests {
+            if let Err(e) = open_share::open_without_connecting(request, LOGGER) {
+                exit_code = exit_code.max(report_error(reporter, parsed, request, &e));
+            }
+        }
+        return exit_code;
+    }
+    if parsed.check_only {
+        return run_check_only(&requests);
+    }
+    if !parsed.connect_as_system && open_share::running_as_system() == Some(true) {
+        report::progress("warning: running as SYSTEM, so shares are accessed using the computer account, which usually lacks access to user shares; pass a USERNAME and --password, or --connect-as-system to silence this warning");
+    }
+    if let Some(command) = &parsed.pre_connect {
+        for request in &requests {
+            let failure = match hook::run_pre_connect(command, request) {
+                Ok(0) => continue,
+                Ok(code) => format!("{}: {:?} exited with {}", request.path(), command, code),
+                Err(e) => format!("{}: {}", request.path(), e),
+            };
+            reporter.error(failure);
+            reporter.error("not connecting anything because --pre-connect failed");
+            return 1;
+        }
+    }
+    let batch_options = BatchOptions {
+        tabs: parsed.tabs,
+        parallel: parsed.parallel,
+        continue_on_error: parsed.continue_on_error,
+        open_delay: parsed.open_delay,
     };
-    let result_int = result.0 as usize;
-    if result_int <= 32 {
-        eprintln!("failed to open share! {}", IoError::from_raw_os_error(result_int as i32));
-        return false;
+    let ctrl_c = interrupt::CancelOnCtrlC::install();
+    let results = open_share::open_shares(&requests, &batch_options, Some(&interrupt::CANCEL), LOGGER);
+    drop(ctrl_c);
+
+    let log_path = parsed.log_file.clone()
+        .or_else(audit::default_log_path);
+    let mut exit_code = 0;
+    for (request, result) in requests.iter().zip(results.iter()) {
+        if let Some(lp) = &log_path {
+            let (outcome, code) = match result {
+                Ok(summary) if summary.already_connected => ("already-connected", 0),
+                Ok(_) => ("connected", 0),
+                Err(e) => ("failed", e.win32_code()),
+            };
+            audit::record(lp, request.path(), request.username(), outcome, code);
+        }
+
+        match result {
+            Ok(summary) => {
+                if let Some(space) = &summary.space {
+                    report::progress(format!(
+                        "{}: {} of {} bytes free",
+                        request.path(), space.free_bytes, space.total_bytes,
+                    ));
+                }
+                if let Some(source) = &parsed.copy_in {
+                    if let Err(e) = copy_in(source, parsed.copy_overwrite, request) {
+                        exit_code = exit_code.max(report_error(reporter, parsed, request, &e));
+                    }
+                }
+                if let (Some(file), Some(drive)) = (&parsed.write_drive_to, request.drive()) {
+                    if let Err(e) = write_drive_file(file, drive) {
+                        report::progress(format!("warning: failed to write the drive to {}: {}", file.display(), e));
+                    }
+                }
+                if let Some(command) = &parsed.on_success {
+                    match hook::run_on_success(command, parsed.wait_on_success, request, summary) {
+                        Ok(0) => {},
+                        Ok(code) => {
+                            reporter.error(format!("{}: {:?} exited with {}", request.path(), command, code));
+                            exit_code = 1;
+                        },
+                        Err(e) => {
+                            reporter.error(format!("{}: {}", request.path(), e));
+                            exit_code = 1;
+                        },
+                    }
+                }
+            },
+            Err(e) => {
+                let error_exit_code = report_error(reporter, parsed, request, e);
+                exit_code = exit_code.max(error_exit_code);
+            },
+        }
+    }
+
+    let succeeded = results.iter().filter(|r| r.is_ok()).count();
+    if succeeded > 0 && succeeded < requests.len() {
+        exit_code = EXIT_PARTIAL_SUCCESS;
+    }
+
+    if parsed.output == OutputFormat::Json {
+        let summaries: Vec<&Summary> = results.iter()
+            .filter_map(|r| r.as_ref().ok())
+            .collect();
+        let outcomes: Vec<serde_json::Value> = requests.iter()
+            .enumerate()
+            .map(|(i, request)| match results.get(i) {
+                Some(Ok(summary)) if summary.already_connected => serde_json::json!({
+                    "path": request.path(), "outcome": "already-connected",
+                }),
+                Some(Ok(_)) => serde_json::json!({ "path": request.path(), "outcome": "connected" }),
+                Some(Err(e)) => serde_json::json!({
+                    "path": request.path(), "outcome": "failed", "error": e.to_string(),
+                }),
+                None => serde_json::json!({ "path": request.path(), "outcome": "skipped" }),
+            })
+            .collect();
+        println!("{}", serde_json::json!({ "shares": summaries, "outcomes": outcomes }));
+    }
+
+    if parsed.toast {
+        let lines: Vec<String> = requests.iter()
+            .zip(results.iter())
+            .map(|(request, result)| match result {
+                Ok(_) => format!("Connected to {}", request.path()),
+                Err(e) => format!("{}: {}", request.path(), e),
+            })
+            .collect();
+        toast::show(results.iter().all(|r| r.is_ok()), &lines.join("\n"));
     }
-    eprintln!("launched!");
-    true
+
+    if parsed.ephemeral || parsed.disconnect_after.is_some() {
+        let newly_connected: Vec<&ShareRequest> = requests.iter()
+            .zip(results.iter())
+            .filter(|(_, r)| matches!(r, Ok(summary) if !summary.already_connected))
+            .map(|(request, _)| request)
+            .collect();
+        let session_exit_code = ephemeral::run_session(
+            &newly_connected, parsed.keepalive, parsed.disconnect_after, parsed.ephemeral,
+            parsed.post_disconnect.as_deref(), reporter,
+        );
+        exit_code = exit_code.max(session_exit_code);
+    }
+    exit_code
 }
 
+fn run_print_unc(name: &str, reporter: &mut ErrorReporter) -> i32 {
+    let unc = if args::looks_like_unc_path(name) {
+        Some(name.replace('/', "\\"))
+    } else if args::looks_like_drive(name) {
+        open_share::device_remote_name(name)
+    } else {
+        reporter.error(format!("{:?} is neither a drive letter nor a UNC path", name));
+        return 1;
+    };
+    match unc {
+        Some(unc) => {
+            println!("{}", unc);
+            0
+        },
+        None => {
+            reporter.error(format!("{} is not mapped to a network share", name));
+            1
+        },
+    }
+}
 
-fn inner_main() -> i32 {
-    let args: Vec<String> = env::args().collect();
-    let program_name: &str = match args.get(0) {
-        Some(pn) => pn,
-        None => "open-share",
+/// Describes this session's connections to the server of the given path, which may be
+/// disconnected to free up sessions on the server.
+fn server_connection_hints(request: &ShareRequest) -> Vec<String> {
+    let server = match UncPath::parse(request.path()) {
+        Some(unc) => unc.server.to_owned(),
+        None => return Vec::new(),
     };
-    if args.len() != 3 {
-        eprintln!("Usage: {} PATH USERNAME", program_name);
-        return 1;
+    let ours: Vec<String> = open_share::enumerate_connections(request.enumeration(), LOGGER)
+        .unwrap_or_default()
+        .into_iter()
+        .filter_map(|c| {
+            let remote = c.remote_name?;
+            let same_server = UncPath::parse(&remote)
+                .map(|unc| unc.server.eq_ignore_ascii_case(&server))
+                .unwrap_or(false);
+            if !same_server {
+                return None;
+            }
+            Some(match c.local_name {
+                Some(local) => format!("{} ({})", local, remote),
+                None => remote,
+            })
+        })
+        .collect();
+    let mut hints = vec![
+        "hint: disconnect idle connections to the server, e.g. those of other users or computers, and try again".to_owned(),
+    ];
+    if ours.is_empty() {
+        hints.push("hint: this session has no other connections to the server".to_owned());
+    } else {
+        hints.push(format!("hint: this session's connections to the server: {}", ours.join(", ")));
     }
+    hints
+}
 
-    let path = args.get(1).unwrap();
-    let username = args.get(2).unwrap();
+/// Explains a share that the server does not offer by listing the shares it does offer.
+fn share_not_found_hints(server: &str) -> Vec<String> {
+    let hint = match open_share::list_shares(server) {
+        Ok(shares) => {
+            let names: Vec<&str> = shares.iter()
+                .filter(|share| !share.special)
+                .map(|share| share.name.as_str())
+                .collect();
+            if names.is_empty() {
+                format!("hint: {} offers no shares visible to this user", server)
+            } else {
+                format!("hint: the shares offered by {}: {}", server, names.join(", "))
+            }
+        },
+        Err(e) => format!("hint: cannot list the shares offered by {}: {}", server, e),
+    };
+    vec![hint]
+}
 
-    if !is_connection_already_open(&path) {
-        if !connect_to_share(path, username) {
-            return 1;
+/// Determines the exit code for a failed share and the lines explaining the failure.
+fn diagnose_error(parsed: &Args, request: &ShareRequest, error: &OpenShareError) -> (i32, Vec<String>) {
+    if matches!(error, OpenShareError::ConnectionLimit { .. }) {
+        return (EXIT_CONNECTION_LIMIT, Vec::new());
+    }
+    if matches!(error, OpenShareError::OpenTimeout { .. }) {
+        return (EXIT_OPEN_TIMEOUT, Vec::new());
+    }
+    if matches!(error, OpenShareError::ServerConnectionLimit { .. }) {
+        return (EXIT_SERVER_CONNECTION_LIMIT, server_connection_hints(request));
+    }
+    if matches!(error, OpenShareError::InvalidDrive { .. }) {
+        return (EXIT_INVALID_DRIVE, Vec::new());
+    }
+    if matches!(error, OpenShareError::NotWritable { .. }) {
+        return (EXIT_NOT_WRITABLE, Vec::new());
+    }
+    if let OpenShareError::ShareNotFound { server, .. } = error {
+        return (EXIT_SHARE_NOT_FOUND, share_not_found_hints(server));
+    }
+    if let OpenShareError::Connect { code, .. } = error {
+        if open_share::workstation_appears_stopped(*code) {
+            let lines = vec![
+                "the Workstation service appears stopped".to_owned(),
+                "hint: wait until Windows has finished starting, or start the service using `sc start LanmanWorkstation` as an administrator".to_owned(),
+            ];
+            return (EXIT_WORKSTATION_STOPPED, lines);
         }
     }
 
-    eprintln!("launching...");
-    let result = open_path(path);
+    let mut lines = Vec::new();
+    if matches!(error, OpenShareError::DriveInUse { .. }) {
+        lines.push("hint: pass --force to replace the existing mapping".to_owned());
+    }
+    let is_guest_rejection = matches!(
+        error,
+        OpenShareError::Connect { code, .. }
+            if [ERROR_ACCESS_DENIED.0, ERROR_LOGON_FAILURE.0, ERROR_LOGON_TYPE_NOT_GRANTED.0].contains(code)
+    );
+    if parsed.guest && is_guest_rejection {
+        lines.push("hint: the server or the local policy rejected guest access; guest logons are disabled by default on current versions of Windows".to_owned());
+    }
+    let is_logon_failure = matches!(error, OpenShareError::Connect { code, .. } if *code == ERROR_LOGON_FAILURE.0);
+    if parsed.diagnose && is_logon_failure {
+        for hint in open_share::logon_failure_hints(request.path()) {
+            lines.push(format!("hint: {}", hint));
+        }
+    }
+    (1, lines)
+}
 
-    if result { 0 } else { 1 }
+/// Reports the failure of a share, as text or (with `--json-errors`) as a single JSON object.
+/// Returns the exit code.
+fn report_error(reporter: &mut ErrorReporter, parsed: &Args, request: &ShareRequest, error: &OpenShareError) -> i32 {
+    let (exit_code, lines) = diagnose_error(parsed, request, error);
+    if parsed.json_errors {
+        let code = error.win32_code();
+        let object = serde_json::json!({
+            "phase": error.phase(),
+            "win32_code": code,
+            "symbolic_name": open_share::win32_error_name(code),
+            "message": error.to_string(),
+            "path": request.path(),
+            "exit_code": exit_code,
+            "hints": lines,
+        });
+        reporter.error(object.to_string());
+    } else {
+        reporter.error(format!("{}: {}", request.path(), error));
+        for line in lines {
+            reporter.error(line);
+        }
+    }
+    exit_code
 }
 
 fn main() {
-    let exit_code = inner_main();
+    let args: Vec<String> = env::args().collect();
+    let gui_errors = !has_console()
+        || args.iter().skip(1).any(|a| a == "--gui-errors");
+    let json_output = args.iter().skip(1).any(|a| a == "--json-errors")
+        || args.windows(2).any(|pair| pair[0] == "--output" && pair[1] == "json");
+    let mut reporter = ErrorReporter::new(gui_errors);
+    color::init(args.iter().skip(1).any(|a| a == "--no-color"));
+    if args.iter().skip(1).any(|a| a == "--quiet-success") {
+        report::hold_back_progress();
+    }
+
+    let exit_code = inner_main(&args, &mut reporter);
 
     if exit_code != 0 {
+        report::release_progress();
+    }
+    reporter.show();
+    // nobody reads the prompt of a program whose output is parsed
+    if exit_code != 0 && !reporter.is_gui() && !json_output {
         eprintln!("exiting with {}", exit_code);
         eprintln!("press Enter to exit (oddly enough)");
 
@@ -220,3 +804,17 @@ fn main() {
 
     process::exit(exit_code);
 }
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rejected_arguments_exit_with_usage_code() {
+        let args: Vec<String> = ["open-share", "--drive", "X", "\\\\server\\share", "user"].iter()
+            .map(|a| (*a).to_owned())
+            .collect();
+        assert_eq!(inner_main(&args, &mut ErrorReporter::new(false)), EXIT_USAGE);
+    }
+}
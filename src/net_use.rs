@@ -0,0 +1,257 @@
+//! Accepting the argument syntax of `net use`, so that open-share can replace it in existing
+//! scripts: `open-share X: \\server\share /user:DOMAIN\me password /persistent:yes`.
+//!
+//! This syntax is recognized if the first argument is a drive (`X:`) or `*`, or if any argument is
+//! one of the switches below. The arguments are translated into the native ones and then parsed as
+//! usual; native options cannot be mixed in. The supported subset:
+//!
+//! * `X:` maps the share to that drive; `*` picks a free drive (`--drive auto`).
+//! * `\\server\share` is the share to connect to.
+//! * A password after the share is passed as `--password`; `*` prompts for it.
+//! * `/USER:[DOMAIN\]NAME` (or `NAME@DOMAIN`) connects as that user. Without it, the share is
+//!   connected using the current logon session, or (if a password is given) as the current user
+//!   with that password.
+//! * `/PERSISTENT:YES` remembers the mapping (`--persistent`); `/PERSISTENT:NO` is the default.
+//! * `/SMARTCARD` authenticates using the smartcard (`--smartcard`).
+//! * `/DELETE` disconnects the drive or share instead (`disconnect`); `/Y` forces this even if
+//!   files are open (`disconnect --force`) and is ignored otherwise, as is `/N`.
+//!
+//! Like in `net use`, the switches are case-insensitive and may be abbreviated as long as they stay
+//! unique (`/u:`, `/p:yes`). Where this diverges from `net use`:
+//!
+//! * The share (or drive) is opened after connecting, as with the native syntax.
+//! * `/PERSISTENT` only applies to this mapping; it does not change the default for later ones.
+//! * `*` as the password shows the credential dialog instead of asking on the console.
+//! * The native options (such as `--no-prompt`) are not available.
+//! * Listing the connections (`net use` without arguments), `/DELETE` with `*`, `/SAVECRED`,
+//!   `/HOME` and the switches of newer versions (such as `/REQUIREPRIVACY` or `/TRANSPORT`) are not
+//!   supported and are rejected.
+
+use std::env;
+
+use crate::args::{looks_like_drive, looks_like_unc_path};
+
+
+/// The switches supported in `net use` syntax.
+const SWITCHES: &[&str] = &["USER", "PERSISTENT", "SMARTCARD", "DELETE", "YES", "NO"];
+
+/// The switches of `net use` that are known but not supported.
+const UNSUPPORTED_SWITCHES: &[&str] = &[
+    "SAVECRED", "HOME", "REQUIREINTEGRITY", "REQUIREPRIVACY", "WRITETHROUGH", "TRANSPORT",
+    "QUICUNSAFESIGNATURES", "SKIPCERTCHECK", "GLOBAL",
+];
+
+
+/// Splits a switch such as `/user:DOMAIN\me` into its name (`user`) and value (`DOMAIN\me`).
+///
+/// Returns `None` for arguments that are not switches, including UNC paths written using slashes.
+fn split_switch(arg: &str) -> Option<(&str, Option<&str>)> {
+    let rest = arg.strip_prefix('/')?;
+    let (name, value) = match rest.split_once(':') {
+        Some((name, value)) => (name, Some(value)),
+        None => (rest, None),
+    };
+    if name.is_empty() || !name.chars().all(|c| c.is_ascii_alphabetic()) {
+        return None;
+    }
+    Some((name, value))
+}
+
+/// Whether the name abbreviates any switch of `net use`, supported or not.
+fn is_switch(name: &str) -> bool {
+    let upper = name.to_ascii_uppercase();
+    SWITCHES.iter()
+        .chain(UNSUPPORTED_SWITCHES.iter())
+        .any(|switch| switch.starts_with(&upper))
+}
+
+/// Finds the switch (or unsupported switch) that the given name abbreviates, if it is unique.
+fn resolve_switch(name: &str) -> Result<&'static str, String> {
+    let upper = name.to_ascii_uppercase();
+    let matches: Vec<&str> = SWITCHES.iter()
+        .chain(UNSUPPORTED_SWITCHES.iter())
+        .copied()
+        .filter(|switch| switch.starts_with(&upper))
+        .collect();
+    match matches[..] {
+        [switch] => if UNSUPPORTED_SWITCHES.contains(&switch) {
+            Err(format!("/{} is not supported in net use syntax", switch))
+        } else {
+            Ok(switch)
+        },
+        [] => Err(format!("unknown switch /{}", name)),
+        _ => Err(format!("ambiguous switch /{} (could be /{})", name, matches.join(", /"))),
+    }
+}
+
+/// Whether the arguments use the syntax of `net use` rather than the native one.
+pub(crate) fn looks_like_net_use(args: &[String]) -> bool {
+    let first_is_device = args.first()
+        .is_some_and(|first| first == "*" || looks_like_drive(first));
+    first_is_device || args.iter()
+        .filter_map(|arg| split_switch(arg))
+        .any(|(name, _)| is_switch(name))
+}
+
+/// The current user as `DOMAIN\NAME`, which `net use` connects as if it is given a password but no
+/// `/USER`.
+fn current_user() -> Result<String, String> {
+    let name = env::var("USERNAME")
+        .map_err(|_| "a password without /USER requires USERNAME to be set".to_owned())?;
+    Ok(match env::var("USERDOMAIN") {
+        Ok(domain) => format!("{}\\{}", domain, name),
+        Err(_) => name,
+    })
+}
+
+/// Translates arguments in `net use` syntax into the equivalent native arguments.
+pub(crate) fn translate(args: &[String]) -> Result<Vec<String>, String> {
+    let mut device = None;
+    let mut path = None;
+    let mut password = None;
+    let mut user = None;
+    let mut persistent = false;
+    let mut smartcard = false;
+    let mut delete = false;
+    let mut yes = false;
+
+    for arg in args {
+        if arg.starts_with("--") {
+            return Err(format!("{} cannot be combined with net use syntax", arg));
+        }
+        if let Some((name, value)) = split_switch(arg) {
+            let switch = resolve_switch(name)?;
+            match (switch, value) {
+                ("USER", Some(value)) if !value.is_empty() => user = Some(value.to_owned()),
+                ("USER", _) => return Err("/USER expects a user name (/USER:DOMAIN\\NAME)".to_owned()),
+                ("PERSISTENT", Some(value)) => match value.to_ascii_uppercase().as_str() {
+                    "YES" => persistent = true,
+                    "NO" => persistent = false,
+                    _ => return Err(format!("/PERSISTENT expects YES or NO, not {:?}", value)),
+                },
+                ("PERSISTENT", None) => return Err("/PERSISTENT expects YES or NO".to_owned()),
+                (_, Some(_)) => return Err(format!("/{} does not take a value", switch)),
+                ("SMARTCARD", None) => smartcard = true,
+                ("DELETE", None) => delete = true,
+                ("YES", None) => yes = true,
+                _ => {}, // /NO
+            }
+            continue;
+        }
+
+        if device.is_none() && path.is_none() && (arg == "*" || looks_like_drive(arg)) {
+            device = Some(arg.clone());
+        } else if path.is_none() && looks_like_unc_path(arg) {
+            path = Some(arg.clone());
+        } else if path.is_some() && password.is_none() {
+            password = Some(arg.clone());
+        } else {
+            return Err(format!("unexpected argument {:?} in net use syntax", arg));
+        }
+    }
+
+    if delete {
+        let target = match (&device, &path) {
+            (Some(device), _) if device == "*" => return Err("/DELETE with * is not supported".to_owned()),
+            (Some(device), _) => device.clone(),
+            (None, Some(path)) => path.clone(),
+            (None, None) => return Err("/DELETE requires a drive or a share".to_owned()),
+        };
+        let mut translated = vec!["disconnect".to_owned()];
+        if yes {
+            translated.push("--force".to_owned());
+        }
+        translated.push(target);
+        return Ok(translated);
+    }
+
+    let path = path.ok_or_else(|| "net use syntax requires a share (\\\\server\\share)".to_owned())?;
+    let mut translated = Vec::new();
+    match device.as_deref() {
+        Some("*") => translated.extend(["--drive".to_owned(), "auto".to_owned()]),
+        Some(drive) => translated.extend(["--drive".to_owned(), drive.to_owned()]),
+        None => {},
+    }
+    if persistent {
+        translated.push("--persistent".to_owned());
+    }
+    if password.is_some() && user.is_none() && !smartcard {
+        user = Some(current_user()?);
+    }
+    if let Some(password) = password.filter(|p| p != "*") {
+        translated.extend(["--password".to_owned(), password]);
+    }
+    if smartcard {
+        translated.push("--smartcard".to_owned());
+    } else if user.is_none() {
+        translated.push("--username-from-current".to_owned());
+    }
+    translated.push("--".to_owned());
+    translated.push(path);
+    translated.extend(user);
+    Ok(translated)
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// `net use` argument lines (split at spaces) and their native translations.
+    const TRANSLATIONS: &[(&str, &str)] = &[
+        (r"X: \\server\share", r"--drive X: --username-from-current -- \\server\share"),
+        (
+            r"x: \\server\share secret /user:DOMAIN\me /persistent:yes",
+            r"--drive x: --persistent --password secret -- \\server\share DOMAIN\me",
+        ),
+        (r"* \\server\share /SMARTCARD", r"--drive auto --smartcard -- \\server\share"),
+        (r"\\server\share * /u:me@domain", r"-- \\server\share me@domain"),
+        (r"X: \\server\share /p:no /n", r"--drive X: --username-from-current -- \\server\share"),
+        (r"X: /DELETE /Y", r"disconnect --force X:"),
+        (r"\\server\share /d", r"disconnect \\server\share"),
+    ];
+
+    /// `net use` argument lines (split at spaces) that are rejected, and the error message.
+    const ERRORS: &[(&str, &str)] = &[
+        (r"X: \\server\share /S", "ambiguous switch /S (could be /SMARTCARD, /SAVECRED, /SKIPCERTCHECK)"),
+        (r"X: \\server\share /SAVECRED", "/SAVECRED is not supported in net use syntax"),
+        (r"X: \\server\share /sav", "/SAVECRED is not supported in net use syntax"),
+        (r"X: \\server\share /frob", "unknown switch /frob"),
+        (r"X: \\server\share --no-prompt", "--no-prompt cannot be combined with net use syntax"),
+        (r"X: \\server\share /user:", r"/USER expects a user name (/USER:DOMAIN\NAME)"),
+        (r"X: \\server\share /persistent", "/PERSISTENT expects YES or NO"),
+        (r"X: \\server\share /persistent:maybe", r#"/PERSISTENT expects YES or NO, not "maybe""#),
+        (r"X: \\server\share /smartcard:yes", "/SMARTCARD does not take a value"),
+        (r"X: \\server\share secret extra", r#"unexpected argument "extra" in net use syntax"#),
+        (r"X: /smartcard", r"net use syntax requires a share (\\server\share)"),
+        (r"* /DELETE", "/DELETE with * is not supported"),
+        (r"/DELETE", "/DELETE requires a drive or a share"),
+    ];
+
+    fn split(line: &str) -> Vec<String> {
+        line.split(' ').map(|a| a.to_owned()).collect()
+    }
+
+    #[test]
+    fn translations() {
+        for (line, expected) in TRANSLATIONS {
+            let args = split(line);
+            assert!(looks_like_net_use(&args), "{:?}", line);
+            assert_eq!(translate(&args), Ok(split(expected)), "{:?}", line);
+        }
+    }
+
+    #[test]
+    fn errors() {
+        for (line, expected) in ERRORS {
+            assert_eq!(translate(&split(line)), Err((*expected).to_owned()), "{:?}", line);
+        }
+    }
+
+    #[test]
+    fn native_arguments_are_not_net_use() {
+        let args = split(r"--drive X: \\server\share user");
+        assert!(!looks_like_net_use(&args));
+        assert!(!looks_like_net_use(&split(r"//server/share user")));
+    }
+}
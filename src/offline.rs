@@ -0,0 +1,100 @@
+//! Detecting shares that are only available from the Offline Files cache.
+//!
+//! With Offline Files (client-side caching), Windows keeps serving a share from the local cache
+//! when its server cannot be reached. The share then still appears connected and its cached
+//! contents remain readable, so neither the list of connections nor accessing the share reveals
+//! that the server is gone. The Offline Files cache knows, though: it tracks whether it passes
+//! requests for a share on to the server (online) or answers them itself (offline).
+
+
+use std::fmt;
+
+use windows::core::{Interface, PCWSTR};
+use windows::Win32::Foundation::{BOOL, GetLastError, NO_ERROR};
+use windows::Win32::Storage::FileSystem::{GetFileAttributesW, INVALID_FILE_ATTRIBUTES};
+use windows::Win32::Storage::OfflineFiles::{
+    IOfflineFilesCache, IOfflineFilesConnectionInfo, OFFLINEFILES_CONNECT_STATE,
+    OFFLINEFILES_CONNECT_STATE_OFFLINE, OFFLINEFILES_OFFLINE_REASON, OfflineFilesCache,
+    OfflineFilesQueryStatus,
+};
+use windows::Win32::System::Com::{
+    CLSCTX_INPROC_SERVER, CoCreateInstance, CoInitializeEx, COINIT_MULTITHREADED, CoUninitialize,
+};
+
+use crate::unc::normalized_share_root;
+use crate::wstr::str_to_wcstring;
+
+
+/// Whether a share can actually be used, as determined by [`share_availability`].
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub enum Availability {
+    /// The share is accessible and served by its server.
+    Online,
+
+    /// The share is accessible, but only its cached contents are served because the server cannot
+    /// be reached.
+    OfflineOnly,
+
+    /// The share is not accessible at all.
+    Unavailable { code: u32 },
+}
+impl fmt::Display for Availability {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Online => write!(f, "online"),
+            Self::OfflineOnly => write!(f, "available offline only"),
+            Self::Unavailable { code } => write!(f, "unavailable! {}", std::io::Error::from_raw_os_error(*code as i32)),
+        }
+    }
+}
+
+
+/// Whether Offline Files is active on this computer.
+fn offline_files_active() -> bool {
+    let mut active = BOOL(0);
+    let result = unsafe { OfflineFilesQueryStatus(Some(&mut active), None) };
+    result == NO_ERROR.0 && active.as_bool()
+}
+
+/// Asks the Offline Files cache whether it serves the given share offline. Returns `None` if the
+/// cache cannot tell, e.g. because it holds nothing of the share.
+fn cache_serves_offline(share_root: &str) -> Option<bool> {
+    let initialized = unsafe { CoInitializeEx(None, COINIT_MULTITHREADED) }.is_ok();
+    let offline = (|| {
+        let cache: IOfflineFilesCache = unsafe {
+            CoCreateInstance(&OfflineFilesCache, None, CLSCTX_INPROC_SERVER)
+        }.ok()?;
+        let share_root_windows = str_to_wcstring(share_root);
+        let item = unsafe { cache.FindItem(PCWSTR(share_root_windows.as_ptr()), 0) }.ok()?;
+        let connection: IOfflineFilesConnectionInfo = item.cast().ok()?;
+        let mut state = OFFLINEFILES_CONNECT_STATE::default();
+        let mut reason = OFFLINEFILES_OFFLINE_REASON::default();
+        unsafe { connection.GetConnectState(&mut state, &mut reason) }.ok()?;
+        Some(state == OFFLINEFILES_CONNECT_STATE_OFFLINE)
+    })();
+    if initialized {
+        unsafe { CoUninitialize() };
+    }
+    offline
+}
+
+/// Checks whether the share of the given path can actually be used, telling a share that is only
+/// served from the Offline Files cache apart from one that is online.
+///
+/// Accessing the share may connect to it using the credentials of the current logon session.
+/// Consulting the Offline Files cache loads its COM server, so this is slower than merely checking
+/// whether the share is connected.
+pub fn share_availability(path: &str) -> Availability {
+    let share_root = normalized_share_root(path);
+    let share_root_windows = str_to_wcstring(&share_root);
+    let attributes = unsafe { GetFileAttributesW(PCWSTR(share_root_windows.as_ptr())) };
+    if attributes == INVALID_FILE_ATTRIBUTES {
+        return Availability::Unavailable { code: unsafe { GetLastError() }.0 };
+    }
+
+    if offline_files_active() && cache_serves_offline(&share_root) == Some(true) {
+        Availability::OfflineOnly
+    } else {
+        Availability::Online
+    }
+}
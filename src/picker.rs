@@ -0,0 +1,106 @@
+//! A minimal console menu (`browse --pick`).
+//!
+//! The menu is written to stdout and redrawn in place by moving the console cursor back to its
+//! first line, so it needs neither virtual terminal processing nor a TUI library. The keys are read
+//! as raw console input events: Up and Down (as well as Home and End) move the selection, Enter
+//! picks the selected entry and Escape cancels.
+
+
+use std::io::{self, Write};
+
+use windows::Win32::Foundation::HANDLE;
+use windows::Win32::System::Console::{
+    CONSOLE_MODE, CONSOLE_SCREEN_BUFFER_INFO, COORD, ENABLE_ECHO_INPUT, ENABLE_LINE_INPUT,
+    GetConsoleMode, GetConsoleScreenBufferInfo, GetStdHandle, INPUT_RECORD, KEY_EVENT,
+    ReadConsoleInputW, SetConsoleCursorPosition, SetConsoleMode, STD_INPUT_HANDLE,
+    STD_OUTPUT_HANDLE,
+};
+use windows::Win32::UI::Input::KeyboardAndMouse::{
+    VIRTUAL_KEY, VK_DOWN, VK_END, VK_ESCAPE, VK_HOME, VK_RETURN, VK_UP,
+};
+
+
+fn screen_buffer_info(output: HANDLE) -> Option<CONSOLE_SCREEN_BUFFER_INFO> {
+    let mut info = CONSOLE_SCREEN_BUFFER_INFO::default();
+    unsafe { GetConsoleScreenBufferInfo(output, &mut info) }.ok()?;
+    Some(info)
+}
+
+/// Writes the entries, marking the selected one. Entries are cut off at the width of the console
+/// so that none of them wraps, which would shift the lines when redrawing.
+fn draw(entries: &[String], selected: usize, width: usize) {
+    let mut stdout = io::stdout().lock();
+    for (i, entry) in entries.iter().enumerate() {
+        let marker = if i == selected { '>' } else { ' ' };
+        let text: String = entry.chars().take(width.saturating_sub(3)).collect();
+        let _ = writeln!(stdout, "{} {}", marker, text);
+    }
+    let _ = stdout.flush();
+}
+
+/// Reads key presses until one of the keys handled by the menu is pressed.
+fn read_key(input: HANDLE) -> Option<VIRTUAL_KEY> {
+    loop {
+        let mut records = [INPUT_RECORD::default()];
+        let mut read = 0u32;
+        unsafe { ReadConsoleInputW(input, &mut records, &mut read) }.ok()?;
+        if read == 0 || u32::from(records[0].EventType) != KEY_EVENT {
+            continue;
+        }
+        let key = unsafe { records[0].Event.KeyEvent };
+        if !key.bKeyDown.as_bool() {
+            continue;
+        }
+        let key = VIRTUAL_KEY(key.wVirtualKeyCode);
+        if [VK_UP, VK_DOWN, VK_HOME, VK_END, VK_RETURN, VK_ESCAPE].contains(&key) {
+            return Some(key);
+        }
+    }
+}
+
+/// Shows the entries as a menu and returns the index of the one picked, or `None` if the menu was
+/// cancelled.
+///
+/// Fails if stdin or stdout is not a console.
+pub(crate) fn pick(entries: &[String]) -> Result<Option<usize>, String> {
+    if entries.is_empty() {
+        return Ok(None);
+    }
+    let not_a_console = |_| "the menu requires an interactive console".to_owned();
+    let input = unsafe { GetStdHandle(STD_INPUT_HANDLE) }.map_err(not_a_console)?;
+    let output = unsafe { GetStdHandle(STD_OUTPUT_HANDLE) }.map_err(not_a_console)?;
+    let mut mode = CONSOLE_MODE::default();
+    unsafe { GetConsoleMode(input, &mut mode) }.map_err(not_a_console)?;
+    let width = screen_buffer_info(output)
+        .map(|info| usize::try_from(info.dwSize.X).unwrap_or(0))
+        .ok_or_else(|| "the menu requires an interactive console".to_owned())?;
+
+    // draw once so that the console scrolls as needed, then find the first line of the menu
+    let mut selected = 0;
+    draw(entries, selected, width);
+    let top = screen_buffer_info(output)
+        .map(|info| info.dwCursorPosition.Y)
+        .unwrap_or(0)
+        .saturating_sub(i16::try_from(entries.len()).unwrap_or(i16::MAX))
+        .max(0);
+
+    let _ = unsafe { SetConsoleMode(input, mode & !(ENABLE_LINE_INPUT | ENABLE_ECHO_INPUT)) };
+    let picked = loop {
+        let key = match read_key(input) {
+            Some(k) => k,
+            None => break None,
+        };
+        selected = match key {
+            VK_UP => selected.saturating_sub(1),
+            VK_DOWN => (selected + 1).min(entries.len() - 1),
+            VK_HOME => 0,
+            VK_END => entries.len() - 1,
+            VK_RETURN => break Some(selected),
+            _ => break None,
+        };
+        let _ = unsafe { SetConsoleCursorPosition(output, COORD { X: 0, Y: top }) };
+        draw(entries, selected, width);
+    };
+    let _ = unsafe { SetConsoleMode(input, mode) };
+    Ok(picked)
+}
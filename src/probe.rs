@@ -0,0 +1,187 @@
+use std::fmt;
+use std::io;
+use std::net::{SocketAddr, TcpStream, ToSocketAddrs};
+use std::time::Duration;
+
+
+/// The TCP port on which SMB servers listen.
+pub const SMB_PORT: u16 = 445;
+
+
+/// Which address family to probe.
+///
+/// This only affects the reachability check performed by this crate; Windows chooses the address
+/// used for the actual SMB connection on its own.
+#[derive(Clone, Copy, Debug, Default, Eq, Hash, PartialEq)]
+pub enum AddressFamily {
+    /// Probe all resolved addresses in the order returned by the resolver.
+    #[default]
+    Any,
+
+    /// Only probe IPv4 addresses.
+    Ipv4,
+
+    /// Only probe IPv6 addresses.
+    Ipv6,
+}
+impl AddressFamily {
+    fn matches(&self, address: &SocketAddr) -> bool {
+        match self {
+            Self::Any => true,
+            Self::Ipv4 => address.is_ipv4(),
+            Self::Ipv6 => address.is_ipv6(),
+        }
+    }
+}
+
+
+/// Resolves host names to socket addresses.
+pub trait Resolver {
+    fn resolve(&self, host: &str, port: u16) -> io::Result<Vec<SocketAddr>>;
+}
+
+/// Resolves host names using the operating system's resolver (`getaddrinfo`).
+#[derive(Clone, Copy, Debug, Default)]
+pub struct SystemResolver;
+impl Resolver for SystemResolver {
+    fn resolve(&self, host: &str, port: u16) -> io::Result<Vec<SocketAddr>> {
+        Ok((host, port).to_socket_addrs()?.collect())
+    }
+}
+
+
+/// The reason why a host could not be reached.
+#[derive(Debug)]
+pub enum ProbeError {
+    /// The host name could not be resolved.
+    Resolve(io::Error),
+
+    /// The host name resolved to no addresses of the requested family.
+    NoAddresses,
+
+    /// None of the host's addresses accepted a connection; contains the error for the last one.
+    Connect(io::Error),
+}
+impl fmt::Display for ProbeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Resolve(e) => write!(f, "failed to resolve host: {}", e),
+            Self::NoAddresses => write!(f, "host name resolved to no addresses of the requested family"),
+            Self::Connect(e) => write!(f, "failed to connect: {}", e),
+        }
+    }
+}
+impl std::error::Error for ProbeError {
+}
+
+
+/// Resolves the host and returns the addresses of the given family to be probed, in order.
+pub fn addresses_to_probe<R: Resolver + ?Sized>(
+    resolver: &R,
+    host: &str,
+    port: u16,
+    family: AddressFamily,
+) -> Result<Vec<SocketAddr>, ProbeError> {
+    let addresses: Vec<SocketAddr> = resolver.resolve(host, port)
+        .map_err(ProbeError::Resolve)?
+        .into_iter()
+        .filter(|a| family.matches(a))
+        .collect();
+    if addresses.is_empty() {
+        Err(ProbeError::NoAddresses)
+    } else {
+        Ok(addresses)
+    }
+}
+
+/// Checks whether a TCP connection to the given port of the given host can be established.
+///
+/// Each resolved address of the given family is tried in turn; the first one that accepts the
+/// connection is returned.
+pub fn probe_host(host: &str, port: u16, timeout: Duration, family: AddressFamily) -> Result<SocketAddr, ProbeError> {
+    probe_host_with(&SystemResolver, host, port, timeout, family)
+}
+
+/// Like [`probe_host`], but resolves the host using the given resolver.
+pub fn probe_host_with<R: Resolver + ?Sized>(
+    resolver: &R,
+    host: &str,
+    port: u16,
+    timeout: Duration,
+    family: AddressFamily,
+) -> Result<SocketAddr, ProbeError> {
+    let addresses = addresses_to_probe(resolver, host, port, family)?;
+
+    let mut last_error = None;
+    for address in addresses {
+        match TcpStream::connect_timeout(&address, timeout) {
+            Ok(_) => return Ok(address),
+            Err(e) => last_error = Some(e),
+        }
+    }
+    Err(ProbeError::Connect(last_error.unwrap()))
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::net::{Ipv4Addr, Ipv6Addr};
+
+    /// Returns a fixed list of addresses, or fails if there is none.
+    struct StubResolver(Vec<SocketAddr>);
+    impl Resolver for StubResolver {
+        fn resolve(&self, _host: &str, port: u16) -> io::Result<Vec<SocketAddr>> {
+            match self.0.as_slice() {
+                [] => Err(io::Error::new(io::ErrorKind::NotFound, "no such host")),
+                addresses => Ok(addresses.iter().map(|a| SocketAddr::new(a.ip(), port)).collect()),
+            }
+        }
+    }
+
+    fn v4(last: u8) -> SocketAddr {
+        SocketAddr::new(Ipv4Addr::new(192, 0, 2, last).into(), 0)
+    }
+
+    fn v6(last: u16) -> SocketAddr {
+        SocketAddr::new(Ipv6Addr::new(0x2001, 0xdb8, 0, 0, 0, 0, 0, last).into(), 0)
+    }
+
+    fn mixed() -> StubResolver {
+        StubResolver(vec![v6(1), v4(1), v6(2), v4(2)])
+    }
+
+    fn with_port(addresses: &[SocketAddr]) -> Vec<SocketAddr> {
+        addresses.iter().map(|a| SocketAddr::new(a.ip(), SMB_PORT)).collect()
+    }
+
+    #[test]
+    fn any_keeps_all_addresses_in_order() {
+        let addresses = addresses_to_probe(&mixed(), "server", SMB_PORT, AddressFamily::Any).unwrap();
+        assert_eq!(addresses, with_port(&[v6(1), v4(1), v6(2), v4(2)]));
+    }
+
+    #[test]
+    fn families_filter_addresses() {
+        let ipv4 = addresses_to_probe(&mixed(), "server", SMB_PORT, AddressFamily::Ipv4).unwrap();
+        assert_eq!(ipv4, with_port(&[v4(1), v4(2)]));
+        let ipv6 = addresses_to_probe(&mixed(), "server", SMB_PORT, AddressFamily::Ipv6).unwrap();
+        assert_eq!(ipv6, with_port(&[v6(1), v6(2)]));
+    }
+
+    #[test]
+    fn no_matching_address_is_reported() {
+        let only_v4 = StubResolver(vec![v4(1)]);
+        let result = addresses_to_probe(&only_v4, "server", SMB_PORT, AddressFamily::Ipv6);
+        assert!(matches!(result, Err(ProbeError::NoAddresses)), "{:?}", result);
+    }
+
+    #[test]
+    fn resolver_errors_pass_through() {
+        let result = addresses_to_probe(&StubResolver(Vec::new()), "server", SMB_PORT, AddressFamily::Any);
+        match result {
+            Err(ProbeError::Resolve(e)) => assert_eq!(e.kind(), io::ErrorKind::NotFound),
+            other => panic!("expected a resolver error, got {:?}", other),
+        }
+    }
+}
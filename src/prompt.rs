@@ -0,0 +1,151 @@
+//! The modern (Windows Vista and later) credential dialog, shown using
+//! `CredUIPromptForWindowsCredentialsW`.
+//!
+//! Unlike the dialog shown by `WNetAddConnection2W` itself when `CONNECT_PROMPT` is passed, its
+//! caption and message can be chosen freely.
+
+
+use std::ffi::c_void;
+use std::mem::size_of;
+use std::ptr::null_mut;
+
+use windows::core::{PCWSTR, PWSTR};
+use windows::Win32::Foundation::{ERROR_CANCELLED, NO_ERROR};
+use windows::Win32::Security::Credentials::{
+    CREDUI_INFOW, CREDUIWIN_GENERIC, CRED_PACK_FLAGS, CRED_PACK_GENERIC_CREDENTIALS,
+    CredPackAuthenticationBufferW, CredUIPromptForWindowsCredentialsW,
+    CredUnPackAuthenticationBufferW,
+};
+use windows::Win32::System::Com::CoTaskMemFree;
+
+use crate::error::{OpenShareError, win32_code};
+use crate::unc::UncPath;
+use crate::wstr::{str_to_wcstring, wcstr_to_string};
+
+
+/// The maximum length of a username including the domain, plus the terminating NUL.
+const MAX_USERNAME_LENGTH: usize = 513;
+
+/// The maximum length of a password, plus the terminating NUL.
+const MAX_PASSWORD_LENGTH: usize = 257;
+
+
+/// Credentials entered into the credential dialog.
+pub(crate) struct PromptedCredentials {
+    pub username: String,
+    pub password: String,
+}
+
+
+/// The caption of the credential dialog for the given path unless specified otherwise.
+pub(crate) fn default_prompt_title(path: &str) -> String {
+    match UncPath::parse(path) {
+        Some(unc) => format!("Connect to {}", unc.share),
+        None => "Connect to share".to_owned(),
+    }
+}
+
+/// The message of the credential dialog for the given path unless specified otherwise.
+pub(crate) fn default_prompt_message(path: &str) -> String {
+    format!("Enter your credentials for {}", path)
+}
+
+fn pack_username(username: &str) -> Option<Vec<u8>> {
+    let username_windows = str_to_wcstring(username);
+    let empty = str_to_wcstring("");
+    let mut size = 0u32;
+    // the first call only obtains the size and fails with ERROR_INSUFFICIENT_BUFFER
+    let _ = unsafe {
+        CredPackAuthenticationBufferW(
+            CRED_PACK_GENERIC_CREDENTIALS,
+            PCWSTR(username_windows.as_ptr()),
+            PCWSTR(empty.as_ptr()),
+            None,
+            &mut size,
+        )
+    };
+    let mut buffer = vec![0u8; size.try_into().unwrap()];
+    unsafe {
+        CredPackAuthenticationBufferW(
+            CRED_PACK_GENERIC_CREDENTIALS,
+            PCWSTR(username_windows.as_ptr()),
+            PCWSTR(empty.as_ptr()),
+            Some(buffer.as_mut_ptr()),
+            &mut size,
+        )
+    }.ok()?;
+    Some(buffer)
+}
+
+/// Shows the credential dialog, pre-filling the username if one is given.
+///
+/// Returns `None` if the user cancelled the dialog.
+pub(crate) fn prompt_credentials(title: &str, message: &str, username: Option<&str>) -> Result<Option<PromptedCredentials>, OpenShareError> {
+    let title_windows = str_to_wcstring(title);
+    let message_windows = str_to_wcstring(message);
+    let ui_info = CREDUI_INFOW {
+        cbSize: size_of::<CREDUI_INFOW>().try_into().unwrap(),
+        pszMessageText: PCWSTR(message_windows.as_ptr()),
+        pszCaptionText: PCWSTR(title_windows.as_ptr()),
+        ..Default::default()
+    };
+    let in_buffer = username.and_then(pack_username);
+    let (in_ptr, in_size) = match &in_buffer {
+        Some(b) => (Some(b.as_ptr() as *const c_void), b.len().try_into().unwrap()),
+        None => (None, 0),
+    };
+
+    let mut auth_package = 0u32;
+    let mut out_buffer: *mut c_void = null_mut();
+    let mut out_size = 0u32;
+    let result = unsafe {
+        CredUIPromptForWindowsCredentialsW(
+            Some(&ui_info),
+            0,
+            &mut auth_package,
+            in_ptr,
+            in_size,
+            &mut out_buffer,
+            &mut out_size,
+            None,
+            CREDUIWIN_GENERIC,
+        )
+    };
+    if result == ERROR_CANCELLED.0 {
+        return Ok(None);
+    } else if result != NO_ERROR.0 {
+        return Err(OpenShareError::Connect { code: result, context: None });
+    }
+
+    let mut username_buffer = vec![0u16; MAX_USERNAME_LENGTH];
+    let mut username_length: u32 = username_buffer.len().try_into().unwrap();
+    let mut password_buffer = vec![0u16; MAX_PASSWORD_LENGTH];
+    let mut password_length: u32 = password_buffer.len().try_into().unwrap();
+    let unpacked = unsafe {
+        CredUnPackAuthenticationBufferW(
+            CRED_PACK_FLAGS(0),
+            out_buffer,
+            out_size,
+            PWSTR(username_buffer.as_mut_ptr()),
+            &mut username_length,
+            PWSTR(null_mut()),
+            None,
+            PWSTR(password_buffer.as_mut_ptr()),
+            &mut password_length,
+        )
+    };
+
+    // don't leave the password lying around in memory
+    unsafe {
+        (out_buffer as *mut u8).write_bytes(0, out_size.try_into().unwrap());
+        CoTaskMemFree(Some(out_buffer));
+    }
+    unpacked.map_err(|e| OpenShareError::Connect { code: win32_code(&e), context: None })?;
+
+    let credentials = PromptedCredentials {
+        username: wcstr_to_string(username_buffer.as_ptr()),
+        password: wcstr_to_string(password_buffer.as_ptr()),
+    };
+    password_buffer.fill(0);
+    Ok(Some(credentials))
+}
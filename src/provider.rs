@@ -0,0 +1,89 @@
+use serde::Serialize;
+use windows::core::PWSTR;
+use windows::Win32::Foundation::NO_ERROR;
+use windows::Win32::NetworkManagement::WNet::WNetGetProviderNameW;
+use windows::Win32::System::Registry::HKEY_LOCAL_MACHINE;
+
+use crate::registry::read_string;
+use crate::wstr::wcstr_to_string;
+
+
+/// The network type of the Microsoft Windows Network (SMB) provider.
+pub(crate) const WNNC_NET_LANMAN: u32 = 0x0002_0000;
+
+/// The registry key holding the order in which the network providers are asked.
+const PROVIDER_ORDER_KEY: &str = "SYSTEM\\CurrentControlSet\\Control\\NetworkProvider\\Order";
+
+
+/// An installed network provider.
+#[derive(Clone, Debug, Eq, PartialEq, Serialize)]
+pub struct NetworkProvider {
+    /// The name of the provider's service, e.g. `LanmanWorkstation`.
+    pub service: String,
+
+    /// The display name of the provider (e.g. `Microsoft Windows Network`), which is what
+    /// [`ShareRequest::provider`](crate::ShareRequest::provider) expects; `None` if it cannot be
+    /// read.
+    pub name: Option<String>,
+}
+
+
+/// Obtains the name of the Microsoft Windows Network (SMB) provider.
+///
+/// On failure, e.g. because the provider is not installed or not running, returns the Win32 error
+/// code.
+pub fn smb_provider_name() -> Result<String, u32> {
+    let mut buffer = vec![0u16; 256];
+    let mut buffer_size: u32 = buffer.len().try_into().unwrap();
+    let result = unsafe {
+        WNetGetProviderNameW(WNNC_NET_LANMAN, PWSTR(buffer.as_mut_ptr()), &mut buffer_size)
+    };
+    if result != NO_ERROR {
+        return Err(result.0);
+    }
+    Ok(wcstr_to_string(buffer.as_ptr()))
+}
+
+/// Splits the comma-separated `ProviderOrder` value into the service names.
+fn parse_provider_order(order: &str) -> Vec<&str> {
+    order.split(',')
+        .map(|service| service.trim())
+        .filter(|service| !service.is_empty())
+        .collect()
+}
+
+/// Lists the installed network providers in the order in which Windows asks them to handle a
+/// path, as configured in the `ProviderOrder` value of
+/// `HKEY_LOCAL_MACHINE\SYSTEM\CurrentControlSet\Control\NetworkProvider\Order`. Each
+/// provider's display name is read from the `NetworkProvider` key of its service.
+///
+/// Returns `None` if the order cannot be read.
+pub fn network_providers() -> Option<Vec<NetworkProvider>> {
+    let order = read_string(HKEY_LOCAL_MACHINE, PROVIDER_ORDER_KEY, "ProviderOrder")?;
+    let providers = parse_provider_order(&order).into_iter()
+        .map(|service| NetworkProvider {
+            name: read_string(
+                HKEY_LOCAL_MACHINE,
+                &format!("SYSTEM\\CurrentControlSet\\Services\\{}\\NetworkProvider", service),
+                "Name",
+            ),
+            service: service.to_owned(),
+        })
+        .collect();
+    Some(providers)
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn provider_order_is_split_at_commas() {
+        assert_eq!(
+            parse_provider_order("RDPNP,LanmanWorkstation, webclient,"),
+            ["RDPNP", "LanmanWorkstation", "webclient"],
+        );
+        assert!(parse_provider_order("").is_empty());
+    }
+}
@@ -0,0 +1,120 @@
+//! Detecting shares that hold folders of the user's profile redirected by Folder Redirection.
+//!
+//! In managed environments, folders such as Documents or Desktop are often redirected to a share
+//! (e.g. `\\fileserver\users$\me\Documents`). Connecting the same share again, possibly using other
+//! credentials or under another drive letter, can confuse Offline Files and the profile, so
+//! [`redirected_folders_on_share`] finds the redirected folders that a path overlaps with.
+//!
+//! The locations come from the shell's known folders, i.e. the user's shell folder settings with
+//! environment variables expanded. Folders redirected to a mapped drive (e.g. `H:\Documents`) are
+//! resolved to the share the drive is mapped to.
+
+
+use windows::core::GUID;
+use windows::Win32::System::Com::CoTaskMemFree;
+use windows::Win32::UI::Shell::{
+    FOLDERID_Desktop, FOLDERID_Documents, FOLDERID_Downloads, FOLDERID_Favorites, FOLDERID_Music,
+    FOLDERID_Pictures, FOLDERID_RoamingAppData, FOLDERID_StartMenu, FOLDERID_Videos,
+    KF_FLAG_DONT_VERIFY, SHGetKnownFolderPath,
+};
+
+use crate::connection::{device_connection, DeviceConnection};
+use crate::unc::{normalized_share_root, UncPath};
+use crate::wstr::wcstr_to_string;
+
+
+/// The folders of the profile that Folder Redirection can redirect, with their display names.
+const REDIRECTABLE_FOLDERS: [(&GUID, &str); 9] = [
+    (&FOLDERID_Desktop, "Desktop"),
+    (&FOLDERID_Documents, "Documents"),
+    (&FOLDERID_Downloads, "Downloads"),
+    (&FOLDERID_Favorites, "Favorites"),
+    (&FOLDERID_Music, "Music"),
+    (&FOLDERID_Pictures, "Pictures"),
+    (&FOLDERID_Videos, "Videos"),
+    (&FOLDERID_RoamingAppData, "AppData (Roaming)"),
+    (&FOLDERID_StartMenu, "Start Menu"),
+];
+
+
+/// A folder of the user's profile that is redirected to a share.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct RedirectedFolder {
+    /// The name of the folder, e.g. `Documents`.
+    pub name: &'static str,
+
+    /// Where the folder is redirected to, as configured (e.g. `\\fileserver\users$\me\Documents`
+    /// or `H:\Documents`).
+    pub location: String,
+}
+
+
+fn known_folder_path(id: &GUID) -> Option<String> {
+    let path = unsafe { SHGetKnownFolderPath(id, KF_FLAG_DONT_VERIFY, None) }.ok()?;
+    let string = wcstr_to_string(path.0);
+    unsafe { CoTaskMemFree(Some(path.0 as *const _)) };
+    Some(string)
+}
+
+/// The share that the given location is on, resolving a mapped drive to its remote name. Returns
+/// `None` for local locations.
+fn share_of_location(location: &str) -> Option<String> {
+    if UncPath::parse(location).is_some() {
+        return Some(normalized_share_root(location));
+    }
+    let drive = location.get(..2)?;
+    match device_connection(drive) {
+        DeviceConnection::Connected(remote) | DeviceConnection::Unavailable(remote) => {
+            Some(normalized_share_root(&remote))
+        },
+        DeviceConnection::None => None,
+    }
+}
+
+/// Picks the folders located on the same share as the path.
+fn folders_on_share<F: Fn(&str) -> Option<String>>(path: &str, folders: Vec<RedirectedFolder>, share_of: F) -> Vec<RedirectedFolder> {
+    let share_root = normalized_share_root(path);
+    folders.into_iter()
+        .filter(|folder| share_of(&folder.location).is_some_and(|share| share == share_root))
+        .collect()
+}
+
+/// Lists the folders of the user's profile that are redirected to the share of the given path.
+///
+/// This is a heuristic: folders are compared by server and share name, so a share reached through
+/// another name of the same server (e.g. its fully qualified name or a DFS namespace) is not
+/// recognized.
+pub fn redirected_folders_on_share(path: &str) -> Vec<RedirectedFolder> {
+    let folders = REDIRECTABLE_FOLDERS.iter()
+        .filter_map(|(id, name)| {
+            known_folder_path(id).map(|location| RedirectedFolder { name, location })
+        })
+        .collect();
+    folders_on_share(path, folders, share_of_location)
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn folders_on_same_share_overlap() {
+        let folder = |name, location: &str| RedirectedFolder { name, location: location.to_owned() };
+        let folders = vec![
+            folder("Documents", "\\\\FileServer\\Users$\\me\\Documents"),
+            folder("Desktop", "H:\\Desktop"),
+            folder("Music", "C:\\Users\\me\\Music"),
+            folder("Pictures", "\\\\fileserver\\pictures$\\me"),
+        ];
+        let share_of = |location: &str| match location.get(..2) {
+            Some("H:") => Some("\\\\fileserver\\users$".to_owned()),
+            _ if UncPath::parse(location).is_some() => Some(normalized_share_root(location)),
+            _ => None,
+        };
+
+        let overlapping = folders_on_share("\\\\fileserver\\users$\\me", folders, share_of);
+        let names: Vec<&str> = overlapping.iter().map(|f| f.name).collect();
+        assert_eq!(names, ["Documents", "Desktop"]);
+    }
+}
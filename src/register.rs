@@ -0,0 +1,74 @@
+//! Registering a persistent drive mapping without connecting it.
+//!
+//! `WNetAddConnection2W` has no flag that stores a mapping in the profile without also connecting
+//! it, so the mapping is written directly to where Windows keeps persistent mappings: the key
+//! `HKEY_CURRENT_USER\Network\<letter>` (e.g. `Network\X` for `X:`) with the values
+//!
+//! * `RemotePath` (`REG_SZ`): the UNC path of the share,
+//! * `UserName` (`REG_SZ`): the username to connect as, empty for the default credentials,
+//! * `ProviderName` (`REG_SZ`): the name of the network provider,
+//! * `ProviderType` (`REG_DWORD`): the network type of the provider (`WNNC_NET_LANMAN`) and
+//! * `ConnectionType` (`REG_DWORD`): `1` for a disk connection.
+//!
+//! Windows reads this key at the next logon and restores the mapping, prompting for credentials if
+//! necessary. Passwords cannot be stored this way.
+
+
+use windows::Win32::Foundation::ERROR_BAD_DEVICE;
+use windows::Win32::NetworkManagement::WNet::RESOURCETYPE_DISK;
+use windows::Win32::System::Registry::HKEY_CURRENT_USER;
+
+use crate::connection::find_device_connection;
+use crate::drive::drive_letter_index;
+use crate::error::OpenShareError;
+use crate::log::{log, ShareLogger};
+use crate::provider::{smb_provider_name, WNNC_NET_LANMAN};
+use crate::registry::{RegistryValue, write_values};
+use crate::request::ShareRequest;
+
+
+/// The name that the SMB provider usually has, used if it cannot be queried.
+const FALLBACK_SMB_PROVIDER_NAME: &str = "Microsoft Windows Network";
+
+
+/// Registers the requested share to be mapped to the requested drive at the next logon, without
+/// connecting it now.
+///
+/// Unless [`force`](ShareRequest::force) is set, this fails with
+/// [`OpenShareError::DriveInUse`] if the drive is currently mapped to another share. A current
+/// mapping is never disconnected.
+///
+/// # Panics
+///
+/// Panics if the request has no [`drive`](ShareRequest::drive).
+pub fn register_mapping(request: &ShareRequest, logger: Option<&dyn ShareLogger>) -> Result<(), OpenShareError> {
+    let drive = request.drive()
+        .expect("registering a mapping requires a drive");
+    let letter = drive_letter_index(drive)
+        .and_then(|i| char::from_u32(u32::from('A') + i))
+        .ok_or(OpenShareError::Register { code: ERROR_BAD_DEVICE.0 })?;
+
+    if !request.force() {
+        if let Some(holder) = find_device_connection(drive, request.enumeration(), logger) {
+            if !holder.eq_ignore_ascii_case(request.path()) {
+                return Err(OpenShareError::DriveInUse { drive: drive.to_owned(), holder: Some(holder) });
+            }
+        }
+    }
+
+    let provider = match request.provider() {
+        Some(p) => p.to_owned(),
+        None => smb_provider_name().unwrap_or_else(|_| FALLBACK_SMB_PROVIDER_NAME.to_owned()),
+    };
+    let values = [
+        ("RemotePath", RegistryValue::String(request.path())),
+        ("UserName", RegistryValue::String(request.username().unwrap_or(""))),
+        ("ProviderName", RegistryValue::String(&provider)),
+        ("ProviderType", RegistryValue::Dword(WNNC_NET_LANMAN)),
+        ("ConnectionType", RegistryValue::Dword(RESOURCETYPE_DISK.0)),
+    ];
+    write_values(HKEY_CURRENT_USER, &format!("Network\\{}", letter), &values)
+        .map_err(|code| OpenShareError::Register { code })?;
+    log!(logger, Success, Connect, "registered {} to be mapped to {} at the next logon", request.path(), drive);
+    Ok(())
+}
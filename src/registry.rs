@@ -0,0 +1,106 @@
+use std::ffi::c_void;
+
+use windows::core::PCWSTR;
+use windows::Win32::Foundation::NO_ERROR;
+use windows::Win32::System::Registry::{
+    HKEY, KEY_SET_VALUE, REG_DWORD, REG_OPTION_NON_VOLATILE, REG_SZ, RegCloseKey, RegCreateKeyExW,
+    RegGetValueW, RegSetValueExW, RRF_RT_REG_SZ,
+};
+
+use crate::wstr::{str_to_wcstring, wcstr_to_string};
+
+
+/// Reads a string value from the registry, returning `None` if it cannot be read.
+pub(crate) fn read_string(root: HKEY, subkey: &str, value: &str) -> Option<String> {
+    let subkey_windows = str_to_wcstring(subkey);
+    let value_windows = str_to_wcstring(value);
+
+    // find out how large the value is
+    let mut byte_count = 0u32;
+    let result = unsafe {
+        RegGetValueW(
+            root,
+            PCWSTR(subkey_windows.as_ptr()),
+            PCWSTR(value_windows.as_ptr()),
+            RRF_RT_REG_SZ,
+            None,
+            None,
+            Some(&mut byte_count),
+        )
+    };
+    if result != NO_ERROR {
+        return None;
+    }
+
+    let word_count: usize = (byte_count / 2 + 1).try_into().unwrap();
+    let mut buffer = vec![0u16; word_count];
+    let mut byte_count: u32 = (buffer.len() * 2).try_into().unwrap();
+    let result = unsafe {
+        RegGetValueW(
+            root,
+            PCWSTR(subkey_windows.as_ptr()),
+            PCWSTR(value_windows.as_ptr()),
+            RRF_RT_REG_SZ,
+            None,
+            Some(buffer.as_mut_ptr() as *mut c_void),
+            Some(&mut byte_count),
+        )
+    };
+    if result != NO_ERROR {
+        return None;
+    }
+    Some(wcstr_to_string(buffer.as_ptr()))
+}
+
+
+/// A value to be written to the registry.
+#[derive(Clone, Copy, Debug)]
+pub(crate) enum RegistryValue<'a> {
+    String(&'a str),
+    Dword(u32),
+}
+
+/// Writes the given values into a registry key, creating the key if necessary.
+///
+/// On failure, returns the Win32 error code; values written before the failure are kept.
+pub(crate) fn write_values(root: HKEY, subkey: &str, values: &[(&str, RegistryValue<'_>)]) -> Result<(), u32> {
+    let subkey_windows = str_to_wcstring(subkey);
+    let mut key = HKEY::default();
+    let result = unsafe {
+        RegCreateKeyExW(
+            root,
+            PCWSTR(subkey_windows.as_ptr()),
+            0,
+            PCWSTR::null(),
+            REG_OPTION_NON_VOLATILE,
+            KEY_SET_VALUE,
+            None,
+            &mut key,
+            None,
+        )
+    };
+    if result != NO_ERROR {
+        return Err(result.0);
+    }
+
+    let mut outcome = Ok(());
+    for (name, value) in values {
+        let name_windows = str_to_wcstring(name);
+        let (value_type, data): (_, Vec<u8>) = match value {
+            RegistryValue::String(s) => (
+                REG_SZ,
+                str_to_wcstring(s).iter().flat_map(|w| w.to_le_bytes()).collect(),
+            ),
+            RegistryValue::Dword(d) => (REG_DWORD, d.to_le_bytes().to_vec()),
+        };
+        let result = unsafe {
+            RegSetValueExW(key, PCWSTR(name_windows.as_ptr()), 0, value_type, Some(&data))
+        };
+        if result != NO_ERROR {
+            outcome = Err(result.0);
+            break;
+        }
+    }
+    let _ = unsafe { RegCloseKey(key) };
+    outcome
+}
@@ -0,0 +1,122 @@
+//! Reporting of fatal errors, either on the console or in a message box.
+//!
+//! When the program has no console (e.g. because it was launched from a shortcut with its console
+//! hidden), anything written to stderr is lost and nobody can answer the "press Enter" prompt.
+//! In that case, or if `--gui-errors` is passed, fatal errors are collected and shown together in
+//! a message box once the program is done.
+//!
+//! With `--quiet-success`, progress messages are held back instead of being written to stderr.
+//! They are written once an error is reported (followed by any further messages as they occur) or
+//! discarded if the program succeeds.
+
+
+use std::ptr::null_mut;
+use std::sync::Mutex;
+
+use open_share::{LogEvent, LogLevel, ShareLogger};
+use windows::core::{HSTRING, PCWSTR};
+use windows::Win32::Foundation::HWND;
+use windows::Win32::System::Console::GetConsoleWindow;
+use windows::Win32::UI::WindowsAndMessaging::{MB_ICONERROR, MB_OK, MessageBoxW};
+
+use crate::color::{self, Stream};
+
+
+/// Whether the process is attached to a console window.
+pub(crate) fn has_console() -> bool {
+    let console_window = unsafe { GetConsoleWindow() };
+    !console_window.0.is_null()
+}
+
+
+/// The progress messages held back by `--quiet-success`; `None` if messages are written directly.
+static HELD_BACK: Mutex<Option<Vec<String>>> = Mutex::new(None);
+
+
+/// Starts holding back progress messages (`--quiet-success`).
+pub(crate) fn hold_back_progress() {
+    let mut held_back = HELD_BACK.lock().unwrap();
+    held_back.get_or_insert_with(Vec::new);
+}
+
+/// Writes the progress messages held back so far and stops holding them back.
+pub(crate) fn release_progress() {
+    let held_back = HELD_BACK.lock().unwrap().take();
+    for message in held_back.unwrap_or_default() {
+        eprintln!("{}", message);
+    }
+}
+
+/// Writes a progress message to stderr unless it is being held back.
+pub(crate) fn progress<S: Into<String>>(message: S) {
+    let message = message.into();
+    match HELD_BACK.lock().unwrap().as_mut() {
+        Some(held_back) => held_back.push(message),
+        None => eprintln!("{}", message),
+    }
+}
+
+
+/// Writes the library's log events to stderr as progress messages.
+pub(crate) struct StderrLogger;
+impl ShareLogger for StderrLogger {
+    fn log(&self, event: &LogEvent) {
+        match event.level {
+            LogLevel::Success => progress(color::success(Stream::Stderr, &event.message)),
+            LogLevel::Warning => progress(format!("warning: {}", event.message)),
+            _ => progress(event.message.clone()),
+        }
+    }
+}
+
+/// The logger passed to each call into the library.
+pub(crate) const LOGGER: Option<&dyn ShareLogger> = Some(&StderrLogger);
+
+
+pub(crate) struct ErrorReporter {
+    gui: bool,
+    messages: Vec<String>,
+}
+impl ErrorReporter {
+    pub fn new(gui: bool) -> Self {
+        Self {
+            gui,
+            messages: Vec::new(),
+        }
+    }
+
+    /// Whether errors are shown in a message box instead of on the console.
+    pub fn is_gui(&self) -> bool {
+        self.gui
+    }
+
+    /// Reports a fatal error.
+    pub fn error<S: Into<String>>(&mut self, message: S) {
+        let message = message.into();
+        release_progress();
+        if self.gui {
+            self.messages.push(message);
+        } else {
+            eprintln!("{}", color::failure(Stream::Stderr, &message));
+        }
+    }
+
+    /// Shows the collected errors in a message box if running in GUI mode.
+    pub fn show(&mut self) {
+        if !self.gui || self.messages.is_empty() {
+            return;
+        }
+
+        let text = HSTRING::from(self.messages.join("\n"));
+        let caption = HSTRING::from("open-share");
+        unsafe {
+            MessageBoxW(
+                HWND(null_mut()),
+                PCWSTR(text.as_ptr()),
+                PCWSTR(caption.as_ptr()),
+                MB_OK | MB_ICONERROR,
+            )
+        };
+        self.messages.clear();
+    }
+}
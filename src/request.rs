@@ -0,0 +1,627 @@
+use std::fmt;
+use std::time::Duration;
+
+use windows::Win32::NetworkManagement::WNet::{
+    CONNECT_INTERACTIVE, CONNECT_PROMPT, CONNECT_TEMPORARY, NET_CONNECT_FLAGS,
+};
+use windows::Win32::UI::WindowsAndMessaging::{SHOW_WINDOW_CMD, SW_SHOWNORMAL};
+
+use crate::connection::EnumerationOptions;
+use crate::summary::CredentialSource;
+use crate::unc::server_root;
+
+
+/// The flags passed to `WNetAddConnection2W` unless specified otherwise.
+pub const DEFAULT_CONNECT_FLAGS: NET_CONNECT_FLAGS = NET_CONNECT_FLAGS(
+    CONNECT_INTERACTIVE.0 | CONNECT_PROMPT.0 | CONNECT_TEMPORARY.0
+);
+
+/// How long to wait for a newly mapped drive to appear unless specified otherwise.
+pub const DEFAULT_DRIVE_WAIT: Duration = Duration::from_secs(5);
+
+
+/// A request to connect to a share and open it.
+///
+/// Construct one using [`ShareRequest::builder`].
+#[derive(Clone)]
+pub struct ShareRequest {
+    path: String,
+    username: Option<String>,
+    password: Option<String>,
+    drive: Option<String>,
+    flags: NET_CONNECT_FLAGS,
+    provider: Option<String>,
+    comment: Option<String>,
+    verb: String,
+    window: SHOW_WINDOW_CMD,
+    drive_wait: Option<Duration>,
+    smartcard: bool,
+    dump_netresource: bool,
+    enumeration: EnumerationOptions,
+    query_space: bool,
+    properties: bool,
+    force: bool,
+    modern_prompt: bool,
+    prompt_title: Option<String>,
+    prompt_message: Option<String>,
+    max_connections: Option<usize>,
+    open_timeout: Option<Duration>,
+    clear_cached_credentials: bool,
+    credential_target: Option<String>,
+    terminal: bool,
+    skip_check: bool,
+    assume_connected: bool,
+    heal_stale: bool,
+    reuse_any: bool,
+    require_write: bool,
+    resolve_long_names: bool,
+    admin_fallback: bool,
+    admin_fallback_dir: Option<String>,
+    open_with: Option<String>,
+    open_with_args: Option<String>,
+    only_if_missing: bool,
+    authenticate_server: bool,
+    credential_source: CredentialSource,
+}
+impl fmt::Debug for ShareRequest {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        // never show the password, e.g. in logs or panic messages
+        f.debug_struct("ShareRequest")
+            .field("path", &self.path)
+            .field("username", &self.username)
+            .field("password", &self.password.as_ref().map(|_| "<redacted>"))
+            .field("drive", &self.drive)
+            .field("flags", &self.flags)
+            .field("provider", &self.provider)
+            .field("comment", &self.comment)
+            .field("verb", &self.verb)
+            .field("window", &self.window)
+            .field("drive_wait", &self.drive_wait)
+            .field("smartcard", &self.smartcard)
+            .field("dump_netresource", &self.dump_netresource)
+            .field("enumeration", &self.enumeration)
+            .field("query_space", &self.query_space)
+            .field("properties", &self.properties)
+            .field("force", &self.force)
+            .field("modern_prompt", &self.modern_prompt)
+            .field("prompt_title", &self.prompt_title)
+            .field("prompt_message", &self.prompt_message)
+            .field("max_connections", &self.max_connections)
+            .field("open_timeout", &self.open_timeout)
+            .field("clear_cached_credentials", &self.clear_cached_credentials)
+            .field("credential_target", &self.credential_target)
+            .field("terminal", &self.terminal)
+            .field("skip_check", &self.skip_check)
+            .field("assume_connected", &self.assume_connected)
+            .field("heal_stale", &self.heal_stale)
+            .field("reuse_any", &self.reuse_any)
+            .field("require_write", &self.require_write)
+            .field("resolve_long_names", &self.resolve_long_names)
+            .field("admin_fallback", &self.admin_fallback)
+            .field("admin_fallback_dir", &self.admin_fallback_dir)
+            .field("open_with", &self.open_with)
+            .field("open_with_args", &self.open_with_args)
+            .field("only_if_missing", &self.only_if_missing)
+            .field("authenticate_server", &self.authenticate_server)
+            .field("credential_source", &self.credential_source)
+            .finish()
+    }
+}
+impl ShareRequest {
+    /// Starts building a request for the share at the given UNC path.
+    pub fn builder<P: Into<String>>(path: P) -> ShareRequestBuilder {
+        ShareRequestBuilder::new(path)
+    }
+
+    /// The UNC path of the share, or of a server (`\\server`) to list its shares; see
+    /// [`authenticate_server`](Self::authenticate_server).
+    pub fn path(&self) -> &str { &self.path }
+
+    /// The username to connect as; `None` uses the default credentials.
+    pub fn username(&self) -> Option<&str> { self.username.as_deref() }
+
+    /// The password to connect with; `None` uses the default password for the username.
+    pub fn password(&self) -> Option<&str> { self.password.as_deref() }
+
+    /// The local device (e.g. `X:`) to map the share to; `None` connects without a device.
+    pub fn drive(&self) -> Option<&str> { self.drive.as_deref() }
+
+    /// The flags passed to `WNetAddConnection2W`.
+    pub fn flags(&self) -> NET_CONNECT_FLAGS { self.flags }
+
+    /// The network provider to use; `None` lets Windows choose.
+    pub fn provider(&self) -> Option<&str> { self.provider.as_deref() }
+
+    /// The comment stored with the connection.
+    ///
+    /// The tokens `{user}`, `{host}` and `{date}` are expanded when connecting; see
+    /// [`expand_comment`](crate::expand_comment).
+    pub fn comment(&self) -> Option<&str> { self.comment.as_deref() }
+
+    /// The shell verb used to open the share.
+    pub fn verb(&self) -> &str { &self.verb }
+
+    /// How the window opened for the share is shown.
+    pub fn window(&self) -> SHOW_WINDOW_CMD { self.window }
+
+    /// How long to wait for a newly mapped drive to appear before opening it; `None` opens it
+    /// immediately.
+    pub fn drive_wait(&self) -> Option<Duration> { self.drive_wait }
+
+    /// Whether to authenticate using the certificate on the inserted smartcard instead of the
+    /// username; see the [`smartcard`](crate::smartcard) module for prerequisites. Without the
+    /// `smartcard` feature, connecting fails with
+    /// [`OpenShareError::NotCompiledIn`](crate::OpenShareError::NotCompiledIn).
+    pub fn smartcard(&self) -> bool { self.smartcard }
+
+    /// Whether to log the `NETRESOURCEW` structure and the other arguments (at
+    /// [`LogLevel::Debug`](crate::LogLevel::Debug)) before passing them to `WNetAddConnection2W`.
+    /// The password is never logged.
+    pub fn dump_netresource(&self) -> bool { self.dump_netresource }
+
+    /// How the existing connections are enumerated for this request, e.g. to check whether the
+    /// share is connected already, to count the connections for
+    /// [`max_connections`](Self::max_connections) or to find the holder of the drive.
+    pub fn enumeration(&self) -> &EnumerationOptions { &self.enumeration }
+
+    /// Whether to query the free and total space of the share once it has been opened; see
+    /// [`Summary::space`](crate::Summary::space).
+    pub fn query_space(&self) -> bool { self.query_space }
+
+    /// Whether to show the properties dialog of the share instead of opening it with
+    /// [`verb`](Self::verb); the verb is only used if the dialog cannot be shown.
+    pub fn properties(&self) -> bool { self.properties }
+
+    /// Whether to disconnect whatever [`drive`](Self::drive) is currently mapped to instead of
+    /// failing with [`OpenShareError::DriveInUse`](crate::OpenShareError::DriveInUse).
+    pub fn force(&self) -> bool { self.force }
+
+    /// Whether to ask for credentials using the modern credential dialog (before connecting) instead
+    /// of letting `WNetAddConnection2W` prompt. Ignored if a password or a smartcard is used.
+    /// Without the `modern-prompt` feature, connecting fails with
+    /// [`OpenShareError::NotCompiledIn`](crate::OpenShareError::NotCompiledIn).
+    pub fn modern_prompt(&self) -> bool { self.modern_prompt }
+
+    /// The caption of the modern credential dialog; `None` derives one from the share name.
+    pub fn prompt_title(&self) -> Option<&str> { self.prompt_title.as_deref() }
+
+    /// The message of the modern credential dialog; `None` derives one from the path.
+    pub fn prompt_message(&self) -> Option<&str> { self.prompt_message.as_deref() }
+
+    /// Refuse to connect if at least this many disk connections exist already; `None` imposes no
+    /// limit. Shares that are connected already are opened regardless.
+    pub fn max_connections(&self) -> Option<usize> { self.max_connections }
+
+    /// How long the open phase may take once the share is connected; `None` imposes no limit.
+    ///
+    /// This bounds the wait for a newly mapped drive to appear (see [`drive_wait`](Self::drive_wait))
+    /// and the wait for the properties dialog (see [`properties`](Self::properties)). It does not
+    /// cover connecting to the share.
+    pub fn open_timeout(&self) -> Option<Duration> { self.open_timeout }
+
+    /// Whether to remove the credentials stored for the server and disconnect all connections to
+    /// it before connecting, e.g. because its password has changed. This also disconnects the
+    /// share itself if it is connected already. Without the `credential-manager` feature,
+    /// connecting fails with
+    /// [`OpenShareError::NotCompiledIn`](crate::OpenShareError::NotCompiledIn).
+    pub fn clear_cached_credentials(&self) -> bool { self.clear_cached_credentials }
+
+    /// The target name under which the credentials for the share are stored in the Credential
+    /// Manager, if it differs from the server name of the path (the default), e.g. a wildcard
+    /// target such as `*.corp.example`. It is used to recognize that stored credentials are used
+    /// (see [`CredentialSource::CredentialManager`]) and by
+    /// [`clear_cached_credentials`](Self::clear_cached_credentials).
+    ///
+    /// This does not change which credentials Windows itself uses when connecting: it looks up the
+    /// entries for the server name, considering wildcard targets matching it (`*.corp.example`
+    /// for `fs1.corp.example`, or `*` for any server) if there is no entry for the exact name,
+    /// and prefers the most specific match. The Credential Manager, on the other hand, is asked
+    /// for exactly the given target, without any matching.
+    pub fn credential_target(&self) -> Option<&str> { self.credential_target.as_deref() }
+
+    /// Whether to open a terminal (Windows Terminal if available, otherwise the Command Prompt) in
+    /// the share instead of opening it with [`verb`](Self::verb).
+    pub fn terminal(&self) -> bool { self.terminal }
+
+    /// Whether to connect without first checking whether the share is connected already, which
+    /// saves enumerating the existing connections.
+    ///
+    /// Connecting to a share that is connected already succeeds without a drive. With a drive that
+    /// is already mapped to the share, the share counts as connected already. Either way,
+    /// [`Summary::canonical`](crate::Summary::canonical) is not determined. As counting the
+    /// connections would also enumerate them, [`max_connections`](Self::max_connections) is
+    /// ignored.
+    pub fn skip_check(&self) -> bool { self.skip_check }
+
+    /// Whether to open the share right away on the assumption that it is connected already, which
+    /// saves enumerating the existing connections if it is.
+    ///
+    /// Only if opening fails with `ERROR_FILE_NOT_FOUND`, `ERROR_PATH_NOT_FOUND` or
+    /// `ERROR_ACCESS_DENIED` (as happens for a drive that is not mapped or a share that requires
+    /// other credentials) is the share connected as usual and opened again; any other failure is
+    /// returned as is. If the current logon session can access the share without a connection, it
+    /// is opened using those credentials and [`username`](Self::username) is not used.
+    ///
+    /// Ignored together with [`terminal`](Self::terminal), [`properties`](Self::properties) or
+    /// [`open_with`](Self::open_with), and
+    /// by [`open_shares`](crate::open_shares) when connecting concurrently.
+    pub fn assume_connected(&self) -> bool { self.assume_connected }
+
+    /// Whether to make sure that a connection found while checking the existing connections still
+    /// works, e.g. after resuming from hibernation. Its share is accessed, which re-establishes a
+    /// dormant connection; if that fails, the stale connection is removed and the share connected
+    /// anew. With a [`drive`](Self::drive), a drive remembered for the share but not currently
+    /// connected is revived the same way.
+    ///
+    /// Accessing a server that cannot be reached may take as long as the network timeout. Ignored
+    /// with [`skip_check`](Self::skip_check) and when the share is opened right away because of
+    /// [`assume_connected`](Self::assume_connected).
+    pub fn heal_stale(&self) -> bool { self.heal_stale }
+
+    /// Whether to open the share without connecting if this logon session has any connection to
+    /// its server, even one made using other credentials (e.g. by a service account on a shared
+    /// kiosk). Connecting with different credentials would fail with
+    /// `ERROR_SESSION_CREDENTIAL_CONFLICT`; reusing the connection avoids that where sharing it is
+    /// intended.
+    ///
+    /// The share is then accessed with the rights of whoever made the existing connection, which
+    /// may differ from those of the requested user, and the requested username and password are
+    /// neither used nor checked. A warning is logged whenever a connection is reused this way. Not
+    /// used for requests mapping a [`drive`](Self::drive), which need a connection of their own,
+    /// or with [`skip_check`](Self::skip_check).
+    pub fn reuse_any(&self) -> bool { self.reuse_any }
+
+    /// Whether to verify that the share can be written to once it is connected, before opening it,
+    /// by creating an empty hidden file in its root and deleting it again. If that fails, the share
+    /// stays connected but is not opened, and
+    /// [`OpenShareError::NotWritable`](crate::OpenShareError::NotWritable) is returned.
+    ///
+    /// Ignored when the share is opened right away because of
+    /// [`assume_connected`](Self::assume_connected).
+    pub fn require_write(&self) -> bool { self.require_write }
+
+    /// Whether to replace 8.3 short names (e.g. `PROJEC~1`) in the path by the long names once the
+    /// share is connected, so that the opened path and
+    /// [`Summary::normalized`](crate::Summary::normalized) use the long form.
+    ///
+    /// This requires the path to exist and be accessible; otherwise, it is kept as is. Not done if
+    /// the share is opened right away because of [`assume_connected`](Self::assume_connected).
+    pub fn resolve_long_names(&self) -> bool { self.resolve_long_names }
+
+    /// Whether to retry through the administrative share `C$` if the server reports that the share
+    /// does not exist (`ERROR_BAD_NET_NAME`); see [`UncPath::administrative_path`] for the path
+    /// tried. The substitution is logged as a warning.
+    ///
+    /// Only administrators of the server can access `C$`, so this needs the credentials of one.
+    /// As the fallback reaches the folder without the share's permissions, only use it
+    /// deliberately.
+    ///
+    /// [`UncPath::administrative_path`]: crate::UncPath::administrative_path
+    pub fn admin_fallback(&self) -> bool { self.admin_fallback }
+
+    /// The directory on drive C containing the share's folder for
+    /// [`admin_fallback`](Self::admin_fallback), or `None` for the root of the drive.
+    pub fn admin_fallback_dir(&self) -> Option<&str> { self.admin_fallback_dir.as_deref() }
+
+    /// The program to open the share with (e.g. another file manager) instead of the shell, if any.
+    /// It is started with [`open_with_args`](Self::open_with_args) as its arguments.
+    pub fn open_with(&self) -> Option<&str> { self.open_with.as_deref() }
+
+    /// The arguments for the [`open_with`](Self::open_with) program. The quoted path to open
+    /// replaces the placeholder `{}` if there is one; otherwise, it is appended.
+    pub fn open_with_args(&self) -> Option<&str> { self.open_with_args.as_deref() }
+
+    /// Whether to skip opening the share with [`verb`](Self::verb) if an Explorer window already
+    /// shows the path to be opened, so that running the same request repeatedly does not pile up
+    /// windows. The share is connected either way.
+    ///
+    /// The Explorer windows are found using the shell's `IShellWindows` COM collection. If it
+    /// cannot be enumerated (e.g. because Explorer is not running as the shell), the share is
+    /// opened as usual.
+    pub fn only_if_missing(&self) -> bool { self.only_if_missing }
+
+    /// For a path naming a server rather than a share (`\\server`, see
+    /// [`server_root`](crate::server_root)), whether to connect to the server's `IPC$` share using
+    /// the [`username`](Self::username) and [`password`](Self::password) before opening it, so that
+    /// listing its shares uses these credentials. Otherwise, such a path is opened without
+    /// connecting anything, listing the shares using the credentials of the current logon session
+    /// or of an existing connection to the server. Ignored for share paths.
+    pub fn authenticate_server(&self) -> bool { self.authenticate_server }
+
+    /// Where the caller obtained the [`username`](Self::username) and
+    /// [`password`](Self::password) from (default: [`CredentialSource::Explicit`]). Reported in
+    /// [`Summary::credential_source`](crate::Summary::credential_source) if they are used.
+    pub fn credential_source(&self) -> CredentialSource { self.credential_source }
+
+    /// The path that is opened once the share is connected.
+    ///
+    /// This is the root of the drive if the share is mapped to one, otherwise the UNC path.
+    pub fn open_target(&self) -> String {
+        match &self.drive {
+            Some(drive) => format!("{}\\", drive),
+            None => self.path.clone(),
+        }
+    }
+
+    /// A copy of this request for another path.
+    pub(crate) fn with_path(&self, path: String) -> ShareRequest {
+        ShareRequest {
+            path,
+            ..self.clone()
+        }
+    }
+}
+
+
+/// Builds a [`ShareRequest`].
+#[derive(Clone)]
+pub struct ShareRequestBuilder {
+    request: ShareRequest,
+}
+impl fmt::Debug for ShareRequestBuilder {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ShareRequestBuilder")
+            .field("request", &self.request)
+            .finish()
+    }
+}
+impl ShareRequestBuilder {
+    fn new<P: Into<String>>(path: P) -> Self {
+        Self {
+            request: ShareRequest {
+                path: path.into(),
+                username: None,
+                password: None,
+                drive: None,
+                flags: DEFAULT_CONNECT_FLAGS,
+                provider: None,
+                comment: None,
+                verb: "open".to_owned(),
+                window: SW_SHOWNORMAL,
+                drive_wait: Some(DEFAULT_DRIVE_WAIT),
+                smartcard: false,
+                dump_netresource: false,
+                enumeration: EnumerationOptions::default(),
+                query_space: false,
+                properties: false,
+                force: false,
+                modern_prompt: false,
+                prompt_title: None,
+                prompt_message: None,
+                max_connections: None,
+                open_timeout: None,
+                clear_cached_credentials: false,
+                credential_target: None,
+                terminal: false,
+                skip_check: false,
+                assume_connected: false,
+                heal_stale: false,
+                reuse_any: false,
+                require_write: false,
+                resolve_long_names: false,
+                admin_fallback: false,
+                admin_fallback_dir: None,
+                open_with: None,
+                open_with_args: None,
+                only_if_missing: false,
+                authenticate_server: false,
+                credential_source: CredentialSource::Explicit,
+            },
+        }
+    }
+
+    pub fn username<S: Into<String>>(mut self, username: S) -> Self {
+        self.request.username = Some(username.into());
+        self
+    }
+
+    pub fn password<S: Into<String>>(mut self, password: S) -> Self {
+        self.request.password = Some(password.into());
+        self
+    }
+
+    pub fn drive<S: Into<String>>(mut self, drive: S) -> Self {
+        self.request.drive = Some(drive.into());
+        self
+    }
+
+    pub fn flags(mut self, flags: NET_CONNECT_FLAGS) -> Self {
+        self.request.flags = flags;
+        self
+    }
+
+    pub fn provider<S: Into<String>>(mut self, provider: S) -> Self {
+        self.request.provider = Some(provider.into());
+        self
+    }
+
+    pub fn comment<S: Into<String>>(mut self, comment: S) -> Self {
+        self.request.comment = Some(comment.into());
+        self
+    }
+
+    pub fn verb<S: Into<String>>(mut self, verb: S) -> Self {
+        self.request.verb = verb.into();
+        self
+    }
+
+    pub fn window(mut self, window: SHOW_WINDOW_CMD) -> Self {
+        self.request.window = window;
+        self
+    }
+
+    pub fn drive_wait(mut self, drive_wait: Option<Duration>) -> Self {
+        self.request.drive_wait = drive_wait;
+        self
+    }
+
+    pub fn smartcard(mut self, smartcard: bool) -> Self {
+        self.request.smartcard = smartcard;
+        self
+    }
+
+    pub fn dump_netresource(mut self, dump_netresource: bool) -> Self {
+        self.request.dump_netresource = dump_netresource;
+        self
+    }
+
+    pub fn enumeration(mut self, enumeration: EnumerationOptions) -> Self {
+        self.request.enumeration = enumeration;
+        self
+    }
+
+    pub fn query_space(mut self, query_space: bool) -> Self {
+        self.request.query_space = query_space;
+        self
+    }
+
+    pub fn properties(mut self, properties: bool) -> Self {
+        self.request.properties = properties;
+        self
+    }
+
+    pub fn force(mut self, force: bool) -> Self {
+        self.request.force = force;
+        self
+    }
+
+    pub fn modern_prompt(mut self, modern_prompt: bool) -> Self {
+        self.request.modern_prompt = modern_prompt;
+        self
+    }
+
+    pub fn prompt_title<S: Into<String>>(mut self, prompt_title: S) -> Self {
+        self.request.prompt_title = Some(prompt_title.into());
+        self
+    }
+
+    pub fn prompt_message<S: Into<String>>(mut self, prompt_message: S) -> Self {
+        self.request.prompt_message = Some(prompt_message.into());
+        self
+    }
+
+    pub fn max_connections(mut self, max_connections: Option<usize>) -> Self {
+        self.request.max_connections = max_connections;
+        self
+    }
+
+    pub fn open_timeout(mut self, open_timeout: Option<Duration>) -> Self {
+        self.request.open_timeout = open_timeout;
+        self
+    }
+
+    pub fn clear_cached_credentials(mut self, clear_cached_credentials: bool) -> Self {
+        self.request.clear_cached_credentials = clear_cached_credentials;
+        self
+    }
+
+    pub fn credential_target<S: Into<String>>(mut self, credential_target: S) -> Self {
+        self.request.credential_target = Some(credential_target.into());
+        self
+    }
+
+    pub fn terminal(mut self, terminal: bool) -> Self {
+        self.request.terminal = terminal;
+        self
+    }
+
+    pub fn skip_check(mut self, skip_check: bool) -> Self {
+        self.request.skip_check = skip_check;
+        self
+    }
+
+    pub fn assume_connected(mut self, assume_connected: bool) -> Self {
+        self.request.assume_connected = assume_connected;
+        self
+    }
+
+    pub fn heal_stale(mut self, heal_stale: bool) -> Self {
+        self.request.heal_stale = heal_stale;
+        self
+    }
+
+    pub fn reuse_any(mut self, reuse_any: bool) -> Self {
+        self.request.reuse_any = reuse_any;
+        self
+    }
+
+    pub fn require_write(mut self, require_write: bool) -> Self {
+        self.request.require_write = require_write;
+        self
+    }
+
+    pub fn resolve_long_names(mut self, resolve_long_names: bool) -> Self {
+        self.request.resolve_long_names = resolve_long_names;
+        self
+    }
+
+    pub fn admin_fallback(mut self, admin_fallback: bool) -> Self {
+        self.request.admin_fallback = admin_fallback;
+        self
+    }
+
+    pub fn admin_fallback_dir<S: Into<String>>(mut self, admin_fallback_dir: S) -> Self {
+        self.request.admin_fallback_dir = Some(admin_fallback_dir.into());
+        self
+    }
+
+    pub fn open_with<S: Into<String>>(mut self, open_with: S) -> Self {
+        self.request.open_with = Some(open_with.into());
+        self
+    }
+
+    pub fn open_with_args<S: Into<String>>(mut self, open_with_args: S) -> Self {
+        self.request.open_with_args = Some(open_with_args.into());
+        self
+    }
+
+    pub fn only_if_missing(mut self, only_if_missing: bool) -> Self {
+        self.request.only_if_missing = only_if_missing;
+        self
+    }
+
+    pub fn authenticate_server(mut self, authenticate_server: bool) -> Self {
+        self.request.authenticate_server = authenticate_server;
+        self
+    }
+
+    pub fn credential_source(mut self, credential_source: CredentialSource) -> Self {
+        self.request.credential_source = credential_source;
+        self
+    }
+
+    /// Finishes the request. A path naming a server (`\\server`) cannot be mapped to a drive, so
+    /// the [`drive`](Self::drive) is dropped for one.
+    pub fn build(mut self) -> ShareRequest {
+        if server_root(&self.request.path).is_some() {
+            self.request.drive = None;
+        }
+        self.request
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn debug_redacts_the_password() {
+        let builder = ShareRequest::builder("\\\\server\\share")
+            .username("alice")
+            .password("hunter2");
+        let builder_debug = format!("{:?}", builder);
+        let request_debug = format!("{:?}", builder.build());
+        for debug in [builder_debug, request_debug] {
+            assert!(!debug.contains("hunter2"), "{}", debug);
+            assert!(debug.contains("<redacted>"), "{}", debug);
+            assert!(debug.contains("alice"), "{}", debug);
+        }
+    }
+
+    #[test]
+    fn debug_shows_a_missing_password() {
+        let debug = format!("{:?}", ShareRequest::builder("\\\\server\\share").build());
+        assert!(debug.contains("password: None"), "{}", debug);
+    }
+}
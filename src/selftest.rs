@@ -0,0 +1,120 @@
+//! The `selftest` subcommand, which checks whether the environment is able to connect to shares.
+
+
+use std::io::Error as IoError;
+use std::time::Duration;
+
+use open_share::{
+    AddressFamily, probe_host, query_service_state, ServiceState, smb_provider_name, SMB_PORT,
+    WORKSTATION_SERVICE,
+};
+
+use crate::color::{self, Stream};
+
+
+const PROBE_TIMEOUT: Duration = Duration::from_secs(5);
+
+
+struct CheckResult {
+    name: String,
+    failure: Option<(String, &'static str)>,
+}
+impl CheckResult {
+    fn pass<N: Into<String>>(name: N) -> Self {
+        Self { name: name.into(), failure: None }
+    }
+
+    fn fail<N: Into<String>, D: Into<String>>(name: N, detail: D, hint: &'static str) -> Self {
+        Self { name: name.into(), failure: Some((detail.into(), hint)) }
+    }
+}
+
+
+fn check_provider() -> CheckResult {
+    let name = "Microsoft Windows Network provider is available";
+    match smb_provider_name() {
+        Ok(_) => CheckResult::pass(name),
+        Err(code) => CheckResult::fail(
+            name,
+            IoError::from_raw_os_error(code as i32).to_string(),
+            "make sure the \"Client for Microsoft Networks\" is installed and enabled on the network adapter",
+        ),
+    }
+}
+
+fn check_workstation_service() -> CheckResult {
+    let name = format!("{} service is running", WORKSTATION_SERVICE);
+    match query_service_state(WORKSTATION_SERVICE) {
+        Ok(ServiceState::Running) => CheckResult::pass(name),
+        Ok(state) => CheckResult::fail(
+            name,
+            format!("service state is {:?}", state),
+            "start the Workstation service (`sc start LanmanWorkstation`) and set it to start automatically",
+        ),
+        Err(code) => CheckResult::fail(
+            name,
+            IoError::from_raw_os_error(code as i32).to_string(),
+            "check that the Workstation service is installed and that you are allowed to query it",
+        ),
+    }
+}
+
+fn check_account() -> CheckResult {
+    let name = "running as a user account rather than SYSTEM";
+    match open_share::running_as_system() {
+        Some(false) => CheckResult::pass(name),
+        Some(true) => CheckResult::fail(
+            name,
+            "running as SYSTEM, so shares are accessed using the computer account",
+            "pass a USERNAME and --password, or run the service or task as a user with access to the shares",
+        ),
+        None => CheckResult::fail(
+            name,
+            "the account of the process could not be determined",
+            "check that the process is allowed to query its own token",
+        ),
+    }
+}
+
+fn check_server(server: &str, family: AddressFamily) -> CheckResult {
+    let family_suffix = match family {
+        AddressFamily::Any => "",
+        AddressFamily::Ipv4 => " via IPv4",
+        AddressFamily::Ipv6 => " via IPv6",
+    };
+    let name = format!("{} is reachable on port {}{}", server, SMB_PORT, family_suffix);
+    match probe_host(server, SMB_PORT, PROBE_TIMEOUT, family) {
+        Ok(_) => CheckResult::pass(name),
+        Err(e) => CheckResult::fail(
+            name,
+            e.to_string(),
+            "check the server name, DNS resolution, and that no firewall blocks TCP port 445",
+        ),
+    }
+}
+
+/// Runs all checks, printing each result. Returns the exit code.
+pub(crate) fn run(server: Option<&str>, family: AddressFamily) -> i32 {
+    let mut results = vec![
+        check_provider(),
+        check_workstation_service(),
+        check_account(),
+    ];
+    if let Some(s) = server {
+        results.push(check_server(s, family));
+    }
+
+    let mut all_passed = true;
+    for result in &results {
+        match &result.failure {
+            None => println!("{} {}", color::success(Stream::Stdout, "[PASS]"), result.name),
+            Some((detail, hint)) => {
+                all_passed = false;
+                println!("{} {}: {}", color::failure(Stream::Stdout, "[FAIL]"), result.name, detail);
+                println!("       hint: {}", hint);
+            },
+        }
+    }
+
+    if all_passed { 0 } else { 1 }
+}
@@ -0,0 +1,64 @@
+use windows::core::PCWSTR;
+use windows::Win32::System::Services::{
+    CloseServiceHandle, OpenSCManagerW, OpenServiceW, QueryServiceStatus, SC_MANAGER_CONNECT,
+    SERVICE_CONTINUE_PENDING, SERVICE_PAUSE_PENDING, SERVICE_PAUSED, SERVICE_QUERY_STATUS,
+    SERVICE_RUNNING, SERVICE_START_PENDING, SERVICE_STATUS, SERVICE_STOP_PENDING, SERVICE_STOPPED,
+};
+
+use crate::error::win32_code;
+use crate::wstr::str_to_wcstring;
+
+
+/// The name of the Workstation service, which provides SMB client connectivity.
+pub const WORKSTATION_SERVICE: &str = "LanmanWorkstation";
+
+
+/// The state of a Windows service.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum ServiceState {
+    Stopped,
+    StartPending,
+    StopPending,
+    Running,
+    ContinuePending,
+    PausePending,
+    Paused,
+    Other(u32),
+}
+
+
+/// Queries the current state of the service with the given name.
+///
+/// On failure, returns the Win32 error code.
+pub fn query_service_state(name: &str) -> Result<ServiceState, u32> {
+    let name_windows = str_to_wcstring(name);
+
+    let manager = unsafe { OpenSCManagerW(PCWSTR::null(), PCWSTR::null(), SC_MANAGER_CONNECT) }
+        .map_err(|e| win32_code(&e))?;
+    let service = match unsafe { OpenServiceW(manager, PCWSTR(name_windows.as_ptr()), SERVICE_QUERY_STATUS) } {
+        Ok(s) => s,
+        Err(e) => {
+            let _ = unsafe { CloseServiceHandle(manager) };
+            return Err(win32_code(&e));
+        },
+    };
+
+    let mut status = SERVICE_STATUS::default();
+    let result = unsafe { QueryServiceStatus(service, &mut status) };
+
+    let _ = unsafe { CloseServiceHandle(service) };
+    let _ = unsafe { CloseServiceHandle(manager) };
+
+    result.map_err(|e| win32_code(&e))?;
+    let state = match status.dwCurrentState {
+        SERVICE_STOPPED => ServiceState::Stopped,
+        SERVICE_START_PENDING => ServiceState::StartPending,
+        SERVICE_STOP_PENDING => ServiceState::StopPending,
+        SERVICE_RUNNING => ServiceState::Running,
+        SERVICE_CONTINUE_PENDING => ServiceState::ContinuePending,
+        SERVICE_PAUSE_PENDING => ServiceState::PausePending,
+        SERVICE_PAUSED => ServiceState::Paused,
+        other => ServiceState::Other(other.0),
+    };
+    Ok(state)
+}
@@ -0,0 +1,286 @@
+use std::env;
+use std::ffi::{c_void, OsStr};
+use std::mem::size_of;
+use std::path::Path;
+use std::ptr::null_mut;
+use std::time::{Duration, Instant};
+
+use windows::core::{Interface, PCWSTR, PWSTR, VARIANT, w};
+use windows::Win32::Foundation::{BOOL, CloseHandle, ERROR_PATH_NOT_FOUND, HWND, LPARAM, TRUE};
+use windows::Win32::System::Com::{
+    CLSCTX_LOCAL_SERVER, CoCreateInstance, CoInitializeEx, COINIT_MULTITHREADED, CoUninitialize,
+};
+use windows::Win32::System::Registry::HKEY_LOCAL_MACHINE;
+use windows::Win32::System::Threading::{
+    CREATE_NEW_CONSOLE, CREATE_UNICODE_ENVIRONMENT, CreateProcessW, GetCurrentProcessId, PROCESS_INFORMATION,
+    STARTF_USESHOWWINDOW, STARTUPINFOW,
+};
+use windows::Win32::UI::Shell::{
+    Folder2, IShellFolderViewDual, IShellWindows, IWebBrowser2, SEE_MASK_INVOKEIDLIST,
+    SEE_MASK_NOASYNC, SHELLEXECUTEINFOW, ShellExecuteExW, ShellExecuteW, ShellWindows,
+};
+use windows::Win32::UI::WindowsAndMessaging::{
+    EnumWindows, GetWindowThreadProcessId, IsWindowVisible, SHOW_WINDOW_CMD, SW_SHOWNORMAL,
+};
+
+use crate::cancel::{CancellationToken, sleep_cancellable};
+use crate::cmdline::quote_argument;
+use crate::envblock::environment_block;
+use crate::log::{log, Logger};
+use crate::error::{OpenShareError, win32_code};
+use crate::registry;
+use crate::unc::normalize_path;
+use crate::wstr::str_to_wcstring;
+
+
+/// The first Windows build (Windows 11 24H2) whose Explorer opens folders launched by other
+/// programs as a new tab of an existing window by default.
+const EXPLORER_TABS_BUILD: u32 = 26100;
+
+/// The environment variable passing the path to the Command Prompt opened by [`open_terminal`].
+const TERMINAL_PATH_VARIABLE: &str = "OPEN_SHARE_TERMINAL_PATH";
+
+/// How long to wait for a properties dialog to appear.
+const PROPERTIES_APPEAR_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// How often to check whether a properties dialog is still open.
+const PROPERTIES_POLL_INTERVAL: Duration = Duration::from_millis(250);
+
+
+fn windows_build_number() -> Option<u32> {
+    registry::read_string(
+        HKEY_LOCAL_MACHINE,
+        "SOFTWARE\\Microsoft\\Windows NT\\CurrentVersion",
+        "CurrentBuildNumber",
+    )?.parse().ok()
+}
+
+/// Whether Explorer on this system opens folders as tabs of an already open window.
+///
+/// This is decided by the Windows build number; users can still disable the behavior in Explorer's
+/// folder options, in which case separate windows are opened.
+pub fn explorer_opens_tabs() -> bool {
+    windows_build_number()
+        .map(|b| b >= EXPLORER_TABS_BUILD)
+        .unwrap_or(false)
+}
+
+
+/// Asks the shell whether one of its Explorer windows shows the given path. Returns `None` if the
+/// windows cannot be enumerated.
+pub(crate) fn explorer_window_shows(path: &str) -> Option<bool> {
+    let initialized = unsafe { CoInitializeEx(None, COINIT_MULTITHREADED) }.is_ok();
+    let shows = (|| {
+        let windows: IShellWindows = unsafe {
+            CoCreateInstance(&ShellWindows, None, CLSCTX_LOCAL_SERVER)
+        }.ok()?;
+        let count = unsafe { windows.Count() }.ok()?;
+        let wanted = normalize_path(path);
+        let shows = (0..count)
+            .filter_map(|i| {
+                // windows that are not Explorer windows, or that close meanwhile, are skipped
+                let browser: IWebBrowser2 = unsafe { windows.Item(&VARIANT::from(i)) }.ok()?.cast().ok()?;
+                let view: IShellFolderViewDual = unsafe { browser.Document() }.ok()?.cast().ok()?;
+                let folder: Folder2 = unsafe { view.Folder() }.ok()?.cast().ok()?;
+                let shown = unsafe { folder.Self_().and_then(|item| item.Path()) }.ok()?;
+                Some(normalize_path(&shown.to_string()))
+            })
+            .any(|shown| shown == wanted);
+        Some(shows)
+    })();
+    if initialized {
+        unsafe { CoUninitialize() };
+    }
+    shows
+}
+
+pub(crate) fn open_path(path: &str, verb: &str, window: SHOW_WINDOW_CMD, logger: Logger<'_>) -> Result<(), OpenShareError> {
+    let path_windows = str_to_wcstring(path);
+    let verb_windows = str_to_wcstring(verb);
+
+    let result = unsafe {
+        ShellExecuteW(
+            HWND(null_mut()),
+            PCWSTR(verb_windows.as_ptr()),
+            PCWSTR(path_windows.as_ptr()),
+            None,
+            None,
+            window,
+        )
+    };
+    let result_int = result.0 as usize;
+    if result_int <= 32 {
+        return Err(OpenShareError::Open { code: result_int.try_into().unwrap() });
+    }
+    log!(logger, Success, Open, "launched!");
+    Ok(())
+}
+
+/// Starts the given program with the given parameters.
+pub(crate) fn open_with(program: &str, parameters: &str, window: SHOW_WINDOW_CMD, logger: Logger<'_>) -> Result<(), OpenShareError> {
+    let program_windows = str_to_wcstring(program);
+    let parameters_windows = str_to_wcstring(parameters);
+    let result = unsafe {
+        ShellExecuteW(
+            HWND(null_mut()),
+            w!("open"),
+            PCWSTR(program_windows.as_ptr()),
+            PCWSTR(parameters_windows.as_ptr()),
+            None,
+            window,
+        )
+    };
+    let result_int = result.0 as usize;
+    if result_int <= 32 {
+        return Err(OpenShareError::Open { code: result_int.try_into().unwrap() });
+    }
+    log!(logger, Success, Open, "launched {}!", program);
+    Ok(())
+}
+
+
+/// Whether Windows Terminal (`wt.exe`) can be found on the `PATH`.
+///
+/// It is usually installed as an app execution alias, a reparse point that cannot be followed, so
+/// only the existence of the entry itself is checked.
+fn windows_terminal_available() -> bool {
+    let path = match env::var_os("PATH") {
+        Some(p) => p,
+        None => return false,
+    };
+    env::split_paths(&path)
+        .any(|dir| dir.join("wt.exe").symlink_metadata().is_ok())
+}
+
+/// Starts the Command Prompt in a new console window, changing to the given path. The path is only
+/// set in the environment of the Command Prompt, not in that of this process.
+fn start_command_prompt(path: &str, window: SHOW_WINDOW_CMD) -> Result<(), OpenShareError> {
+    let environment = environment_block(&[(TERMINAL_PATH_VARIABLE, OsStr::new(path))]);
+    let mut command_line = str_to_wcstring(&format!("cmd.exe /k pushd \"%{}%\"", TERMINAL_PATH_VARIABLE));
+    let startup_info = STARTUPINFOW {
+        cb: size_of::<STARTUPINFOW>().try_into().unwrap(),
+        dwFlags: STARTF_USESHOWWINDOW,
+        wShowWindow: window.0.try_into().unwrap(),
+        ..Default::default()
+    };
+    let mut process_info = PROCESS_INFORMATION::default();
+    unsafe {
+        CreateProcessW(
+            PCWSTR::null(),
+            PWSTR(command_line.as_mut_ptr()),
+            None,
+            None,
+            false,
+            CREATE_NEW_CONSOLE | CREATE_UNICODE_ENVIRONMENT,
+            Some(environment.as_ptr() as *const c_void),
+            PCWSTR::null(),
+            &startup_info,
+            &mut process_info,
+        )
+    }.map_err(|e| OpenShareError::Open { code: win32_code(&e) })?;
+
+    let _ = unsafe { CloseHandle(process_info.hThread) };
+    let _ = unsafe { CloseHandle(process_info.hProcess) };
+    Ok(())
+}
+
+/// Opens a terminal whose working directory is the given path.
+///
+/// Windows Terminal is preferred if it is found on the `PATH`; otherwise, the Command Prompt is
+/// used. As the Command Prompt cannot use a UNC path as its working directory, it changes there
+/// using `pushd`, which maps a temporary drive if necessary. The Command Prompt would expand
+/// variables such as `%USERNAME%` in its command line, so the path is passed to it in the
+/// environment variable [`TERMINAL_PATH_VARIABLE`] instead.
+pub(crate) fn open_terminal(path: &str, window: SHOW_WINDOW_CMD, logger: Logger<'_>) -> Result<(), OpenShareError> {
+    if !Path::new(path).is_dir() {
+        return Err(OpenShareError::Open { code: ERROR_PATH_NOT_FOUND.0 });
+    }
+
+    if !windows_terminal_available() {
+        start_command_prompt(path, window)?;
+        log!(logger, Success, Open, "launched cmd.exe!");
+        return Ok(());
+    }
+
+    // Windows Terminal separates its subcommands with semicolons
+    let program = "wt.exe";
+    let parameters = format!("-d {}", quote_argument(&path.replace(';', "\\;")));
+    let program_windows = str_to_wcstring(program);
+    let parameters_windows = str_to_wcstring(&parameters);
+    let result = unsafe {
+        ShellExecuteW(
+            HWND(null_mut()),
+            w!("open"),
+            PCWSTR(program_windows.as_ptr()),
+            PCWSTR(parameters_windows.as_ptr()),
+            None,
+            window,
+        )
+    };
+    let result_int = result.0 as usize;
+    if result_int <= 32 {
+        return Err(OpenShareError::Open { code: result_int.try_into().unwrap() });
+    }
+    log!(logger, Success, Open, "launched {}!", program);
+    Ok(())
+}
+
+
+unsafe extern "system" fn count_own_visible_window(hwnd: HWND, lparam: LPARAM) -> BOOL {
+    let (process_id, count) = &mut *(lparam.0 as *mut (u32, usize));
+    let mut window_process_id = 0;
+    GetWindowThreadProcessId(hwnd, Some(&mut window_process_id));
+    if window_process_id == *process_id && IsWindowVisible(hwnd).as_bool() {
+        *count += 1;
+    }
+    TRUE
+}
+
+fn own_visible_window_count() -> usize {
+    let mut state = (unsafe { GetCurrentProcessId() }, 0usize);
+    let _ = unsafe {
+        EnumWindows(
+            Some(count_own_visible_window),
+            LPARAM(&mut state as *mut (u32, usize) as isize),
+        )
+    };
+    state.1
+}
+
+/// Shows the properties dialog of the given path.
+///
+/// The dialog is owned by this process and disappears once it exits, so this waits until the
+/// dialog has been closed (or has not appeared within a few seconds). If `timeout` is given, the
+/// wait fails with [`OpenShareError::OpenTimeout`] once it has elapsed since `start`.
+pub(crate) fn open_properties(path: &str, start: Instant, timeout: Option<Duration>, cancel: Option<&CancellationToken>, logger: Logger<'_>) -> Result<(), OpenShareError> {
+    let path_windows = str_to_wcstring(path);
+    let mut info = SHELLEXECUTEINFOW {
+        cbSize: size_of::<SHELLEXECUTEINFOW>().try_into().unwrap(),
+        fMask: SEE_MASK_INVOKEIDLIST | SEE_MASK_NOASYNC,
+        lpVerb: w!("properties"),
+        lpFile: PCWSTR(path_windows.as_ptr()),
+        nShow: SW_SHOWNORMAL.0,
+        ..Default::default()
+    };
+    unsafe { ShellExecuteExW(&mut info) }
+        .map_err(|e| OpenShareError::Open { code: win32_code(&e) })?;
+    log!(logger, Info, Open, "launched properties; waiting for the dialog to be closed...");
+
+    let shown = Instant::now();
+    let mut appeared = false;
+    loop {
+        let open = own_visible_window_count() > 0;
+        if open {
+            appeared = true;
+        } else if appeared || shown.elapsed() >= PROPERTIES_APPEAR_TIMEOUT {
+            break;
+        }
+        if let Some(timeout) = timeout {
+            if start.elapsed() >= timeout {
+                return Err(OpenShareError::OpenTimeout { timeout });
+            }
+        }
+        sleep_cancellable(cancel, PROPERTIES_POLL_INTERVAL)?;
+    }
+    Ok(())
+}
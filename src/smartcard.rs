@@ -0,0 +1,126 @@
+//! Smartcard (certificate) credentials for connecting to shares.
+//!
+//! `WNetAddConnection2W` authenticates using a certificate if the username is a marshalled
+//! certificate credential (as produced by `CredMarshalCredentialW` with `CertCredential`) that
+//! contains the SHA-1 hash of the certificate. Windows then asks for the smartcard's PIN itself.
+//!
+//! Prerequisites:
+//! * the smartcard is inserted,
+//! * its certificate has been propagated into the current user's personal ("MY") certificate store,
+//!   which the Certificate Propagation service does automatically on insertion, and
+//! * the target server's domain accepts the certificate for logon.
+//!
+//! A certificate is considered to reside on a smartcard if its private key is managed by a
+//! provider whose name contains "Smart Card", such as the Microsoft Smart Card Key Storage
+//! Provider.
+
+
+use std::ffi::c_void;
+use std::ptr::null_mut;
+
+use windows::core::{PWSTR, w};
+use windows::Win32::Security::Credentials::{
+    CERT_CREDENTIAL_INFO, CertCredential, CredFree, CredMarshalCredentialW,
+};
+use windows::Win32::Security::Cryptography::{
+    CERT_CONTEXT, CERT_HASH_PROP_ID, CERT_KEY_PROV_INFO_PROP_ID, CRYPT_KEY_PROV_INFO,
+    CertCloseStore, CertEnumCertificatesInStore, CertFreeCertificateContext,
+    CertGetCertificateContextProperty, CertOpenSystemStoreW, HCRYPTPROV_LEGACY,
+};
+
+use crate::error::{OpenShareError, win32_code};
+use crate::wstr::wcstr_to_string;
+
+
+fn cert_property(cert: *const CERT_CONTEXT, property: u32) -> Option<Vec<u64>> {
+    let mut byte_count = 0u32;
+    unsafe { CertGetCertificateContextProperty(cert, property, None, &mut byte_count) }.ok()?;
+
+    // allocate u64s to ensure the buffer is aligned for any structure stored in it
+    let word_count: usize = byte_count.div_ceil(8).try_into().unwrap();
+    let mut buffer = vec![0u64; word_count];
+    unsafe {
+        CertGetCertificateContextProperty(
+            cert,
+            property,
+            Some(buffer.as_mut_ptr() as *mut c_void),
+            &mut byte_count,
+        )
+    }.ok()?;
+    Some(buffer)
+}
+
+fn is_on_smartcard(cert: *const CERT_CONTEXT) -> bool {
+    let buffer = match cert_property(cert, CERT_KEY_PROV_INFO_PROP_ID) {
+        Some(b) => b,
+        None => return false,
+    };
+    let prov_info = unsafe { &*(buffer.as_ptr() as *const CRYPT_KEY_PROV_INFO) };
+    if prov_info.pwszProvName.0.is_null() {
+        return false;
+    }
+    wcstr_to_string(prov_info.pwszProvName.0)
+        .to_lowercase()
+        .contains("smart card")
+}
+
+fn sha1_hash(cert: *const CERT_CONTEXT) -> Option<[u8; 20]> {
+    let buffer = cert_property(cert, CERT_HASH_PROP_ID)?;
+    let bytes = unsafe { std::slice::from_raw_parts(buffer.as_ptr() as *const u8, 20) };
+    bytes.try_into().ok()
+}
+
+/// Finds the SHA-1 hash of the first certificate in the current user's personal store whose
+/// private key resides on a smartcard.
+pub fn find_smartcard_certificate() -> Result<[u8; 20], OpenShareError> {
+    let store = unsafe { CertOpenSystemStoreW(HCRYPTPROV_LEGACY::default(), w!("MY")) }
+        .map_err(|e| OpenShareError::Smartcard { code: win32_code(&e) })?;
+
+    let mut found = None;
+    let mut cert: *mut CERT_CONTEXT = null_mut();
+    loop {
+        // passing the previous context frees it
+        let prev = if cert.is_null() { None } else { Some(cert as *const CERT_CONTEXT) };
+        cert = unsafe { CertEnumCertificatesInStore(store, prev) };
+        if cert.is_null() {
+            break;
+        }
+        if is_on_smartcard(cert) {
+            found = sha1_hash(cert);
+            if found.is_some() {
+                let _ = unsafe { CertFreeCertificateContext(Some(cert)) };
+                break;
+            }
+        }
+    }
+
+    let _ = unsafe { CertCloseStore(store, 0) };
+    found.ok_or(OpenShareError::NoSmartcardCertificate)
+}
+
+/// Marshals a certificate credential for the certificate with the given SHA-1 hash, producing a
+/// string that can be passed as the username to `WNetAddConnection2W`.
+pub fn marshal_certificate_credential(hash: &[u8; 20]) -> Result<String, OpenShareError> {
+    let info = CERT_CREDENTIAL_INFO {
+        cbSize: std::mem::size_of::<CERT_CREDENTIAL_INFO>().try_into().unwrap(),
+        rgbHashOfCert: *hash,
+    };
+    let mut marshalled = PWSTR(null_mut());
+    unsafe {
+        CredMarshalCredentialW(
+            CertCredential,
+            &info as *const CERT_CREDENTIAL_INFO as *const c_void,
+            &mut marshalled,
+        )
+    }.map_err(|e| OpenShareError::Smartcard { code: win32_code(&e) })?;
+
+    let ret = wcstr_to_string(marshalled.0);
+    unsafe { CredFree(marshalled.0 as *const c_void) };
+    Ok(ret)
+}
+
+/// Obtains the marshalled credential of the inserted smartcard's certificate.
+pub(crate) fn smartcard_username() -> Result<String, OpenShareError> {
+    let hash = find_smartcard_certificate()?;
+    marshal_certificate_credential(&hash)
+}
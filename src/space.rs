@@ -0,0 +1,48 @@
+use serde::Serialize;
+use windows::core::PCWSTR;
+use windows::Win32::Storage::FileSystem::GetDiskFreeSpaceExW;
+
+use crate::error::win32_code;
+use crate::wstr::str_to_wcstring;
+
+
+/// The capacity of a share as reported by its network provider.
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Serialize)]
+pub struct ShareSpace {
+    /// The number of bytes available to the current user, taking quotas into account.
+    pub free_bytes: u64,
+
+    /// The total number of bytes available to the current user, taking quotas into account.
+    pub total_bytes: u64,
+}
+
+
+/// Queries the free and total space of the share at the given path, which may be a UNC path or a
+/// drive root.
+///
+/// Returns the Win32 error code if the space cannot be determined, e.g. because the network
+/// provider does not report it.
+pub fn query_space(path: &str) -> Result<ShareSpace, u32> {
+    // UNC paths must end with a backslash
+    let mut directory = path.to_owned();
+    if !directory.ends_with('\\') {
+        directory.push('\\');
+    }
+    let directory_windows = str_to_wcstring(&directory);
+
+    let mut free_bytes = 0u64;
+    let mut total_bytes = 0u64;
+    unsafe {
+        GetDiskFreeSpaceExW(
+            PCWSTR(directory_windows.as_ptr()),
+            Some(&mut free_bytes),
+            Some(&mut total_bytes),
+            None,
+        )
+    }.map_err(|e| win32_code(&e))?;
+
+    Ok(ShareSpace {
+        free_bytes,
+        total_bytes,
+    })
+}
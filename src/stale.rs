@@ -0,0 +1,83 @@
+//! Reviving connections that went stale, e.g. after resuming from hibernation
+//! ([`ShareRequest::heal_stale`]).
+//!
+//! After a resume, a drive mapped to a share often shows as disconnected until it is accessed, and
+//! a connection listed as existing may no longer reach its server. Accessing the root of the share
+//! (as Explorer does when the drive is opened) makes Windows re-establish a connection that is
+//! merely dormant; if that fails, the connection is dead and is removed, so that the share is
+//! connected anew.
+
+
+use std::io::Error as IoError;
+
+use windows::core::PCWSTR;
+use windows::Win32::Foundation::{ERROR_ACCESS_DENIED, GetLastError};
+use windows::Win32::Storage::FileSystem::{GetFileAttributesW, INVALID_FILE_ATTRIBUTES};
+
+use crate::connection::{device_connection, disconnect, DeviceConnection};
+use crate::log::{log, Logger};
+use crate::request::ShareRequest;
+use crate::unc::{normalized_share_root, UncPath};
+use crate::wstr::str_to_wcstring;
+
+
+/// Accesses the path, returning the error code if that fails. Being denied access still shows that
+/// the server answers.
+pub(crate) fn touch(path: &str) -> Result<(), u32> {
+    let path_windows = str_to_wcstring(path);
+    let attributes = unsafe { GetFileAttributesW(PCWSTR(path_windows.as_ptr())) };
+    if attributes != INVALID_FILE_ATTRIBUTES {
+        return Ok(());
+    }
+    match unsafe { GetLastError() } {
+        ERROR_ACCESS_DENIED => Ok(()),
+        error => Err(error.0),
+    }
+}
+
+/// The root of the share that the connection is for: the root of its drive, if any, otherwise the
+/// root of its remote name.
+fn connection_root(remote_name: &str, drive: Option<&str>) -> String {
+    match (drive, UncPath::parse(remote_name)) {
+        (Some(drive), _) => format!("{}\\", drive),
+        (None, Some(unc)) => format!("\\\\{}\\{}\\", unc.server, unc.share),
+        (None, None) => remote_name.to_owned(),
+    }
+}
+
+/// Checks that the connection found for the request (if any) still works, reviving it if it is
+/// dormant. Returns the remote name of the connection if it can be used, or `None` if the share
+/// needs to be connected anew.
+///
+/// If the request maps a drive that is remembered for the share but not currently connected, the
+/// drive is revived as well, although the existing connections do not include it.
+pub(crate) fn heal(request: &ShareRequest, found: Option<String>, logger: Logger<'_>) -> Option<String> {
+    let found = match (found, request.drive()) {
+        (Some(found), _) => found,
+        (None, Some(drive)) => match device_connection(drive) {
+            DeviceConnection::Unavailable(remote)
+                if normalized_share_root(&remote) == normalized_share_root(request.path()) => {
+                log!(logger, Info, Check, "{} is mapped to {} but not connected; accessing it to reconnect...", drive, remote);
+                remote
+            },
+            _ => return None,
+        },
+        (None, None) => return None,
+    };
+
+    let root = connection_root(&found, request.drive());
+    let code = match touch(&root) {
+        Ok(()) => return Some(found),
+        Err(code) => code,
+    };
+    log!(
+        logger, Warning, Check, code = code,
+        "the connection to {} is stale ({}); reconnecting...",
+        found, IoError::from_raw_os_error(code as i32),
+    );
+    let name = request.drive().unwrap_or(&found);
+    if let Err(e) = disconnect(name, false, true, logger) {
+        log!(logger, Warning, Check, code = e.win32_code(), "cannot remove the stale connection: {}", e);
+    }
+    None
+}
@@ -0,0 +1,95 @@
+use std::fmt;
+
+use serde::Serialize;
+
+use crate::space::ShareSpace;
+
+
+/// Where the credentials used to connect to a share came from. Never includes the credentials
+/// themselves.
+///
+/// When neither a password nor a stored credential is available, Windows decides on its own
+/// whether to ask the user; the source is then reported as [`Prompt`](Self::Prompt) if a username
+/// was given and prompting was allowed, and [`CurrentUser`](Self::CurrentUser) without a
+/// username.
+#[derive(Clone, Copy, Debug, Default, Eq, Hash, PartialEq, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum CredentialSource {
+    /// No new connection was made, so the credentials of the existing connection (or logon
+    /// session) were used.
+    ExistingConnection,
+
+    /// The username and password set in the request by the caller, e.g. from the command line.
+    #[default]
+    Explicit,
+
+    /// The username and password set in the request, read by the caller from standard input.
+    Stdin,
+
+    /// A marshalled credential passed as the username (e.g. a certificate reference).
+    MarshalledCredential,
+
+    /// A credential stored in the Credential Manager for the server.
+    CredentialManager,
+
+    /// The credentials of the current logon session.
+    CurrentUser,
+
+    /// The credentials entered by the user in a credential dialog.
+    Prompt,
+
+    /// The certificate on a smartcard.
+    Smartcard,
+
+    /// Guest access (an empty username and password).
+    Guest,
+}
+impl fmt::Display for CredentialSource {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let description = match self {
+            Self::ExistingConnection => "those of the existing connection",
+            Self::Explicit => "given explicitly",
+            Self::Stdin => "read from standard input",
+            Self::MarshalledCredential => "a marshalled credential",
+            Self::CredentialManager => "stored in the Credential Manager",
+            Self::CurrentUser => "those of the current user",
+            Self::Prompt => "entered in a credential dialog",
+            Self::Smartcard => "from the smartcard",
+            Self::Guest => "guest access",
+        };
+        f.write_str(description)
+    }
+}
+
+
+/// The outcome of a successful [`open_share`](crate::open_share) call.
+#[derive(Clone, Debug, Eq, PartialEq, Serialize)]
+pub struct Summary {
+    /// The path exactly as supplied by the caller.
+    pub input: String,
+
+    /// The path in the normalized form used for comparisons.
+    pub normalized: String,
+
+    /// The path as reported by the network provider for the connection, if it could be found.
+    pub canonical: Option<String>,
+
+    /// Whether the share is an administrative share such as `C$`.
+    pub administrative_share: bool,
+
+    /// Whether the share was already connected, making a new connection unnecessary.
+    pub already_connected: bool,
+
+    /// The path that was opened in the shell.
+    pub opened_path: String,
+
+    /// Where the credentials used to connect came from.
+    pub credential_source: CredentialSource,
+
+    /// The free and total space of the share, if it was requested and could be determined.
+    pub space: Option<ShareSpace>,
+
+    /// The SMB dialect negotiated for the connection (e.g. `3.1.1`), if it could be determined.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub smb_dialect: Option<String>,
+}
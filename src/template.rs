@@ -0,0 +1,116 @@
+use std::env;
+
+use windows::Win32::System::SystemInformation::GetLocalTime;
+
+
+/// Replaces `{name}` tokens in the template by the value returned by `lookup` for `name`.
+///
+/// Tokens for which `lookup` returns `None` are kept verbatim, as is an unterminated `{`. `{{` and
+/// `}}` produce literal braces.
+pub fn expand_tokens<F: Fn(&str) -> Option<String>>(template: &str, lookup: F) -> String {
+    let mut ret = String::with_capacity(template.len());
+    let mut rest = template;
+    while let Some(brace_index) = rest.find(['{', '}']) {
+        ret.push_str(&rest[..brace_index]);
+        let from_brace = &rest[brace_index..];
+
+        if let Some(after) = from_brace.strip_prefix("{{") {
+            ret.push('{');
+            rest = after;
+            continue;
+        }
+        if let Some(after) = from_brace.strip_prefix("}}").or_else(|| from_brace.strip_prefix('}')) {
+            ret.push('}');
+            rest = after;
+            continue;
+        }
+
+        match from_brace.find('}') {
+            Some(close_index) => {
+                let name = &from_brace[1..close_index];
+                match lookup(name) {
+                    Some(value) => ret.push_str(&value),
+                    None => ret.push_str(&from_brace[..close_index+1]),
+                }
+                rest = &from_brace[close_index+1..];
+            },
+            None => {
+                ret.push_str(from_brace);
+                rest = "";
+            },
+        }
+    }
+    ret.push_str(rest);
+    ret
+}
+
+fn local_date() -> String {
+    let now = unsafe { GetLocalTime() };
+    format!("{:04}-{:02}-{:02}", now.wYear, now.wMonth, now.wDay)
+}
+
+/// Expands the tokens supported in connection comments: `{user}` (the current user), `{host}`
+/// (this computer's name) and `{date}` (today's local date as YYYY-MM-DD).
+pub fn expand_comment(template: &str) -> String {
+    expand_comment_with(template, |variable| env::var(variable).ok(), local_date)
+}
+
+/// Like [`expand_comment`], but reads environment variables and today's date using the given
+/// functions.
+fn expand_comment_with<V: Fn(&str) -> Option<String>, D: Fn() -> String>(template: &str, variable: V, date: D) -> String {
+    expand_tokens(template, |name| match name {
+        "user" => variable("USERNAME"),
+        "host" => variable("COMPUTERNAME"),
+        "date" => Some(date()),
+        _ => None,
+    })
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn stub_variable(name: &str) -> Option<String> {
+        match name {
+            "USERNAME" => Some("alice".to_owned()),
+            "COMPUTERNAME" => Some("WORKSTATION".to_owned()),
+            _ => None,
+        }
+    }
+
+    fn expand(template: &str) -> String {
+        expand_comment_with(template, stub_variable, || "2024-02-29".to_owned())
+    }
+
+    #[test]
+    fn known_tokens_are_replaced() {
+        assert_eq!(expand("{user} on {host}, {date}"), "alice on WORKSTATION, 2024-02-29");
+    }
+
+    #[test]
+    fn unset_variables_keep_their_token() {
+        let expanded = expand_comment_with("by {user}", |_| None, String::new);
+        assert_eq!(expanded, "by {user}");
+    }
+
+    #[test]
+    fn unknown_tokens_are_kept_verbatim() {
+        assert_eq!(expand("{user} {shoe} {}"), "alice {shoe} {}");
+    }
+
+    #[test]
+    fn doubled_braces_are_literal() {
+        assert_eq!(expand("{{user}} {{{user}}}"), "{user} {alice}");
+    }
+
+    #[test]
+    fn lone_closing_brace_is_literal() {
+        assert_eq!(expand("a } b {user}"), "a } b alice");
+    }
+
+    #[test]
+    fn unterminated_opening_brace_is_kept() {
+        assert_eq!(expand("{user} {host"), "alice {host");
+    }
+}
@@ -0,0 +1,92 @@
+//! Desktop notifications about the outcome (`--toast`).
+//!
+//! The notification is posted as a balloon of a temporary notification area icon using
+//! `Shell_NotifyIconW`; Windows 10 and later show such balloons as toast notifications. This avoids
+//! the WinRT toast API, which requires the program to be registered with an AppUserModelID and a
+//! Start menu shortcut before its notifications are shown.
+//!
+//! The icon belongs to a hidden window of this process, and the notification vanishes once the icon
+//! is removed, so open-share waits for [`TOAST_DISPLAY_TIME`] before it exits.
+
+
+use std::mem::size_of;
+use std::thread::sleep;
+use std::time::Duration;
+
+use windows::core::w;
+use windows::Win32::Foundation::{HINSTANCE, HWND};
+use windows::Win32::UI::Shell::{
+    NIF_ICON, NIF_INFO, NIF_TIP, NIIF_ERROR, NIIF_INFO, NIM_ADD, NIM_DELETE, NOTIFYICONDATAW,
+    Shell_NotifyIconW,
+};
+use windows::Win32::UI::WindowsAndMessaging::{
+    CreateWindowExW, DestroyWindow, IDI_ERROR, IDI_INFORMATION, LoadIconW, WINDOW_EX_STYLE,
+    WINDOW_STYLE,
+};
+
+use crate::report::progress;
+
+
+/// How long the notification is shown before its icon is removed.
+const TOAST_DISPLAY_TIME: Duration = Duration::from_secs(5);
+
+
+/// Copies as much of `text` as fits into a fixed-size, NUL-terminated string field.
+fn fill_wide(field: &mut [u16], text: &str) {
+    let capacity = field.len() - 1;
+    let mut length = 0;
+    for (slot, unit) in field.iter_mut().zip(text.encode_utf16().take(capacity)) {
+        *slot = unit;
+        length += 1;
+    }
+    field[length] = 0;
+}
+
+/// Shows a notification with the given message, marked as an error if `success` is false.
+///
+/// Failures to show the notification are reported to stderr; they do not affect the outcome.
+pub(crate) fn show(success: bool, message: &str) {
+    let window = unsafe {
+        CreateWindowExW(
+            WINDOW_EX_STYLE(0),
+            w!("STATIC"),
+            w!("open-share"),
+            WINDOW_STYLE(0),
+            0, 0, 0, 0,
+            HWND::default(),
+            None,
+            HINSTANCE::default(),
+            None,
+        )
+    };
+    let window = match window {
+        Ok(w) => w,
+        Err(e) => {
+            progress(format!("cannot show a notification: {}", e));
+            return;
+        },
+    };
+
+    let icon = unsafe { LoadIconW(None, if success { IDI_INFORMATION } else { IDI_ERROR }) }
+        .unwrap_or_default();
+    let mut data = NOTIFYICONDATAW {
+        cbSize: size_of::<NOTIFYICONDATAW>().try_into().unwrap(),
+        hWnd: window,
+        uID: 1,
+        uFlags: NIF_ICON | NIF_TIP | NIF_INFO,
+        hIcon: icon,
+        dwInfoFlags: if success { NIIF_INFO } else { NIIF_ERROR },
+        ..Default::default()
+    };
+    fill_wide(&mut data.szTip, "open-share");
+    fill_wide(&mut data.szInfoTitle, "open-share");
+    fill_wide(&mut data.szInfo, message);
+
+    if unsafe { Shell_NotifyIconW(NIM_ADD, &data) }.as_bool() {
+        sleep(TOAST_DISPLAY_TIME);
+        let _ = unsafe { Shell_NotifyIconW(NIM_DELETE, &data) };
+    } else {
+        progress("cannot show a notification: the notification area is unavailable");
+    }
+    let _ = unsafe { DestroyWindow(window) };
+}
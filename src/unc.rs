@@ -0,0 +1,171 @@
+use std::net::IpAddr;
+
+
+/// Normalizes a path for comparison: forward slashes become backslashes, trailing backslashes
+/// are removed (unless that would leave only the `\\` prefix) and the result is lowercased.
+pub fn normalize_path(path: &str) -> String {
+    let mut normalized = path.replace('/', "\\").to_lowercase();
+    while normalized.len() > 2 && normalized.ends_with('\\') {
+        normalized.pop();
+    }
+    normalized
+}
+
+/// Reduces a path to its share root (`\\server\share`) in [normalized](normalize_path) form, so
+/// that a path inside a share compares equal to the share itself.
+///
+/// Paths that are not UNC paths are merely normalized.
+pub(crate) fn normalized_share_root(path: &str) -> String {
+    let normalized = normalize_path(path);
+    match UncPath::parse(&normalized) {
+        Some(unc) => format!("\\\\{}\\{}", unc.server, unc.share),
+        None => normalized,
+    }
+}
+
+/// The server of a path of the form `\\server` (optionally with a trailing backslash), which
+/// names the server itself rather than one of its shares; opening it in Explorer lists its shares.
+///
+/// Returns `None` for other paths, including those with a share component. Forward slashes are not
+/// accepted as separators.
+pub fn server_root(path: &str) -> Option<&str> {
+    let server = path.strip_prefix("\\\\")?;
+    let server = server.strip_suffix('\\').unwrap_or(server);
+    if server.is_empty() || server.contains('\\') {
+        return None;
+    }
+    Some(server)
+}
+
+
+/// The components of a UNC path of the form `\\server\share\rest`.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct UncPath<'a> {
+    /// The server name, e.g. `server` in `\\server\share`.
+    pub server: &'a str,
+
+    /// The share name, e.g. `share` in `\\server\share`.
+    pub share: &'a str,
+
+    /// Everything after the share name without the leading backslash; empty for the share root.
+    pub rest: &'a str,
+}
+impl<'a> UncPath<'a> {
+    /// Splits a UNC path into its components.
+    ///
+    /// Returns `None` if the path does not begin with `\\server\share`. Forward slashes are not
+    /// accepted as separators.
+    pub fn parse(path: &'a str) -> Option<Self> {
+        let without_prefix = path.strip_prefix("\\\\")?;
+        let (server, after_server) = without_prefix.split_once('\\')?;
+        if server.is_empty() {
+            return None;
+        }
+        let (share, rest) = match after_server.split_once('\\') {
+            Some((share, rest)) => (share, rest),
+            None => (after_server, ""),
+        };
+        if share.is_empty() {
+            return None;
+        }
+        Some(Self {
+            server,
+            share,
+            rest,
+        })
+    }
+
+    /// Whether the share is an administrative share such as `C$` or `ADMIN$`.
+    ///
+    /// Strictly speaking, any share whose name ends in `$` is merely hidden from browsing, but
+    /// those created by Windows itself are only accessible to administrators of the target.
+    pub fn is_administrative_share(&self) -> bool {
+        self.share.ends_with('$')
+    }
+
+    /// The path reaching the same folder through the administrative share `C$`, on the assumption
+    /// that the share's folder is named like the share and resides in `directory` on drive C (or
+    /// its root if `None`): `\\server\data\x` becomes `\\server\C$\directory\data\x`.
+    ///
+    /// Returns `None` for an administrative share.
+    pub fn administrative_path(&self, directory: Option<&str>) -> Option<String> {
+        if self.is_administrative_share() {
+            return None;
+        }
+        let mut path = format!("\\\\{}\\C$", self.server);
+        for component in [directory.unwrap_or(""), self.share, self.rest] {
+            let component = component.trim_matches('\\');
+            if !component.is_empty() {
+                path.push('\\');
+                path.push_str(component);
+            }
+        }
+        Some(path)
+    }
+
+    /// Whether the server is specified as an IP address rather than a name.
+    ///
+    /// Both bare addresses and bracketed IPv6 addresses are recognized.
+    pub fn server_is_ip_address(&self) -> bool {
+        let unbracketed = self.server
+            .strip_prefix('[')
+            .and_then(|s| s.strip_suffix(']'))
+            .unwrap_or(self.server);
+        unbracketed.parse::<IpAddr>().is_ok()
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn server_root_has_no_share() {
+        assert_eq!(server_root("\\\\server"), Some("server"));
+        assert_eq!(server_root("\\\\server\\"), Some("server"));
+        assert_eq!(server_root("\\\\server\\share"), None);
+        assert_eq!(server_root("\\\\"), None);
+        assert_eq!(server_root("\\\\\\"), None);
+        assert_eq!(UncPath::parse("\\\\server"), None);
+    }
+
+    #[test]
+    fn share_root_of_root() {
+        assert_eq!(normalized_share_root("\\\\Server\\Share"), "\\\\server\\share");
+        assert_eq!(normalized_share_root("\\\\Server\\Share\\"), "\\\\server\\share");
+    }
+
+    #[test]
+    fn subpath_matches_connected_root() {
+        let connected = "\\\\server\\share";
+        let requested = "\\\\SERVER\\share\\projects\\2024";
+        assert_eq!(normalized_share_root(requested), normalized_share_root(connected));
+    }
+
+    #[test]
+    fn root_matches_connected_subpath() {
+        let connected = "\\\\server\\share\\projects";
+        let requested = "//server/Share/";
+        assert_eq!(normalized_share_root(requested), normalized_share_root(connected));
+    }
+
+    #[test]
+    fn administrative_path_prepends_directory() {
+        let unc = UncPath::parse("\\\\server\\data\\reports").unwrap();
+        assert_eq!(unc.administrative_path(None).as_deref(), Some("\\\\server\\C$\\data\\reports"));
+        assert_eq!(
+            unc.administrative_path(Some("\\Shares\\")).as_deref(),
+            Some("\\\\server\\C$\\Shares\\data\\reports"),
+        );
+        assert_eq!(UncPath::parse("\\\\server\\C$").unwrap().administrative_path(None), None);
+    }
+
+    #[test]
+    fn other_share_does_not_match() {
+        assert_ne!(
+            normalized_share_root("\\\\server\\share\\projects"),
+            normalized_share_root("\\\\server\\shared\\projects"),
+        );
+    }
+}
@@ -0,0 +1,64 @@
+//! Verifying that a connected share accepts writes ([`ShareRequest::require_write`]).
+//!
+//! Connecting only shows that the share can be accessed; a read-only export connects just as well.
+//! To find out whether writing works, an empty hidden file with a unique name is created in the
+//! root of the share and deleted again.
+//!
+//! [`ShareRequest::require_write`]: crate::ShareRequest::require_write
+
+
+use std::process;
+use std::sync::atomic::{AtomicU32, Ordering};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use windows::core::PCWSTR;
+use windows::Win32::Foundation::{CloseHandle, GENERIC_WRITE};
+use windows::Win32::Storage::FileSystem::{
+    CREATE_NEW, CreateFileW, DeleteFileW, FILE_ATTRIBUTE_HIDDEN, FILE_ATTRIBUTE_TEMPORARY,
+    FILE_SHARE_MODE,
+};
+
+use crate::error::win32_code;
+use crate::log::{log, Logger};
+use crate::wstr::str_to_wcstring;
+
+
+/// Distinguishes the test files of several shares checked by the same process.
+static COUNTER: AtomicU32 = AtomicU32::new(0);
+
+
+/// A name for the test file that no other process checking the same share uses at the same time.
+fn test_file_name() -> String {
+    let nanos = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.subsec_nanos())
+        .unwrap_or(0);
+    format!(
+        ".open-share-write-test-{}-{:08x}-{}.tmp",
+        process::id(), nanos, COUNTER.fetch_add(1, Ordering::Relaxed),
+    )
+}
+
+/// Creates a file in the given directory and deletes it again. Returns the Win32 error code if the
+/// file cannot be created; failing to delete it is only logged.
+pub(crate) fn check_write_access(directory: &str, logger: Logger<'_>) -> Result<(), u32> {
+    let path = format!("{}\\{}", directory.trim_end_matches('\\'), test_file_name());
+    let path_windows = str_to_wcstring(&path);
+    let handle = unsafe {
+        CreateFileW(
+            PCWSTR(path_windows.as_ptr()),
+            GENERIC_WRITE.0,
+            FILE_SHARE_MODE(0),
+            None,
+            CREATE_NEW,
+            FILE_ATTRIBUTE_HIDDEN | FILE_ATTRIBUTE_TEMPORARY,
+            None,
+        )
+    }.map_err(|e| win32_code(&e))?;
+
+    let _ = unsafe { CloseHandle(handle) };
+    if let Err(e) = unsafe { DeleteFileW(PCWSTR(path_windows.as_ptr())) } {
+        log!(logger, Warning, Open, code = win32_code(&e), "cannot delete the test file {}: {}", path, e);
+    }
+    Ok(())
+}
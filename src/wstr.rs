@@ -0,0 +1,61 @@
+pub(crate) fn wcstr_to_string(ptr: *const u16) -> String {
+    let mut moving_ptr = ptr;
+    let mut utf16_buf = Vec::new();
+    while unsafe { *moving_ptr } != 0x0000 {
+        utf16_buf.push(unsafe { *moving_ptr });
+        moving_ptr = moving_ptr.wrapping_add(1);
+    }
+    String::from_utf16(&utf16_buf).unwrap()
+}
+
+pub(crate) fn str_to_wcstring(s: &str) -> Vec<u16> {
+    let mut ret = Vec::with_capacity(s.len() + 1);
+    for w in s.encode_utf16() {
+        ret.push(w);
+    }
+    ret.push(0);
+    ret
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn round_trip(s: &str) -> String {
+        let wide = str_to_wcstring(s);
+        wcstr_to_string(wide.as_ptr())
+    }
+
+    #[test]
+    fn round_trip_empty() {
+        assert_eq!(round_trip(""), "");
+    }
+
+    #[test]
+    fn round_trip_ascii() {
+        assert_eq!(round_trip("\\\\server\\share"), "\\\\server\\share");
+    }
+
+    #[test]
+    fn round_trip_non_ascii() {
+        assert_eq!(round_trip("caf\u{E9}"), "caf\u{E9}");
+        assert_eq!(round_trip("\u{6F22}\u{5B57}"), "\u{6F22}\u{5B57}");
+    }
+
+    #[test]
+    fn round_trip_combining_characters() {
+        // e followed by a combining acute accent must not be composed into é
+        assert_eq!(round_trip("cafe\u{301}"), "cafe\u{301}");
+    }
+
+    #[test]
+    fn round_trip_surrogate_pairs() {
+        assert_eq!(round_trip("\u{1F4C1} share"), "\u{1F4C1} share");
+    }
+
+    #[test]
+    fn str_to_wcstring_terminates() {
+        assert_eq!(str_to_wcstring("ab"), vec![u16::from(b'a'), u16::from(b'b'), 0]);
+    }
+}